@@ -0,0 +1,65 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::{fs, path::Path};
+use super_snoofer::{cache::snapshot::snapshot_path, CacheFormat, CommandCache, HistoryTracker};
+
+/// Build a cache resembling a long-lived install: a large PATH and a full history
+fn large_cache() -> CommandCache {
+    let mut cache = CommandCache::default();
+    for i in 0..5000 {
+        cache.insert(&format!("command-{i}"));
+    }
+    for i in 0..1000 {
+        cache.record_correction_in_line(
+            &format!("comand-{i}"),
+            &format!("command-{i}"),
+            &format!("comand-{i} --flag value-{i}"),
+        );
+    }
+    // Fresh aliases keep the shell config parse out of the measured loads
+    cache.set_shell_aliases(
+        (0..200)
+            .map(|i| (format!("alias-{i}"), format!("command-{i} --flag")))
+            .collect(),
+    );
+    cache
+}
+
+/// Save the cache in a format under a directory, returning the file it was written to
+fn save_as(cache: &mut CommandCache, dir: &Path, format: CacheFormat) -> std::path::PathBuf {
+    let path = dir.join(format!("cache.{}", format.extension()));
+    cache.set_cache_path(path.clone());
+    cache.settings_mut().cache_format = format;
+    cache.save().unwrap();
+    path
+}
+
+fn bench_cache_load(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cache = large_cache();
+    let json_path = save_as(&mut cache, dir.path(), CacheFormat::Json);
+    let binary_path = save_as(&mut cache, dir.path(), CacheFormat::Binary);
+
+    let mut group = c.benchmark_group("cache_load");
+
+    // Saving writes a command snapshot, so these take the snapshot fast path
+    group.bench_function("json", |b| {
+        b.iter(|| black_box(CommandCache::load_from_path(&json_path).unwrap()));
+    });
+
+    group.bench_function("binary", |b| {
+        b.iter(|| black_box(CommandCache::load_from_path(&binary_path).unwrap()));
+    });
+
+    // Without a snapshot the command and alias sets are parsed from the cache file
+    fs::remove_file(snapshot_path(&json_path)).unwrap();
+    group.bench_function("json_without_snapshot", |b| {
+        b.iter(|| black_box(CommandCache::load_from_path(&json_path).unwrap()));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_cache_load);
+criterion_main!(benches);
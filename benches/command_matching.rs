@@ -1,7 +1,7 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use super_snoofer::CommandCache;
+use super_snoofer::{CommandCache, CommandPatterns};
 
 fn setup_test_cache() -> CommandCache {
     let mut cache = CommandCache::default();
@@ -40,7 +40,7 @@ fn bench_command_matching(c: &mut Criterion) {
     // Benchmark learned corrections
     group.bench_function("learned_correction", |b| {
         let mut cache = setup_test_cache();
-        cache.learn_correction("gti", "git").unwrap();
+        cache.learn_correction("gti", "git", None).unwrap();
         b.iter(|| cache.find_similar(black_box("gti")));
     });
     
@@ -57,5 +57,21 @@ fn bench_command_matching(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_command_matching);
+fn bench_command_patterns(c: &mut Criterion) {
+    let mut group = c.benchmark_group("command_patterns");
+
+    // Building the built-in patterns from scratch, as every construction used to
+    group.bench_function("build_builtin", |b| {
+        b.iter(|| black_box(CommandPatterns::build_builtin()));
+    });
+
+    // Copying the built-in patterns constructed once per process
+    group.bench_function("new_cached", |b| {
+        b.iter(|| black_box(CommandPatterns::new()));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_command_matching, bench_command_patterns);
 criterion_main!(benches); 
\ No newline at end of file
@@ -0,0 +1,127 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use super_snoofer::utils::{
+    calculate_similarity, find_closest_match, levenshtein_distance, trigram_similarity,
+};
+
+/// Build a large candidate set resembling a well-populated PATH
+fn large_candidate_set() -> Vec<String> {
+    let prefixes = [
+        "git", "cargo", "docker", "kube", "python", "node", "npm", "rust", "system", "journal",
+    ];
+    let suffixes = ["", "-ctl", "-config", "-helper", "-daemon", "d", "-cli", "-run"];
+
+    let mut candidates = Vec::new();
+    for prefix in &prefixes {
+        for suffix in &suffixes {
+            for i in 0..5 {
+                candidates.push(format!("{prefix}{suffix}{i}"));
+            }
+        }
+    }
+    candidates
+}
+
+fn bench_calculate_similarity(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calculate_similarity");
+
+    // Short commands hit the specialized short-string branch
+    group.bench_function("short_command", |b| {
+        b.iter(|| calculate_similarity(black_box("gti"), black_box("git")));
+    });
+
+    // Typical command names use normalized Levenshtein
+    group.bench_function("medium_command", |b| {
+        b.iter(|| calculate_similarity(black_box("systemctll"), black_box("systemctl")));
+    });
+
+    // Full command lines as typed into the shell
+    group.bench_function("long_command_line", |b| {
+        b.iter(|| {
+            calculate_similarity(
+                black_box("docker run --rm -it -v /home/user/projcet:/app ubuntu:latest bash"),
+                black_box("docker run --rm -it -v /home/user/project:/app ubuntu:latest bash"),
+            )
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_levenshtein_distance(c: &mut Criterion) {
+    let mut group = c.benchmark_group("levenshtein_distance");
+
+    group.bench_function("short_command", |b| {
+        b.iter(|| levenshtein_distance(black_box("gti"), black_box("git")));
+    });
+
+    group.bench_function("long_command_line", |b| {
+        b.iter(|| {
+            levenshtein_distance(
+                black_box("cargo build --relese --target x86_64-unknown-linux-gnu"),
+                black_box("cargo build --release --target x86_64-unknown-linux-gnu"),
+            )
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_long_line_similarity(c: &mut Criterion) {
+    let typed = "kubectl --namespace production logs --follow --tail 100 deployment/api-gatewya";
+    let learned = "kubectl --namespace production logs --follow --tail 100 deployment/api-gateway";
+    let reordered = "kubectl logs --follow --tail 100 --namespace production deployment/api-gatewya";
+
+    let mut group = c.benchmark_group("long_line_similarity");
+
+    group.bench_function("levenshtein", |b| {
+        b.iter(|| calculate_similarity(black_box(typed), black_box(learned)));
+    });
+
+    group.bench_function("trigram", |b| {
+        b.iter(|| trigram_similarity(black_box(typed), black_box(learned)));
+    });
+
+    // Reordered flags cost Levenshtein many edits but keep most trigrams
+    group.bench_function("levenshtein_reordered", |b| {
+        b.iter(|| calculate_similarity(black_box(reordered), black_box(learned)));
+    });
+
+    group.bench_function("trigram_reordered", |b| {
+        b.iter(|| trigram_similarity(black_box(reordered), black_box(learned)));
+    });
+
+    group.finish();
+}
+
+fn bench_find_closest_match(c: &mut Criterion) {
+    let small_set = ["git", "cargo", "docker", "ls", "grep", "find", "curl", "ssh"];
+    let large_set = large_candidate_set();
+    let large_refs: Vec<&String> = large_set.iter().collect();
+
+    let mut group = c.benchmark_group("find_closest_match");
+
+    group.bench_function("small_set", |b| {
+        b.iter(|| find_closest_match(black_box("dokcer"), black_box(&small_set), 0.4));
+    });
+
+    group.bench_function("large_set", |b| {
+        b.iter(|| find_closest_match(black_box("dokcer-ctl3"), black_box(&large_refs), 0.4));
+    });
+
+    group.bench_function("large_set_no_match", |b| {
+        b.iter(|| find_closest_match(black_box("xyzabc"), black_box(&large_refs), 0.4));
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_calculate_similarity,
+    bench_levenshtein_distance,
+    bench_long_line_similarity,
+    bench_find_closest_match
+);
+criterion_main!(benches);
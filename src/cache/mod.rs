@@ -1,23 +1,62 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 use crate::{
-    command::CommandPatterns,
-    history::{CommandHistoryEntry, HistoryManager, HistoryTracker},
-    shell::aliases::parse_shell_aliases,
-    utils::{find_closest_match, get_path_commands},
+    command::{CommandPatterns, HelpText},
+    git::BranchCache,
+    history::{CommandHistoryEntry, HistoryManager, HistoryTracker, SourceStats, alias_log},
+    settings::{CacheFormat, MatchSource, Settings},
+    shell::{
+        aliases::parse_shell_aliases,
+        arguments::{replace_words, split_words},
+    },
+    utils::{
+        calculate_similarity_with, combined_score, find_closest_match_with, get_path_commands_with,
+        length_penalty, FREQUENCY_WEIGHT,
+    },
 };
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
+use log::debug;
+use serde::{Deserialize, Deserializer, Serialize, de::IgnoredAny};
 use std::{
+    cell::Cell,
     collections::{HashMap, HashSet},
     fs::{self, File},
+    io::{BufReader, BufWriter},
     path::{Path, PathBuf},
-    time::SystemTime,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime},
 };
 
+pub mod snapshot;
+pub mod trace;
+
+pub use snapshot::CommandSnapshot;
+pub use trace::TraceNode;
+
 /// Default file name for the cache
 pub const CACHE_FILE: &str = "super_snoofer_cache.json";
 
+/// Typo learned, and forgotten again, by [`CommandCache::self_test`]
+const SELF_TEST_TYPO: &str = "__super_snoofer_self_test__";
+
+/// Correction learned for [`SELF_TEST_TYPO`]
+const SELF_TEST_CORRECTION: &str = "super_snoofer";
+
+/// A named step rewriting a command line, returning `None` when it changes nothing
+type LineStage<'a> = (&'static str, &'a dyn Fn(&str) -> Option<String>);
+
+/// Step of [`CommandCache::fix_command_line`] reusing a line learned as a whole
+const LEARNED_LINE_STEP: &str = "learned full-line correction";
+
+/// Step of [`CommandCache::fix_command_line`] correcting the command, arguments and flags
+const COMMAND_STEP: &str = "command, arguments and flags";
+
+/// Minimum similarity for a command line to take the correction learned for another line
+pub const LINE_MATCH_THRESHOLD: f64 = 0.8;
+
 /// Threshold for similarity checks
 pub const SIMILARITY_THRESHOLD: f64 = 0.4;
 
@@ -27,10 +66,28 @@ pub const CACHE_LIFETIME_SECS: u64 = 86400;
 /// Cache lifetime for aliases in seconds (24 hours)
 pub const ALIAS_CACHE_LIFETIME_SECS: u64 = 86400;
 
+/// What a cache or memory reset would remove, for previewing it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClearSummary {
+    /// Cached PATH and man page commands
+    pub commands: usize,
+    /// Learned typo corrections
+    pub learned_corrections: usize,
+    /// Command history entries
+    pub history_entries: usize,
+    /// Categories of learned corrections
+    pub categories: usize,
+    /// Typos with declined suggestions
+    pub declined_suggestions: usize,
+    /// Learned command patterns
+    pub learned_patterns: usize,
+}
+
 /// Main cache structure for the Super Snoofer application
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CommandCache {
     /// Set of available commands in the PATH
+    #[serde(deserialize_with = "deserialize_unless_in_snapshot")]
     commands: HashSet<String>,
 
     /// Map of learned corrections: typo -> correct command
@@ -45,20 +102,321 @@ pub struct CommandCache {
     cache_path: Option<PathBuf>,
 
     /// Shell aliases - key is the alias name, value is the command it expands to
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_unless_in_snapshot")]
     shell_aliases: HashMap<String, String>,
 
-    /// Last time shell aliases were updated
-    #[serde(default = "SystemTime::now")]
+    /// Last time shell aliases were successfully updated
+    ///
+    /// Starts out stale so a fresh cache parses aliases right away, and is only
+    /// advanced by a successful parse so a failed one is retried on the next run.
+    #[serde(default = "stale_alias_update")]
     alias_last_update: SystemTime,
 
     /// History management
     #[serde(default)]
     history_manager: HistoryManager,
 
-    /// Command patterns for well-known commands (not serialized)
-    #[serde(skip)]
+    /// Command patterns for well-known and learned commands
+    ///
+    /// Built-ins are re-derived on load and merged with the persisted learned patterns.
+    #[serde(default)]
     command_patterns: CommandPatterns,
+
+    /// User-configurable settings
+    #[serde(default)]
+    settings: Settings,
+
+    /// Local git branches per repository root, refreshed when the repository's refs change
+    ///
+    /// Only kept for the life of the process, e.g. the daemon's.
+    #[serde(skip)]
+    git_branches: Mutex<HashMap<PathBuf, BranchCache>>,
+
+    /// Commands documented by installed man pages, scanned with the PATH while the
+    /// `man_page_commands` setting is on
+    #[serde(default)]
+    man_commands: HashSet<String>,
+
+    /// When each suggestion was last declined, per typo: typo -> suggestion -> time
+    #[serde(default)]
+    declined_suggestions: HashMap<String, HashMap<String, SystemTime>>,
+
+    /// User-defined subcommands per tool, like cargo aliases and kubectl plugins,
+    /// discovered along with the PATH commands
+    #[serde(default)]
+    tool_subcommands: HashMap<String, Vec<String>>,
+
+    /// Categories of learned corrections, like `work` or `personal`: typo -> category
+    #[serde(default)]
+    correction_categories: HashMap<String, String>,
+
+    /// Subcommands and flags of tools without a built-in pattern, discovered from their
+    /// `--help` output while the `help_discovery` setting is on (empty when it had none)
+    #[serde(default)]
+    help_discoveries: HashMap<String, HelpText>,
+
+    /// Minimum similarity for fuzzy matches, set by `--threshold` and saved with the cache
+    #[serde(default = "default_similarity_threshold")]
+    similarity_threshold: f64,
+
+    /// When the file this cache was loaded from was last modified, to notice saves by
+    /// other processes (not serialized)
+    #[serde(skip)]
+    loaded_modified: Option<SystemTime>,
+}
+
+thread_local! {
+    /// Whether the cache being parsed on this thread takes its command and alias sets
+    /// from a fresh snapshot, so the copies in the cache file can be skipped
+    static SETS_IN_SNAPSHOT: Cell<bool> = const { Cell::new(false) };
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Private directory standing in for the user's fallback directory in the tests
+    /// running on this thread
+    static TEST_FALLBACK_DIR: tempfile::TempDir = tempfile::tempdir().unwrap();
+}
+
+/// Deserialize a command or alias set, or skip over it when a snapshot provides it
+fn deserialize_unless_in_snapshot<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    if SETS_IN_SNAPSHOT.get() {
+        IgnoredAny::deserialize(deserializer)?;
+        Ok(T::default())
+    } else {
+        T::deserialize(deserializer)
+    }
+}
+
+/// Explain why a directory could not be created
+///
+/// Distinguishes a dangling symlink and a file in the way, which `create_dir_all`
+/// reports opaquely, from a plain permission problem.
+fn describe_create_dir_error(dir: &Path, error: &std::io::Error) -> String {
+    for ancestor in dir.ancestors() {
+        let Ok(metadata) = fs::symlink_metadata(ancestor) else {
+            continue;
+        };
+
+        if metadata.file_type().is_symlink() && fs::metadata(ancestor).is_err() {
+            let target = fs::read_link(ancestor).unwrap_or_default();
+            return format!(
+                "symlink target missing ({} -> {})",
+                ancestor.display(),
+                target.display()
+            );
+        }
+        if !ancestor.is_dir() {
+            return format!("parent is not a directory ({})", ancestor.display());
+        }
+        break;
+    }
+
+    if error.kind() == std::io::ErrorKind::PermissionDenied {
+        "permission denied".to_string()
+    } else {
+        error.to_string()
+    }
+}
+
+/// Correct the misspelled directories of every `~/` path argument in a command line
+///
+/// See [`crate::command::correct_home_path`]; a line without such arguments, or without
+/// a home directory to check them against, is returned unchanged.
+fn correct_home_paths(command_line: &str) -> String {
+    match dirs::home_dir() {
+        Some(home) => correct_home_paths_in(command_line, &home),
+        None => command_line.to_string(),
+    }
+}
+
+/// Correct the `~/` path arguments of a command line against `home`
+///
+/// Only the corrected words are replaced, so quoting and spacing stay as typed. Quoted
+/// words are left alone: the shell doesn't expand `~` in them, and a quoted command is
+/// run elsewhere, where the home directory isn't ours to check.
+fn correct_home_paths_in(command_line: &str, home: &Path) -> String {
+    if !command_line.contains("~/") {
+        return command_line.to_string();
+    }
+    let replacements = split_words(command_line)
+        .into_iter()
+        .filter(|word| !word.quoted)
+        .filter_map(|word| {
+            crate::command::correct_home_path(word.text, home).map(|fixed| (word.range, fixed))
+        });
+    replace_words(command_line, replacements)
+}
+
+/// Correct the misspelled target of a `cd` command line against the current directory
+/// and `$CDPATH`
+///
+/// See [`crate::command::correct_cd_target`]; returns `None` for other commands and
+/// targets with nothing to correct.
+fn correct_cd_line(command_line: &str) -> Option<String> {
+    correct_cd_line_in(command_line, &crate::command::cd_search_dirs())
+}
+
+/// Correct the misspelled target of a `cd` command line against `search_dirs`
+fn correct_cd_line_in(command_line: &str, search_dirs: &[PathBuf]) -> Option<String> {
+    let mut tokens = command_line.split_whitespace();
+    if tokens.next() != Some("cd") {
+        return None;
+    }
+    let target = tokens.next()?;
+    if tokens.next().is_some() {
+        return None;
+    }
+    let corrected = crate::command::correct_cd_target(target, search_dirs)?;
+    Some(format!("cd {corrected}"))
+}
+
+/// Apply a learned full-line correction to a similar typed line
+///
+/// The correction is reused whole only when the typed line has the learned typo's
+/// arguments (the words after the command and subcommand). Otherwise the corrected
+/// command and subcommand are put in front of the typed arguments, as long as the
+/// learned correction left its own arguments alone. Returns `None` when it didn't.
+fn reuse_learned_line(command_line: &str, typo: &str, correction: &str) -> Option<String> {
+    const HEAD: usize = 2;
+    let typo_words: Vec<&str> = typo.split_whitespace().collect();
+    let typed_words: Vec<&str> = command_line.split_whitespace().collect();
+    if typed_words.get(HEAD..) == typo_words.get(HEAD..) {
+        return Some(correction.to_string());
+    }
+
+    let correction_words: Vec<&str> = correction.split_whitespace().collect();
+    if correction_words.len() != typo_words.len()
+        || correction_words.get(HEAD..) != typo_words.get(HEAD..)
+    {
+        return None;
+    }
+    let head = correction_words[..HEAD.min(correction_words.len())].join(" ");
+    let mut arguments = command_line.trim();
+    for _ in 0..HEAD {
+        arguments = arguments
+            .trim_start_matches(|c: char| !c.is_whitespace())
+            .trim_start();
+    }
+    Some(if arguments.is_empty() {
+        head
+    } else {
+        format!("{head} {arguments}")
+    })
+}
+
+/// Gets where a cache is saved when its own path can't be written: its file name in the
+/// user's private fallback directory
+fn fallback_path(cache_path: &Path) -> PathBuf {
+    let file_name = cache_path
+        .file_name()
+        .unwrap_or_else(|| std::ffi::OsStr::new(CACHE_FILE));
+    fallback_dir().join(file_name)
+}
+
+/// Gets the directory caches fall back to: the user's runtime directory, or a
+/// `super_snoofer-<uid>` directory in the temp directory
+///
+/// Never the temp directory itself, where any other user could plant a cache.
+fn fallback_dir() -> PathBuf {
+    #[cfg(test)]
+    {
+        TEST_FALLBACK_DIR.with(|dir| dir.path().to_path_buf())
+    }
+    #[cfg(not(test))]
+    {
+        dirs::runtime_dir().unwrap_or_else(|| {
+            #[cfg(unix)]
+            let name = format!("super_snoofer-{}", current_uid());
+            #[cfg(not(unix))]
+            let name = "super_snoofer".to_string();
+            std::env::temp_dir().join(name)
+        })
+    }
+}
+
+/// Gets the fallback of a cache saved at `path`, if it was saved after the cache itself
+///
+/// A fallback that isn't a file of the current user's is ignored, see
+/// [`is_owned_by_current_user`].
+fn newer_fallback(path: &Path) -> Option<PathBuf> {
+    let fallback = fallback_path(path);
+    (is_newer(&fallback, path) && is_owned_by_current_user(&fallback)).then_some(fallback)
+}
+
+/// Gets the effective user id of this process
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: `geteuid` has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+/// Checks that a path is the current user's own, and not a symlink to somewhere else
+#[cfg(unix)]
+fn is_owned_by_current_user(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path)
+        .is_ok_and(|metadata| !metadata.file_type().is_symlink() && metadata.uid() == current_uid())
+}
+
+/// Checks that a path exists and isn't a symlink; there are no other users to check for
+#[cfg(not(unix))]
+fn is_owned_by_current_user(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.file_type().is_symlink())
+}
+
+/// Create a directory only the current user can access, if it doesn't exist yet
+///
+/// # Errors
+/// Returns an error if the directory cannot be created, or exists but belongs to
+/// another user
+fn create_private_dir(dir: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(dir)
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+
+    if !is_owned_by_current_user(dir) {
+        return Err(anyhow::anyhow!(
+            "Refusing to use {}: it belongs to another user",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Checks whether a file exists and was modified after another one, or the other one
+/// doesn't exist
+fn is_newer(path: &Path, than: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    modified(path).is_some_and(|newer| modified(than).is_none_or(|older| newer > older))
+}
+
+/// Number of cache saves started by this process, to name their partial files
+static PARTIAL_SAVES: AtomicUsize = AtomicUsize::new(0);
+
+/// Gets a path with a suffix appended to its file name, e.g. `cache.json.tmp`
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Gets the similarity threshold of a cache without an override
+fn default_similarity_threshold() -> f64 {
+    SIMILARITY_THRESHOLD
+}
+
+/// Gets an alias update timestamp old enough that aliases are re-parsed on next use
+fn stale_alias_update() -> SystemTime {
+    SystemTime::UNIX_EPOCH
 }
 
 impl Default for CommandCache {
@@ -69,9 +427,18 @@ impl Default for CommandCache {
             last_update: SystemTime::now(),
             cache_path: None,
             shell_aliases: HashMap::new(),
-            alias_last_update: SystemTime::now(),
+            alias_last_update: stale_alias_update(),
             history_manager: HistoryManager::default(),
             command_patterns: CommandPatterns::new(),
+            settings: Settings::default(),
+            git_branches: Mutex::new(HashMap::new()),
+            man_commands: HashSet::new(),
+            declined_suggestions: HashMap::new(),
+            tool_subcommands: HashMap::new(),
+            correction_categories: HashMap::new(),
+            help_discoveries: HashMap::new(),
+            similarity_threshold: SIMILARITY_THRESHOLD,
+            loaded_modified: None,
         }
     }
 }
@@ -93,23 +460,79 @@ impl CommandCache {
     ///
     /// This function will return an error if:
     /// - The cache file exists but cannot be opened
-    /// - The cache file exists but cannot be parsed as valid JSON
+    /// - The cache file exists but cannot be parsed
     /// - There is an error updating the cache if needed
     pub fn load() -> Result<Self> {
+        match Self::default_path() {
+            Some(cache_path) => Self::load_from_path(&cache_path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Get the path [`Self::load`] reads the cache from
+    ///
+    /// Returns `None` when there is neither a cache nor a home directory to keep it in.
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
         // Try to find the cache file in the standard locations
-        let cache_dir = dirs::cache_dir().or_else(dirs::home_dir);
+        let dir = dirs::cache_dir().or_else(dirs::home_dir)?;
+        let json_path = if dir.ends_with(".cache") {
+            dir.join(CACHE_FILE)
+        } else {
+            dir.join(format!(".{CACHE_FILE}"))
+        };
 
-        if let Some(dir) = cache_dir {
-            let cache_path = if dir.ends_with(".cache") {
-                dir.join(CACHE_FILE)
-            } else {
-                dir.join(format!(".{CACHE_FILE}"))
-            };
+        // The cache is in whichever format was saved last; a migration removes the other
+        let binary_path = json_path.with_extension(CacheFormat::Binary.extension());
+        let cache_path = [binary_path, json_path.clone()]
+            .into_iter()
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((modified, path))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map_or(json_path, |(_, path)| path);
+        Some(cache_path)
+    }
 
-            return Self::load_from_path(&cache_path);
+    /// Check that learned corrections persist in the cache at `path`
+    ///
+    /// A throwaway correction is learned and saved, read back from a fresh load, then
+    /// forgotten and checked to be gone again. The command patterns it touched are
+    /// restored, leaving the user's data as it was.
+    ///
+    /// # Errors
+    /// Returns an error naming the step that failed: loading, saving, or finding the
+    /// correction (or its absence) after reloading
+    pub fn self_test(path: &Path) -> Result<()> {
+        let mut cache = Self::load_from_path(path).context("Failed to load the cache")?;
+        let patterns = cache.command_patterns.clone();
+        cache
+            .learn_correction(SELF_TEST_TYPO, SELF_TEST_CORRECTION, None)
+            .context("Failed to save a learned correction")?;
+
+        let mut reloaded = Self::load_from_path(path).context("Failed to reload the cache")?;
+        // Nothing reached the file in this case, so there is nothing to undo
+        if reloaded.find_similar(SELF_TEST_TYPO).as_deref() != Some(SELF_TEST_CORRECTION) {
+            return Err(anyhow::anyhow!(
+                "The learned correction was missing after reloading {} (it may have been saved elsewhere)",
+                path.display()
+            ));
         }
 
-        Ok(Self::default())
+        reloaded.unlearn_correction(SELF_TEST_TYPO);
+        reloaded.command_patterns = patterns;
+        reloaded
+            .save()
+            .context("Failed to save the cache after forgetting the correction")?;
+        let reloaded = Self::load_from_path(path).context("Failed to reload the cache")?;
+        if reloaded.has_correction(SELF_TEST_TYPO) {
+            return Err(anyhow::anyhow!(
+                "The forgotten correction was still in {} after reloading",
+                path.display()
+            ));
+        }
+        Ok(())
     }
 
     /// Load the command cache from a specific path
@@ -122,26 +545,79 @@ impl CommandCache {
     ///
     /// A `Result` containing the loaded cache or a new one if no cache exists at the path
     ///
+    /// A path with a `.cbor` extension is read as binary, anything else as JSON. A cache
+    /// file that can't be parsed, e.g. one cut short by a crash in an older version, is
+    /// moved aside to `<path>.corrupt` and a new cache is started in its place.
+    ///
+    /// When [`Self::save`] last had to fall back to the user's private fallback directory,
+    /// the cache is read from there, and saved to `path` again once it can be.
+    ///
     /// # Errors
     ///
     /// This function will return an error if:
     /// - The cache file exists but cannot be opened
-    /// - The cache file exists but cannot be parsed as valid JSON
+    /// - The cache file exists but can neither be parsed nor moved aside
     /// - There is an error updating the cache if needed
     pub fn load_from_path(path: &Path) -> Result<Self> {
-        let cache = if path.exists() {
-            // Try to load the existing cache
-            let file = File::open(path)
-                .with_context(|| format!("Failed to open cache file at {}", path.display()))?;
+        // A cache that couldn't be saved to its path was saved to the fallback directory
+        let fallback = newer_fallback(path);
+        let source = fallback.as_deref().unwrap_or(path);
 
-            let mut cache: CommandCache = serde_json::from_reader(file)
-                .with_context(|| format!("Failed to parse cache file at {}", path.display()))?;
+        let mut cache = if source.exists() {
+            // Try to load the existing cache
+            let file = File::open(source)
+                .with_context(|| format!("Failed to open cache file at {}", source.display()))?;
+
+            // Take the command and alias sets from the snapshot when it's fresh, so only
+            // the rest of the cache file has to be parsed
+            let snapshot = CommandSnapshot::open(source);
+            SETS_IN_SNAPSHOT.set(snapshot.is_some());
+            let reader = BufReader::new(file);
+            let parsed: Result<CommandCache> = match CacheFormat::from_path(source) {
+                CacheFormat::Json => serde_json::from_reader(reader).map_err(Into::into),
+                CacheFormat::Binary => ciborium::from_reader(reader).map_err(Into::into),
+            };
+            SETS_IN_SNAPSHOT.set(false);
+            let mut cache = match parsed {
+                Ok(cache) => cache,
+                Err(error) => {
+                    let error = error
+                        .context(format!("Failed to parse cache file at {}", source.display()));
+                    let corrupt = path_with_suffix(source, ".corrupt");
+                    if let Err(rename_error) = fs::rename(source, &corrupt) {
+                        return Err(error.context(format!(
+                            "Failed to move the cache aside to {}: {rename_error}",
+                            corrupt.display()
+                        )));
+                    }
+                    snapshot::remove_snapshot(source);
+                    eprintln!(
+                        "🐺 warning: {error:#}; starting a new cache and keeping the old one at {}",
+                        corrupt.display()
+                    );
+                    return Self::load_from_path(path);
+                }
+            };
+            if let Some(snapshot) = snapshot {
+                cache.commands = snapshot.to_command_set();
+                cache.shell_aliases = snapshot.to_alias_map();
+            }
 
             // Set the cache path
             cache.cache_path = Some(path.to_path_buf());
-
-            // Initialize command patterns
-            cache.command_patterns = CommandPatterns::new();
+            cache.loaded_modified = Self::saved_modified(path);
+            cache
+                .history_manager
+                .merge_alias_uses(alias_log::read(&alias_log::log_path(path)));
+
+            // Re-derive the built-in command patterns and merge the learned ones back in
+            let learned_patterns =
+                std::mem::replace(&mut cache.command_patterns, CommandPatterns::new());
+            cache.command_patterns.merge_learned(learned_patterns);
+            cache.register_learned_runner_packages();
+            cache.register_tool_subcommands();
+            cache.register_help_discoveries();
+            cache.evict_learned_patterns();
 
             // If the cache is too old, clear it
             if cache.should_clear_cache() {
@@ -169,9 +645,43 @@ impl CommandCache {
             cache
         };
 
+        cache.apply_history_settings();
         Ok(cache)
     }
 
+    /// Get when the cache saved at `path` was last modified, including a save to its
+    /// fallback directory
+    fn saved_modified(path: &Path) -> Option<SystemTime> {
+        [path, fallback_path(path).as_path()]
+            .into_iter()
+            .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .max()
+    }
+
+    /// Reload the cache if its file was saved since it was loaded, e.g. by another process
+    ///
+    /// Long-lived processes like the daemon call this so corrections learned and settings
+    /// changed elsewhere are used, rather than answering from a stale snapshot. Its own
+    /// saves also count as changes, which costs one extra reload.
+    ///
+    /// Returns whether the cache was reloaded.
+    ///
+    /// # Errors
+    /// Returns an error if the changed cache cannot be loaded
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        let Some(path) = self.cache_path.clone() else {
+            return Ok(false);
+        };
+        if Self::saved_modified(&path) == self.loaded_modified {
+            return Ok(false);
+        }
+
+        let threshold = self.similarity_threshold;
+        *self = Self::load_from_path(&path)?;
+        self.similarity_threshold = threshold;
+        Ok(true)
+    }
+
     /// Check if the cache should be cleared due to age
     fn should_clear_cache(&self) -> bool {
         if let Ok(duration) = SystemTime::now().duration_since(self.last_update) {
@@ -199,14 +709,33 @@ impl CommandCache {
     /// Clear the command cache (retains learned corrections)
     pub fn clear_cache(&mut self) {
         self.commands.clear();
+        self.man_commands.clear();
+        self.help_discoveries.clear();
         self.last_update = SystemTime::now();
     }
 
+    /// Report what [`Self::clear_cache`], or [`Self::clear_memory`] if `memory` is set,
+    /// would remove, without removing anything
+    #[must_use]
+    pub fn clear_summary(&self, memory: bool) -> ClearSummary {
+        ClearSummary {
+            commands: self.commands.len() + self.man_commands.len(),
+            learned_corrections: if memory { self.learned_corrections.len() } else { 0 },
+            history_entries: if memory { self.history_manager.get_history_size() } else { 0 },
+            categories: if memory { self.correction_categories.len() } else { 0 },
+            declined_suggestions: if memory { self.declined_suggestions.len() } else { 0 },
+            learned_patterns: if memory { self.command_patterns.learned_count() } else { 0 },
+        }
+    }
+
     /// Clear both the command cache and learned corrections
     pub fn clear_memory(&mut self) {
         self.clear_cache();
         self.learned_corrections.clear();
+        self.correction_categories.clear();
+        self.declined_suggestions.clear();
         self.history_manager.clear_history();
+        self.command_patterns = CommandPatterns::new();
     }
 
     /// Check if the cache has a correction for the given typo
@@ -221,28 +750,115 @@ impl CommandCache {
     ///
     /// A `Result` indicating success or failure
     ///
+    /// A [`CommandSnapshot`] of the command and alias sets is written next to the cache
+    /// file, for the next load to read instead of parsing them.
+    ///
+    /// The cache is written in the format of the `cache_format` setting. When that differs
+    /// from the format it was loaded from, the file is migrated: the new one is written
+    /// next to the old one, which is then removed.
+    ///
+    /// If the cache file cannot be written where it belongs, it is saved to the user's
+    /// private fallback directory instead with a warning, so learned corrections aren't
+    /// lost.
+    ///
     /// # Errors
     ///
     /// This function will return an error if:
-    /// - The parent directory for the cache file cannot be created
-    /// - The cache file cannot be created
-    /// - The cache cannot be serialized to JSON
+    /// - The cache can be written neither to its path nor to the fallback directory
+    /// - The cache cannot be serialized
     pub fn save(&self) -> Result<()> {
-        if let Some(cache_path) = &self.cache_path {
-            // Ensure the parent directory exists
-            if let Some(parent) = cache_path.parent() {
-                fs::create_dir_all(parent)
-                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        let Some(loaded_path) = &self.cache_path else {
+            return Ok(());
+        };
+        let cache_path = &loaded_path.with_extension(self.settings.cache_format.extension());
+
+        let fallback = fallback_path(cache_path);
+        let Err(error) = self.write_to(cache_path) else {
+            // The cache is back where it belongs, so an older fallback must not shadow it
+            if fallback != *cache_path {
+                let _ = fs::remove_file(&fallback);
+                snapshot::remove_snapshot(&fallback);
             }
+            if cache_path != loaded_path && loaded_path.exists() {
+                fs::remove_file(loaded_path).with_context(|| {
+                    format!("Failed to remove migrated cache at {}", loaded_path.display())
+                })?;
+                snapshot::remove_snapshot(loaded_path);
+            }
+            return Ok(());
+        };
+
+        if fallback == *cache_path
+            || create_private_dir(&fallback_dir())
+                .and_then(|()| self.write_to(&fallback))
+                .is_err()
+        {
+            return Err(error);
+        }
+
+        eprintln!(
+            "🐺 warning: {error:#}; saved the cache to {} instead",
+            fallback.display()
+        );
+        Ok(())
+    }
 
-            let file = File::create(cache_path).with_context(|| {
-                format!("Failed to create cache file at {}", cache_path.display())
+    /// Write the cache to a path in the format of its extension, creating its parent
+    /// directory if needed
+    ///
+    /// The cache is written to a temporary file next to it first, synced and renamed into
+    /// place, so a crash mid-write leaves the previous cache file intact. The file is
+    /// created readable by the current user only.
+    fn write_to(&self, cache_path: &Path) -> Result<()> {
+        if let Some(parent) = cache_path.parent() {
+            fs::create_dir_all(parent).map_err(|error| {
+                anyhow::anyhow!(
+                    "Failed to create directory {}: {}",
+                    parent.display(),
+                    describe_create_dir_error(parent, &error)
+                )
             })?;
+        }
 
-            serde_json::to_writer(file, self)
-                .with_context(|| format!("Failed to write cache to {}", cache_path.display()))?;
+        // Unique per process and save, so concurrent saves never write the same file
+        let partial = path_with_suffix(
+            cache_path,
+            &format!(
+                ".{}.{}.tmp",
+                std::process::id(),
+                PARTIAL_SAVES.fetch_add(1, Ordering::Relaxed)
+            ),
+        );
+        // Readable by the current user only, as the cache holds their command history
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options
+            .open(&partial)
+            .with_context(|| format!("Failed to create cache file at {}", partial.display()))?;
+
+        let mut writer = BufWriter::new(file);
+        let written: Result<()> = match CacheFormat::from_path(cache_path) {
+            CacheFormat::Json => serde_json::to_writer(&mut writer, self).map_err(Into::into),
+            CacheFormat::Binary => ciborium::into_writer(self, &mut writer).map_err(Into::into),
+        };
+        // The data must be on disk before the rename makes it the cache
+        let written = written
+            .and_then(|()| writer.into_inner().map_err(|error| error.into_error().into()))
+            .and_then(|file| file.sync_all().map_err(Into::into))
+            .and_then(|()| fs::rename(&partial, cache_path).map_err(Into::into))
+            .with_context(|| format!("Failed to write cache to {}", cache_path.display()));
+        if written.is_err() {
+            let _ = fs::remove_file(&partial);
+            return written;
         }
 
+        // The snapshot only speeds up loading; the cache file alone is complete
+        if let Err(e) = snapshot::write_snapshot(cache_path, &self.commands, &self.shell_aliases) {
+            debug!("{e:#}");
+            snapshot::remove_snapshot(cache_path);
+        }
         Ok(())
     }
 
@@ -252,6 +868,7 @@ impl CommandCache {
     ///
     /// * `typo` - The mistyped command
     /// * `correct_command` - The correct command
+    /// * `category` - The category to tag the correction with; `None` clears any earlier one
     ///
     /// # Returns
     ///
@@ -261,7 +878,12 @@ impl CommandCache {
     ///
     /// This function will return an error if:
     /// - The cache cannot be saved to disk
-    pub fn learn_correction(&mut self, typo: &str, correct_command: &str) -> Result<()> {
+    pub fn learn_correction(
+        &mut self,
+        typo: &str,
+        correct_command: &str,
+        category: Option<&str>,
+    ) -> Result<()> {
         // If the correction contains spaces, it likely contains arguments
         // In this case, we'll store the full correction for the typo
         let correction = if correct_command.contains(' ') {
@@ -271,26 +893,359 @@ impl CommandCache {
             correct_command.to_string()
         };
 
+        self.command_patterns.learn_from_command(&correction);
+        self.evict_learned_patterns();
         self.learned_corrections
             .insert(typo.to_string(), correction);
+        self.set_correction_category(typo, category);
+        self.register_learned_runner_packages();
         self.save()
     }
 
-    /// Find a similar command for a given command
+    /// Learn corrections in bulk, as (typo, correction, category), saving once at the end
+    ///
+    /// Returns the number of corrections that were new or changed.
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be saved
+    pub fn import_corrections<'a>(
+        &mut self,
+        corrections: impl IntoIterator<Item = (&'a str, &'a str, Option<&'a str>)>,
+    ) -> Result<usize> {
+        let mut changed = 0;
+        for (typo, correction, category) in corrections {
+            if self.learned_corrections.get(typo).map(String::as_str) != Some(correction) {
+                self.command_patterns.learn_from_command(correction);
+                self.learned_corrections
+                    .insert(typo.to_string(), correction.to_string());
+                changed += 1;
+            }
+            if category.is_some() {
+                self.set_correction_category(typo, category);
+            }
+        }
+        self.evict_learned_patterns();
+        self.register_learned_runner_packages();
+        self.save()?;
+        Ok(changed)
+    }
+
+    /// Forget a learned correction
+    ///
+    /// Returns `true` if a correction was learned for the typo.
+    pub fn unlearn_correction(&mut self, typo: &str) -> bool {
+        self.correction_categories.remove(typo);
+        self.learned_corrections.remove(typo).is_some()
+    }
+
+    /// Tag a learned correction with a category, or remove its category with `None`
+    ///
+    /// Returns `false` if no correction is learned for the typo.
+    pub fn set_correction_category(&mut self, typo: &str, category: Option<&str>) -> bool {
+        if !self.learned_corrections.contains_key(typo) {
+            return false;
+        }
+        match category {
+            Some(category) => {
+                self.correction_categories
+                    .insert(typo.to_string(), category.to_string());
+            }
+            None => {
+                self.correction_categories.remove(typo);
+            }
+        }
+        true
+    }
+
+    /// Get the learned corrections as (typo, correction, category), sorted by typo
+    ///
+    /// With a category, only the corrections tagged with it are returned.
     #[must_use]
-    pub fn find_similar(&self, command: &str) -> Option<String> {
-        // First, check learned corrections - this should take priority
-        if let Some(correction) = self.learned_corrections.get(command) {
-            return Some(correction.clone());
+    pub fn learned_corrections(&self, category: Option<&str>) -> Vec<(&str, &str, Option<&str>)> {
+        let mut corrections: Vec<_> = self
+            .learned_corrections
+            .iter()
+            .map(|(typo, correction)| {
+                let tag = self.correction_categories.get(typo).map(String::as_str);
+                (typo.as_str(), correction.as_str(), tag)
+            })
+            .filter(|(_, _, tag)| category.is_none() || *tag == category)
+            .collect();
+        corrections.sort_unstable();
+        corrections
+    }
+
+    /// Forget every learned correction tagged with a category
+    ///
+    /// Returns the number of corrections forgotten.
+    pub fn unlearn_category(&mut self, category: &str) -> usize {
+        let typos: Vec<String> = self
+            .correction_categories
+            .iter()
+            .filter(|(_, tag)| *tag == category)
+            .map(|(typo, _)| typo.clone())
+            .collect();
+        typos
+            .iter()
+            .filter(|typo| self.unlearn_correction(typo))
+            .count()
+    }
+
+    /// Hold back a declined suggestion for a typo during the `decline_cooldown_secs` setting
+    ///
+    /// Declines whose cooldown has already run out are dropped along the way.
+    pub fn decline_suggestion(&mut self, typo: &str, suggestion: &str) {
+        let cooldown = Duration::from_secs(self.settings.decline_cooldown_secs);
+        if cooldown.is_zero() {
+            return;
+        }
+
+        let now = SystemTime::now();
+        for declined in self.declined_suggestions.values_mut() {
+            declined.retain(|_, when| now.duration_since(*when).is_ok_and(|age| age < cooldown));
+        }
+        self.declined_suggestions.retain(|_, declined| !declined.is_empty());
+
+        self.declined_suggestions
+            .entry(typo.to_string())
+            .or_default()
+            .insert(suggestion.to_string(), now);
+    }
+
+    /// Check whether a suggestion for a typo was declined within the cooldown
+    fn is_cooling_down(&self, typo: &str, suggestion: &str) -> bool {
+        let Some(declined_at) = self
+            .declined_suggestions
+            .get(typo)
+            .and_then(|declined| declined.get(suggestion))
+        else {
+            return false;
+        };
+        let cooldown = Duration::from_secs(self.settings.decline_cooldown_secs);
+        SystemTime::now()
+            .duration_since(*declined_at)
+            .is_ok_and(|age| age < cooldown)
+    }
+
+    /// Hide a typo's corrections from the frequency statistics without deleting history
+    ///
+    /// Corrections recorded for the typo later are hidden too. Returns `false` if it was
+    /// already muted.
+    pub fn mute_typo(&mut self, typo: &str) -> bool {
+        self.history_manager.mute_typo(typo)
+    }
+
+    /// Get the aliases not used since `since` (or never used when `None`), sorted by name
+    ///
+    /// Each alias comes with its target and when it was last used, if ever.
+    #[must_use]
+    pub fn unused_aliases(
+        &self,
+        since: Option<SystemTime>,
+    ) -> Vec<(String, String, Option<SystemTime>)> {
+        let mut unused: Vec<_> = self
+            .shell_aliases
+            .iter()
+            .map(|(name, target)| {
+                (
+                    name.clone(),
+                    target.clone(),
+                    self.history_manager.alias_last_used(name),
+                )
+            })
+            .filter(|(_, _, last_used)| match (last_used, since) {
+                (None, _) => true,
+                (Some(last_used), Some(since)) => *last_used < since,
+                (Some(_), None) => false,
+            })
+            .collect();
+        unused.sort_by(|a, b| a.0.cmp(&b.0));
+        unused
+    }
+
+    /// Learn a command's subcommand and flags from a command line the user ran
+    pub fn learn_from_command(&mut self, command_line: &str) {
+        self.command_patterns.learn_from_command(command_line);
+        self.evict_learned_patterns();
+    }
+
+    /// Drop the least recently used learned command patterns beyond the
+    /// `max_learned_patterns` setting
+    ///
+    /// A dropped command's `--help` discovery is kept, so the tool is never run for its
+    /// help again.
+    fn evict_learned_patterns(&mut self) {
+        let max = self.settings.max_learned_patterns;
+        if max == 0 {
+            return;
+        }
+        self.command_patterns.evict_least_recently_used(max);
+    }
+
+    /// Teach the command patterns about the discovered user-defined subcommands, replacing
+    /// the ones registered before
+    fn register_tool_subcommands(&mut self) {
+        self.command_patterns.clear_registered_subcommands();
+        for (tool, subcommands) in &self.tool_subcommands {
+            for subcommand in subcommands {
+                self.command_patterns.register_subcommand(tool, subcommand);
+            }
+        }
+    }
+
+    /// Teach the command patterns about the subcommands discovered from `--help` output
+    fn register_help_discoveries(&mut self) {
+        for (command, help) in &self.help_discoveries {
+            self.command_patterns.register_help(command, help);
         }
+    }
 
-        // Second, check if we have this exact command
-        if self.commands.contains(command) || self.shell_aliases.contains_key(command) {
-            return Some(command.to_string());
+    /// Discover the subcommands of the tool a command line runs from its `--help` output,
+    /// if the `help_discovery` setting is on
+    ///
+    /// Only tools in the PATH without a built-in pattern are asked, once each, and only
+    /// when the line passes them a subcommand their pattern doesn't know yet. Returns
+    /// whether a tool was asked, so the cache can be saved.
+    pub fn discover_help(&mut self, command_line: &str) -> bool {
+        let mut tokens = command_line.split_whitespace();
+        let (Some(command), Some(subcommand)) = (tokens.next(), tokens.next()) else {
+            return false;
+        };
+        let known = self
+            .command_patterns
+            .get_args_for_command(command)
+            .is_some_and(|mut args| args.any(|arg| arg == subcommand));
+        if !self.settings.help_discovery
+            || known
+            || subcommand.starts_with('-')
+            || !self.commands.contains(command)
+            || self.help_discoveries.contains_key(command)
+            || CommandPatterns::is_builtin(command)
+            || !crate::command::help_discovery::may_run_for_help(command)
+        {
+            return false;
         }
 
-        // Last resort: find the closest match using fuzzy matching
-        self.get_closest_match(command, SIMILARITY_THRESHOLD)
+        let help = crate::command::help_discovery::discover_help(command);
+        self.command_patterns.register_help(command, &help);
+        self.command_patterns.touch(command);
+        self.help_discoveries.insert(command.to_string(), help);
+        self.evict_learned_patterns();
+        true
+    }
+
+    /// Teach the command patterns about packages from learned package-runner corrections
+    fn register_learned_runner_packages(&mut self) {
+        for correction in self.learned_corrections.values() {
+            if let Some(package) = crate::command::runner_package(correction) {
+                self.command_patterns.register_runner_package(package);
+            }
+        }
+    }
+
+    /// Find a similar command for a given command
+    ///
+    /// Sources are consulted in the order set by the `match_precedence` setting, which
+    /// defaults to exact alias > exact command > learned correction > fuzzy alias >
+    /// fuzzy command.
+    #[must_use]
+    pub fn find_similar(&self, command: &str) -> Option<String> {
+        self.find_by_precedence(command, |source| self.fuzzy_match(command, source))
+    }
+
+    /// Get the source that [`Self::find_similar`] resolves a command from
+    ///
+    /// e.g. [`MatchSource::Learned`] for a typo the user confirmed a correction for before.
+    #[must_use]
+    pub fn match_source(&self, command: &str) -> Option<MatchSource> {
+        self.find_with_source(command, |source| self.fuzzy_match(command, source))
+            .map(|(source, _)| source)
+    }
+
+    /// Consult each correction source in the configured [`crate::settings::MatchPrecedence`] order
+    ///
+    /// Exact sources also accept names that only differ by case (e.g. caps lock).
+    /// `fuzzy` resolves the fuzzy sources, so callers can bias them (e.g. by frequency).
+    fn find_by_precedence(
+        &self,
+        command: &str,
+        fuzzy: impl Fn(MatchSource) -> Option<String>,
+    ) -> Option<String> {
+        self.find_with_source(command, fuzzy)
+            .map(|(_, correction)| correction)
+    }
+
+    /// Like [`Self::find_by_precedence`], also returning the source the match came from
+    fn find_with_source(
+        &self,
+        command: &str,
+        fuzzy: impl Fn(MatchSource) -> Option<String>,
+    ) -> Option<(MatchSource, String)> {
+        let lowered = command.to_lowercase();
+
+        self.settings.match_precedence.0.iter().find_map(|&source| {
+            let correction = match source {
+                MatchSource::ExactAlias => [command, lowered.as_str()]
+                    .into_iter()
+                    .find(|name| self.shell_aliases.contains_key(*name))
+                    .map(str::to_string),
+                MatchSource::ExactCommand => [command, lowered.as_str()]
+                    .into_iter()
+                    .find(|name| self.commands.contains(*name))
+                    .map(str::to_string),
+                MatchSource::Learned => self.learned_corrections.get(command).cloned(),
+                MatchSource::FuzzyAlias | MatchSource::FuzzyCommand => {
+                    if self.is_too_short_to_correct(command) {
+                        None
+                    } else {
+                        fuzzy(source)
+                    }
+                }
+            };
+            correction
+                .filter(|correction| !self.is_cooling_down(command, correction))
+                .map(|correction| (source, correction))
+        })
+    }
+
+    /// Check whether a command is shorter than the `min_correction_length` setting
+    fn is_too_short_to_correct(&self, command: &str) -> bool {
+        command.chars().count() < self.settings.min_correction_length
+    }
+
+    /// Find the closest alias or command (depending on `source`) within the similarity threshold
+    fn fuzzy_match(&self, command: &str, source: MatchSource) -> Option<String> {
+        let candidates: Vec<&String> = match source {
+            MatchSource::FuzzyAlias => self.shell_aliases.keys().collect(),
+            MatchSource::FuzzyCommand => self.commands.iter().chain(&self.man_commands).collect(),
+            _ => return None,
+        };
+        // A recently declined candidate makes way for the next best one
+        let candidates: Vec<&String> = candidates
+            .into_iter()
+            .filter(|candidate| !self.is_cooling_down(command, candidate))
+            .collect();
+
+        find_closest_match_with(
+            command,
+            &candidates,
+            self.similarity_threshold,
+            self.settings.similarity_algorithm,
+        )
+        .map(|s| (*s).clone())
+    }
+
+    /// Get the minimum similarity for fuzzy matches
+    #[must_use]
+    pub fn similarity_threshold(&self) -> f64 {
+        self.similarity_threshold
+    }
+
+    /// Set the minimum similarity for fuzzy matches, clamped to 0.0..=1.0
+    ///
+    /// Lower thresholds match looser typos; the threshold is saved with the cache.
+    pub fn set_similarity_threshold(&mut self, threshold: f64) {
+        self.similarity_threshold = threshold.clamp(0.0, 1.0);
     }
 
     /// Insert a command into the cache
@@ -312,6 +1267,7 @@ impl CommandCache {
     /// - There is an error saving the updated cache to disk
     pub fn update(&mut self) -> Result<()> {
         self.update_path_commands();
+        self.update_man_commands();
 
         if self.shell_aliases.is_empty() || self.should_update_aliases() {
             self.update_aliases();
@@ -321,21 +1277,156 @@ impl CommandCache {
         self.save()
     }
 
+    /// Rescan the PATH and shell aliases like [`Self::update`], but merge them into the
+    /// saved cache rather than overwriting it
+    ///
+    /// Unlike [`Self::update`], corrections and settings saved by other processes since
+    /// this cache was loaded are kept, so long-lived processes like the daemon can't lose
+    /// them. A cache without a path is simply updated.
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be loaded or saved
+    pub fn update_saved(&mut self) -> Result<()> {
+        let Some(path) = self.cache_path.clone() else {
+            return self.update();
+        };
+
+        let threshold = self.similarity_threshold;
+        *self = Self::refresh_saved_at(&path)?;
+        self.similarity_threshold = threshold;
+        Ok(())
+    }
+
     /// Update commands from PATH
     fn update_path_commands(&mut self) {
         // Get commands from PATH
-        let path_commands = get_path_commands();
+        let path_commands = get_path_commands_with(self.settings.symlink_target_commands);
+
+        // Tools' own subcommands may come from plugins in the PATH, so rediscover them too
+        self.tool_subcommands = crate::command::discover_tool_subcommands(&path_commands);
+        self.register_tool_subcommands();
 
         // Update the command set
         self.commands = path_commands;
     }
 
+    /// Scan the installed man pages for commands if the `man_page_commands` setting is on
+    fn update_man_commands(&mut self) {
+        if self.settings.man_page_commands {
+            self.man_commands = crate::utils::get_man_page_commands();
+        } else {
+            self.man_commands.clear();
+        }
+    }
+
     /// Update shell aliases
+    ///
+    /// The timestamp is left untouched when parsing fails so the next run retries.
     fn update_aliases(&mut self) {
-        if let Ok(aliases) = parse_shell_aliases() {
-            self.shell_aliases = aliases;
-            self.alias_last_update = SystemTime::now();
+        self.apply_parsed_aliases(parse_shell_aliases());
+    }
+
+    /// Store the outcome of an alias parse, keeping the old aliases and timestamp on failure
+    fn apply_parsed_aliases(&mut self, parsed: Result<HashMap<String, String>>) {
+        if let Ok(aliases) = parsed {
+            self.set_shell_aliases(aliases);
+        }
+    }
+
+    /// Replace the shell aliases with ones parsed elsewhere, marking them as fresh
+    ///
+    /// Fresh aliases aren't re-parsed from the shell config until they expire.
+    pub fn set_shell_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.shell_aliases = aliases;
+        self.alias_last_update = SystemTime::now();
+    }
+
+    /// Re-parse shell aliases now, regardless of when they were last updated
+    ///
+    /// Returns the number of aliases found.
+    ///
+    /// # Errors
+    /// Returns an error if the shell aliases cannot be parsed or the cache cannot be saved
+    pub fn force_refresh_aliases(&mut self) -> Result<usize> {
+        self.set_shell_aliases(parse_shell_aliases()?);
+        self.save()?;
+        Ok(self.shell_aliases.len())
+    }
+
+    /// Rebuild the saved cache's PATH commands and shell aliases from scratch
+    ///
+    /// The slow scan runs before the cache file is read, so corrections learned by a
+    /// foreground run in the meantime are kept rather than overwritten. Aliases that
+    /// fail to parse keep their previous value.
+    ///
+    /// Returns the refreshed cache.
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be loaded or saved
+    pub fn refresh_saved() -> Result<Self> {
+        if let Some(path) = Self::default_path() {
+            return Self::refresh_saved_at(&path);
+        }
+
+        let mut cache = Self::default();
+        cache.update()?;
+        Ok(cache)
+    }
+
+    /// Rebuild the PATH commands and shell aliases of the cache saved at `path`, like
+    /// [`Self::refresh_saved`]
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be loaded or saved
+    pub fn refresh_saved_at(path: &Path) -> Result<Self> {
+        let settings = Self::saved_settings(path);
+        let commands = get_path_commands_with(settings.symlink_target_commands);
+        let tool_subcommands = crate::command::discover_tool_subcommands(&commands);
+        let aliases = parse_shell_aliases().ok();
+
+        let mut cache = Self::load_from_path(path)?;
+        cache.commands = commands;
+        cache.tool_subcommands = tool_subcommands;
+        cache.register_tool_subcommands();
+        cache.update_man_commands();
+        if let Some(aliases) = aliases {
+            cache.set_shell_aliases(aliases);
+        }
+        cache.last_update = SystemTime::now();
+        cache.save()?;
+        Ok(cache)
+    }
+
+    /// Read only the settings of the cache at [`Self::default_path`]
+    ///
+    /// Cheaper than [`Self::load`] for callers that need nothing but the settings.
+    #[must_use]
+    pub fn load_settings() -> Settings {
+        Self::default_path().map_or_else(Settings::default, |path| Self::saved_settings(&path))
+    }
+
+    /// Read only the settings of the cache saved at `path`
+    ///
+    /// Skips the rest of [`Self::load_from_path`]'s work, for callers that need a setting
+    /// before loading the cache. A missing or unreadable cache has the default settings.
+    fn saved_settings(path: &Path) -> Settings {
+        #[derive(Deserialize)]
+        struct SavedSettings {
+            #[serde(default)]
+            settings: Settings,
         }
+
+        let fallback = newer_fallback(path);
+        let source = fallback.as_deref().unwrap_or(path);
+        let Ok(file) = File::open(source) else {
+            return Settings::default();
+        };
+        let reader = BufReader::new(file);
+        let saved: Option<SavedSettings> = match CacheFormat::from_path(source) {
+            CacheFormat::Json => serde_json::from_reader(reader).ok(),
+            CacheFormat::Binary => ciborium::from_reader(reader).ok(),
+        };
+        saved.map(|saved| saved.settings).unwrap_or_default()
     }
 
     /// Check if the cache contains a command
@@ -344,18 +1435,97 @@ impl CommandCache {
         self.commands.contains(command) || self.shell_aliases.contains_key(command)
     }
 
-    /// Get the closest matching command within a threshold
+    /// Check if a name is a command in the PATH, regardless of aliases
+    #[must_use]
+    pub fn is_path_command(&self, name: &str) -> bool {
+        self.commands.contains(name)
+    }
+
+    /// Get the shell aliases, mapping each alias name to the command it expands to
     #[must_use]
-    pub fn get_closest_match(&self, command: &str, threshold: f64) -> Option<String> {
-        // Combine commands and alias names for matching
+    pub fn shell_aliases(&self) -> &HashMap<String, String> {
+        &self.shell_aliases
+    }
+
+    /// Get the number of known commands and aliases
+    #[must_use]
+    pub fn command_count(&self) -> usize {
+        self.commands.len() + self.shell_aliases.len()
+    }
+
+    /// Get the closest matching command within the similarity threshold
+    ///
+    /// Commands shorter than the `min_correction_length` setting never match.
+    #[must_use]
+    pub fn get_closest_match(&self, command: &str) -> Option<String> {
+        if self.is_too_short_to_correct(command) {
+            return None;
+        }
+
+        // Combine commands, alias names and man page commands for matching
         let mut all_commands: Vec<String> = self.commands.iter().cloned().collect();
         all_commands.extend(self.shell_aliases.keys().cloned());
+        all_commands.extend(self.man_commands.iter().cloned());
 
         // Create a vector of references to use with find_closest_match
-        let command_refs: Vec<&String> = all_commands.iter().collect();
+        let command_refs: Vec<&String> = all_commands
+            .iter()
+            .filter(|candidate| !self.is_cooling_down(command, candidate))
+            .collect();
 
         // Find the closest match
-        find_closest_match(command, &command_refs, threshold).map(|s| (*s).to_string())
+        find_closest_match_with(
+            command,
+            &command_refs,
+            self.similarity_threshold,
+            self.settings.similarity_algorithm,
+        )
+        .map(|s| (*s).clone())
+    }
+
+    /// Get all matching commands within the similarity threshold, best first
+    ///
+    /// Candidates are ranked by [`combined_score`], blending length-penalized
+    /// similarity with how often each candidate has been accepted as a correction.
+    #[must_use]
+    pub fn get_closest_matches(&self, command: &str) -> Vec<(String, f64)> {
+        if self.is_too_short_to_correct(command) {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<(String, f64)> = self
+            .commands
+            .iter()
+            .chain(self.shell_aliases.keys())
+            .chain(&self.man_commands)
+            .filter(|candidate| !self.is_cooling_down(command, candidate))
+            .filter_map(|candidate| {
+                let similarity = calculate_similarity_with(
+                    command,
+                    candidate,
+                    self.settings.similarity_algorithm,
+                );
+                if similarity < self.similarity_threshold {
+                    return None;
+                }
+
+                let frequency = self
+                    .history_manager
+                    .correction_frequency
+                    .get(candidate)
+                    .copied()
+                    .unwrap_or(0);
+                let ranked_similarity = similarity * length_penalty(command, candidate);
+                Some((
+                    candidate.clone(),
+                    combined_score(ranked_similarity, frequency, FREQUENCY_WEIGHT),
+                ))
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        matches.dedup_by(|a, b| a.0 == b.0);
+        matches
     }
 
     /// Get the target command for an alias
@@ -367,31 +1537,494 @@ impl CommandCache {
     /// Find a similar command with frequency bias
     #[must_use]
     pub fn find_similar_with_frequency(&self, command: &str) -> Option<String> {
-        // First, check learned corrections - this should take priority
-        if let Some(correction) = self.learned_corrections.get(command) {
+        // Fuzzy sources use the history manager to bias towards frequent corrections
+        self.find_by_precedence(command, |source| {
+            self.history_manager
+                .find_similar_with_frequency(command, |cmd| self.fuzzy_match(cmd, source))
+        })
+    }
+
+    /// Fix a command line by correcting typos in command, arguments, and flags
+    ///
+    /// With the `bare_subcommands` setting on, a line starting with a distinctive
+    /// subcommand gets its parent command prepended instead (`commit -m "x"` ->
+    /// `git commit -m "x"`). With the `keyword_correction` setting on, a shell keyword
+    /// leading a simple command is taken as a cut-short command (`fi foo.txt` -> `find
+    /// foo.txt`). With the `glued_argument_splitting` setting on, a known command glued
+    /// to its first argument is split (`cd..` -> `cd ..`). With the `path_correction`
+    /// setting on, misspelled directories of `~/` path arguments are corrected
+    /// (`cat ~/Documnets/todo` -> `cat ~/Documents/todo`).
+    /// With the `dictionary_correction` setting on, misspelled English word arguments are
+    /// corrected too (`echo helo wrold` -> `echo hello world`). With the `cd_correction`
+    /// setting on, a misspelled `cd` target is corrected against the directories in the
+    /// current directory and `$CDPATH` (`cd myprj` -> `cd myproj`). With the
+    /// `argument_case_correction` setting on, only the command's case is normalized while
+    /// arguments naming existing paths keep theirs, up to a case typo in the file name
+    /// (`Cat README.Md` -> `cat README.md`).
+    /// A quoted command run remotely or by another shell is never touched
+    /// (`shh host 'gti status'` -> `ssh host 'gti status'`), and neither is a shell keyword
+    /// followed by a command (`do ps`), unless the keyword has a learned correction.
+    #[must_use]
+    pub fn fix_command_line(&self, command_line: &str) -> Option<String> {
+        self.fix_command_line_traced(command_line, &mut |_, _| {})
+    }
+
+    /// Like [`Self::fix_command_line`], also returning the source of the correction
+    ///
+    /// The source follows the first step that rewrote the line: a line learned as a whole
+    /// is [`MatchSource::Learned`], and a corrected command word takes its own source
+    /// (see [`Self::match_source`]). The other steps, like splitting a glued argument,
+    /// leave it `None`.
+    #[must_use]
+    pub fn fix_command_line_with_source(
+        &self,
+        command_line: &str,
+    ) -> Option<(String, Option<MatchSource>)> {
+        let mut first_step = None;
+        let corrected = self.fix_command_line_traced(command_line, &mut |name, _| {
+            first_step.get_or_insert(name);
+        })?;
+        let source = match first_step {
+            Some(LEARNED_LINE_STEP) => Some(MatchSource::Learned),
+            Some(COMMAND_STEP) => command_line
+                .split_whitespace()
+                .next()
+                .and_then(|command| self.match_source(command)),
+            _ => None,
+        };
+        Some((corrected, source))
+    }
+
+    /// Suggest a correction for a command line whose command wasn't found
+    ///
+    /// Like [`Self::fix_command_line_with_source`], falling back to the closest match of
+    /// the command word alone when the line can't be corrected as a whole.
+    #[must_use]
+    pub fn suggest_correction(&self, command_line: &str) -> Option<(String, Option<MatchSource>)> {
+        self.fix_command_line_with_source(command_line).or_else(|| {
+            let command = command_line.split_whitespace().next()?;
+            let similar = self.get_closest_match(command)?;
+            let source = if self.get_alias_target(&similar).is_some() {
+                MatchSource::FuzzyAlias
+            } else {
+                MatchSource::FuzzyCommand
+            };
+            Some((similar, Some(source)))
+        })
+    }
+
+    /// Like [`Self::fix_command_line`], reporting each step that rewrote the line to
+    /// `step` as (step name, line after the step)
+    fn fix_command_line_traced(
+        &self,
+        command_line: &str,
+        step: &mut dyn FnMut(&'static str, &str),
+    ) -> Option<String> {
+        let command_line = command_line.trim();
+        if let Some(learned) = self.match_learned_line(command_line) {
+            step(LEARNED_LINE_STEP, &learned);
+            return Some(learned);
+        }
+        if self.is_keyword_before_command(command_line) {
+            return None;
+        }
+        if let Some(implied) = self.imply_parent_command(command_line) {
+            step("parent command of a bare subcommand", &implied);
+            return Some(implied);
+        }
+
+        let keyword_corrected = self.correct_shell_keyword(command_line);
+        let glued_split = keyword_corrected
+            .is_none()
+            .then(|| self.split_glued_command(command_line))
+            .flatten();
+        let rewritten = keyword_corrected.or_else(|| glued_split.clone().map(|(line, _)| line));
+        if let Some(rewritten) = &rewritten {
+            step("shell keyword or glued command", rewritten);
+        }
+        let command_line = rewritten.as_deref().unwrap_or(command_line);
+
+        // A command split off a glued argument is known already, so it isn't fuzzily replaced
+        let split_command = glued_split.map(|(_, command)| command);
+        let fixed = crate::command::fix_command_line(
+            command_line,
+            |cmd| {
+                if split_command.as_deref() == Some(cmd) {
+                    Some(cmd.to_string())
+                } else {
+                    self.find_similar(cmd)
+                }
+            },
+            &self.command_patterns,
+        );
+        if let Some(fixed) = &fixed {
+            step(COMMAND_STEP, fixed);
+        }
+        let changed = fixed.is_some() || rewritten.is_some();
+        let mut corrected = fixed
+            .or_else(|| rewritten.clone())
+            .unwrap_or_else(|| command_line.to_string());
+
+        let stages: [LineStage; 5] = [
+            ("home directory paths", &|line| {
+                let corrected = correct_home_paths(line);
+                (self.settings.path_correction && corrected != line).then_some(corrected)
+            }),
+            ("dictionary words", &|line| self.correct_dictionary_words(line)),
+            ("cd target", &|line| self.correct_cd_line(line)),
+            ("path arguments", &|line| {
+                self.preserve_path_arguments(command_line, line)
+            }),
+            ("git branch", &|line| {
+                if !self.settings.git_branch_correction {
+                    return None;
+                }
+                let corrected = self.correct_branch_name(command_line, line.to_string());
+                (corrected != line).then_some(corrected)
+            }),
+        ];
+        for (name, stage) in stages {
+            if let Some(next) = stage(&corrected) {
+                step(name, &next);
+                corrected = next;
+            }
+        }
+
+        (changed || corrected != command_line).then_some(corrected)
+    }
+
+    /// Get the learned correction of a whole command line, if it has one
+    ///
+    /// That's the correction learned for the exact line, or for a similar multi-word
+    /// line (see [`Self::fix_command_line`]). A line whose command word alone was learned
+    /// has none.
+    #[must_use]
+    pub fn learned_line(&self, command_line: &str) -> Option<String> {
+        let command_line = command_line.trim();
+        self.learned_corrections
+            .get(command_line)
+            .cloned()
+            .or_else(|| self.match_learned_line(command_line))
+    }
+
+    /// Match a multi-word command line against the learned full-line corrections
+    ///
+    /// Lines learned as a whole (e.g. through the `c` answer to a suggestion) are scored
+    /// with the `line_similarity_algorithm` setting, trigram similarity by default, and
+    /// the best one scoring at least [`LINE_MATCH_THRESHOLD`] gives its correction.
+    ///
+    /// A learned line is only reused whole when its arguments are the typed ones.
+    /// Otherwise only its command and subcommand are taken, and the typed arguments are
+    /// kept, so `git psuh origin feature-logout` never becomes the learned
+    /// `git push origin feature-login`.
+    fn match_learned_line(&self, command_line: &str) -> Option<String> {
+        if !command_line.contains(char::is_whitespace) {
+            return None;
+        }
+        if let Some(correction) = self.learned_corrections.get(command_line) {
             return Some(correction.clone());
         }
 
-        // Second, check if we have this exact command
-        if self.commands.contains(command) || self.shell_aliases.contains_key(command) {
-            return Some(command.to_string());
+        let algorithm = self.settings.line_similarity_algorithm;
+        let (typo, correction) = self
+            .learned_corrections
+            .iter()
+            .filter(|(typo, _)| typo.contains(char::is_whitespace))
+            .map(|(typo, correction)| {
+                (calculate_similarity_with(command_line, typo, algorithm), typo, correction)
+            })
+            .filter(|(score, _, _)| *score >= LINE_MATCH_THRESHOLD)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, typo, correction)| (typo, correction))?;
+        reuse_learned_line(command_line, typo, correction)
+    }
+
+    /// Put back the path arguments of the typed line into its correction, if the
+    /// `argument_case_correction` setting is on
+    ///
+    /// An argument naming an existing path is kept as typed, or with its case typo
+    /// corrected (see [`crate::command::correct_path_case`]), unless the correction only
+    /// changed its case to a known word. Arguments the correction left alone only get
+    /// their case corrected if they look like paths, so `status` never becomes a
+    /// `STATUS` file. Lines whose correction added or removed words are left alone, and
+    /// so are quoted words, whose text isn't the path the shell sees. Only the preserved
+    /// words are replaced, so the rest of the correction keeps its quoting and spacing.
+    /// Returns `None` when nothing changed.
+    fn preserve_path_arguments(&self, typed: &str, corrected: &str) -> Option<String> {
+        if !self.settings.argument_case_correction {
+            return None;
+        }
+        let typed_words = split_words(typed);
+        let words = split_words(corrected);
+        if typed_words.len() != words.len() {
+            return None;
         }
 
-        // Finally, use the history manager to find a similar command with frequency bias
-        self.history_manager
-            .find_similar_with_frequency(command, |cmd| {
-                self.get_closest_match(cmd, SIMILARITY_THRESHOLD)
+        let replacements: Vec<(std::ops::Range<usize>, String)> = words
+            .iter()
+            .zip(&typed_words)
+            .skip(1)
+            .filter(|(word, typed)| !word.quoted && !typed.quoted)
+            .filter_map(|(word, typed)| {
+                let (token, typed) = (word.text, typed.text);
+                let looks_like_path = typed.contains(['.', '/']);
+                if (token == typed && !looks_like_path)
+                    || (token != typed && token.eq_ignore_ascii_case(typed))
+                {
+                    return None;
+                }
+                let preserved = if Path::new(typed).exists() {
+                    typed.to_string()
+                } else {
+                    crate::command::correct_path_case(typed)?
+                };
+                (preserved != token).then(|| (word.range.clone(), preserved))
             })
+            .collect();
+        (!replacements.is_empty()).then(|| replace_words(corrected, replacements))
     }
 
-    /// Fix a command line by correcting typos in command, arguments, and flags
+    /// Correct a misspelled `cd` target if the `cd_correction` setting is on
+    fn correct_cd_line(&self, command_line: &str) -> Option<String> {
+        if !self.settings.cd_correction {
+            return None;
+        }
+        correct_cd_line(command_line)
+    }
+
+    /// Correct misspelled English word arguments if the `dictionary_correction` setting is on
+    ///
+    /// Returns `None` when the setting is off or no word was corrected.
+    fn correct_dictionary_words(&self, command_line: &str) -> Option<String> {
+        if !self.settings.dictionary_correction {
+            return None;
+        }
+        let corrected =
+            crate::command::correct_dictionary_words(command_line, &self.command_patterns);
+        (corrected != command_line).then_some(corrected)
+    }
+
+    /// Correct the branch name of a `git checkout`/`switch`/`branch` line against the
+    /// local branches of the repository in the current directory
+    ///
+    /// `typed` is the line as the user typed it and `corrected` the line with its command,
+    /// subcommand and flags already corrected, e.g. `git chekout mian` and `git checkout
+    /// merge` give `git checkout main` given a `main` branch. A branch name with no close
+    /// match is kept as typed rather than corrected to a subcommand.
+    fn correct_branch_name(&self, typed: &str, corrected: String) -> String {
+        let mut tokens: Vec<&str> = corrected.split_whitespace().collect();
+        let Some(index) = crate::git::branch_argument_index(&tokens) else {
+            return corrected;
+        };
+        let subcommand = tokens[1..].iter().find(|token| !token.starts_with('-'));
+        if tokens[0] != "git"
+            || !subcommand.is_some_and(|subcommand| {
+                crate::git::BRANCH_SUBCOMMANDS.contains(subcommand)
+            })
+        {
+            return corrected;
+        }
+
+        let typed_tokens: Vec<&str> = typed.split_whitespace().collect();
+        let Some(typed_branch) =
+            crate::git::branch_argument_index(&typed_tokens).map(|i| typed_tokens[i])
+        else {
+            return corrected;
+        };
+
+        let branches = self.local_branches();
+        let branch = if branches.iter().any(|branch| branch == typed_branch)
+            || Path::new(typed_branch).exists()
+        {
+            typed_branch.to_string()
+        } else {
+            crate::utils::find_closest_match(typed_branch, &branches, self.similarity_threshold)
+                .map_or_else(|| typed_branch.to_string(), Clone::clone)
+        };
+
+        tokens[index] = &branch;
+        tokens.join(" ")
+    }
+
+    /// Get the local branches of the git repository in the current directory
+    ///
+    /// Branches are listed once per repository and reused until its `HEAD` or
+    /// `refs/heads` changes.
+    fn local_branches(&self) -> Vec<String> {
+        let Some(root) = std::env::current_dir()
+            .ok()
+            .and_then(|dir| crate::git::find_repo_root(&dir))
+        else {
+            return Vec::new();
+        };
+        let stamp = crate::git::refs_stamp(&root);
+
+        let Ok(mut git_branches) = self.git_branches.lock() else {
+            return crate::git::list_local_branches(&root);
+        };
+        if let Some(cached) = git_branches
+            .get(&root)
+            .filter(|cached| Some(cached.stamp) == stamp)
+        {
+            return cached.branches.clone();
+        }
+
+        let branches = crate::git::list_local_branches(&root);
+        if let Some(stamp) = stamp {
+            git_branches.insert(
+                root,
+                BranchCache {
+                    stamp,
+                    branches: branches.clone(),
+                },
+            );
+        }
+        branches
+    }
+
+    /// Prepend the parent command implied by a bare subcommand, if the setting is on
+    ///
+    /// Known commands, aliases and learned typos are never treated as bare subcommands.
+    fn imply_parent_command(&self, command_line: &str) -> Option<String> {
+        if !self.settings.bare_subcommands {
+            return None;
+        }
+
+        let command_line = command_line.trim_start();
+        let first = command_line.split_whitespace().next()?;
+        if self.contains(first) || self.learned_corrections.contains_key(first) {
+            return None;
+        }
+
+        let parent = self
+            .command_patterns
+            .find_implied_parent(first, &self.settings.bare_subcommand_parents)?;
+        Some(format!("{parent} {command_line}"))
+    }
+
+    /// Insert the missing space between a known command and its first argument
+    ///
+    /// Gated by the `glued_argument_splitting` setting, e.g. `cd..` -> `cd ..` and
+    /// `ls-la /tmp` -> `ls -la /tmp`. Only a first word that isn't itself a command, alias,
+    /// learned typo or existing path is split. Returns the split line and the command.
+    fn split_glued_command(&self, command_line: &str) -> Option<(String, String)> {
+        if !self.settings.glued_argument_splitting {
+            return None;
+        }
+        let command_line = command_line.trim();
+        let first = command_line.split_whitespace().next()?;
+        if self.learned_corrections.contains_key(first) || Path::new(first).exists() {
+            return None;
+        }
+
+        let (command, argument) = crate::command::split_glued_argument(first, |command| {
+            self.contains(command) || self.command_patterns.is_known_command(command)
+        })?;
+        Some((
+            format!("{command} {argument}{}", &command_line[first.len()..]),
+            command.to_string(),
+        ))
+    }
+
+    /// Check whether a line is a shell keyword followed by a command, like `do ps` from a
+    /// loop typed across lines, which is shell syntax rather than a typo
+    ///
+    /// A keyword with a learned correction of its own is still corrected.
+    fn is_keyword_before_command(&self, command_line: &str) -> bool {
+        let mut words = command_line.split_whitespace();
+        let (Some(keyword), Some(next)) = (words.next(), words.next()) else {
+            return false;
+        };
+        let is_keyword = crate::command::CONTINUATION_KEYWORDS.contains(&keyword)
+            || crate::command::COMPOUND_OPENERS.contains(&keyword);
+        is_keyword
+            && !self.learned_corrections.contains_key(keyword)
+            && (self.contains(next) || self.command_patterns.is_known_command(next))
+    }
+
+    /// Replace a shell keyword typed where a command belongs with the command it cuts short
+    ///
+    /// Gated by the `keyword_correction` setting, e.g. `fi foo.txt` -> `find foo.txt`. A
+    /// learned correction for the keyword wins; otherwise the best-ranked command starting
+    /// with it is used, so frequently accepted corrections break ties like `find`/`file`.
+    fn correct_shell_keyword(&self, command_line: &str) -> Option<String> {
+        if !self.settings.keyword_correction {
+            return None;
+        }
+
+        let command_line = command_line.trim();
+        let keyword = crate::command::misplaced_keyword(command_line)?;
+        let command = self.learned_corrections.get(keyword).cloned().or_else(|| {
+            self.get_closest_matches(keyword)
+                .into_iter()
+                .map(|(candidate, _)| candidate)
+                .find(|candidate| candidate.len() > keyword.len() && candidate.starts_with(keyword))
+        })?;
+        Some(format!("{command}{}", &command_line[keyword.len()..]))
+    }
+
+    /// Get the flags most often used with a command in the correction history, most frequent first
     #[must_use]
-    pub fn fix_command_line(&self, command_line: &str) -> Option<String> {
-        crate::command::fix_command_line(
-            command_line,
-            |cmd| self.find_similar(cmd),
-            &self.command_patterns,
-        )
+    pub fn frequent_flags_for(&self, command: &str) -> Vec<String> {
+        self.history_manager
+            .frequent_flags_for(command)
+            .into_iter()
+            .map(|(flag, _)| flag)
+            .collect()
+    }
+
+    /// Complete a command line for the shell
+    ///
+    /// Typos are corrected first. When the `append_frequent_flags` setting is on, flags
+    /// the user habitually passes to the command (e.g. `-it --rm` for `docker run`) are
+    /// appended unless already present.
+    #[must_use]
+    pub fn generate_full_completion(&self, command_line: &str) -> String {
+        let mut completion = self
+            .fix_command_line(command_line)
+            .unwrap_or_else(|| command_line.trim().to_string());
+
+        if self.settings.append_frequent_flags {
+            let present: HashSet<String> = completion
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            for flag in self.frequent_flags_for(&self.flag_prefix(&completion)) {
+                if !present.contains(&flag) {
+                    completion.push(' ');
+                    completion.push_str(&flag);
+                }
+            }
+        }
+
+        completion
+    }
+
+    /// The part of a command line that frequent flags are looked up by: the command and,
+    /// if its first non-flag argument is a known subcommand, that subcommand
+    ///
+    /// Positional arguments like image names or paths are left out, so `docker run nginx`
+    /// looks up the flags used with `docker run`.
+    fn flag_prefix(&self, command_line: &str) -> String {
+        let mut words = command_line.split_whitespace();
+        let Some(command) = words.next() else {
+            return String::new();
+        };
+        let subcommand = words.find(|word| !word.starts_with('-')).filter(|word| {
+            self.command_patterns
+                .get_args_for_command(command)
+                .is_some_and(|mut args| args.any(|arg| arg == word))
+        });
+        match subcommand {
+            Some(subcommand) => format!("{command} {subcommand}"),
+            None => command.to_string(),
+        }
+    }
+
+    /// Get the command patterns used to correct arguments and flags
+    #[must_use]
+    pub fn command_patterns(&self) -> &CommandPatterns {
+        &self.command_patterns
     }
 
     /// Set the cache path (useful for testing)
@@ -405,12 +2038,72 @@ impl CommandCache {
         &self.history_manager
     }
 
+    /// Get a reference to the user settings
+    #[must_use]
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Get a mutable reference to the user settings
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Update a setting by name
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the setting is unknown or the value is invalid
+    pub fn set_setting(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            // Scan right away rather than at the next daily update
+            "man_page_commands" => {
+                self.settings.set(key, value)?;
+                self.update_man_commands();
+                Ok(())
+            }
+            "symlink_target_commands" => {
+                self.settings.set(key, value)?;
+                self.update_path_commands();
+                Ok(())
+            }
+            "max_learned_patterns" => {
+                self.settings.set(key, value)?;
+                self.evict_learned_patterns();
+                Ok(())
+            }
+            _ => {
+                self.settings.set(key, value)?;
+                self.apply_history_settings();
+                Ok(())
+            }
+        }
+    }
+
+    /// Hand the settings that change how history is ranked to the history manager
+    fn apply_history_settings(&mut self) {
+        self.history_manager.collapse_repeats = self.settings.collapse_repeated_history;
+        self.history_manager.frequency_half_life_days = self.settings.frequency_half_life_days;
+    }
+
+    /// List all settings with their current values
+    #[must_use]
+    pub fn setting_entries(&self) -> Vec<(&'static str, String)> {
+        self.settings.entries()
+    }
+
     /// Get the direct correction for a typo without fuzzy matching
     #[must_use]
     pub fn get_direct_correction(&self, typo: &str) -> Option<&String> {
         self.learned_corrections.get(typo)
     }
 
+    /// Make the cache old enough to need an update (for testing)
+    #[cfg(test)]
+    pub fn expire_for_test(&mut self) {
+        self.last_update = SystemTime::UNIX_EPOCH;
+    }
+
     /// Check if shell aliases are empty (helpful for testing)
     #[must_use]
     #[cfg(test)]
@@ -464,8 +2157,26 @@ impl CommandCache {
 
 // Implement HistoryTracker to delegate to the history manager
 impl HistoryTracker for CommandCache {
-    fn record_correction(&mut self, typo: &str, correction: &str) {
-        self.history_manager.record_correction(typo, correction);
+    fn record_correction_from(
+        &mut self,
+        typo: &str,
+        correction: &str,
+        full_line: &str,
+        source: Option<MatchSource>,
+    ) {
+        self.history_manager
+            .record_correction_from(typo, correction, full_line, source);
+        if let Some(command) = correction.split_whitespace().next() {
+            self.command_patterns.touch(command);
+        }
+    }
+
+    fn record_rejection(&mut self, source: MatchSource) {
+        self.history_manager.record_rejection(source);
+    }
+
+    fn get_source_stats(&self) -> Vec<SourceStats> {
+        self.history_manager.get_source_stats()
     }
 
     fn get_frequent_typos(&self, limit: usize) -> Vec<(String, usize)> {
@@ -486,6 +2197,9 @@ impl HistoryTracker for CommandCache {
 
     fn clear_history(&mut self) {
         self.history_manager.clear_history();
+        if let Some(cache_path) = &self.cache_path {
+            let _ = alias_log::clear(&alias_log::log_path(cache_path));
+        }
     }
 
     fn is_history_enabled(&self) -> bool {
@@ -502,3 +2216,6 @@ impl HistoryTracker for CommandCache {
         self.save()
     }
 }
+
+#[cfg(test)]
+mod tests;
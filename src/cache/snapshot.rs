@@ -0,0 +1,409 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Binary snapshot of the cached command and alias sets
+//!
+//! The snapshot is written next to the cache file whenever the cache is saved, and
+//! lets short-lived invocations read the command and alias sets without parsing them
+//! out of the cache file. It is only trusted while the cache file it was written
+//! alongside is unchanged; otherwise the cache file is parsed as usual.
+//!
+//! Layout (all integers little-endian):
+//!
+//! | Offset | Size         | Field                                                 |
+//! |--------|--------------|-------------------------------------------------------|
+//! | 0      | 8            | Magic `SNOOFIDX`                                      |
+//! | 8      | 4            | Format version                                        |
+//! | 12     | 4            | Number of commands                                    |
+//! | 16     | 4            | Number of aliases                                     |
+//! | 20     | 4            | Reserved                                              |
+//! | 24     | 8            | Length of the cache file this snapshot belongs to     |
+//! | 32     | 8            | Its modification time, seconds since the epoch        |
+//! | 40     | 4            | Its modification time, nanoseconds                    |
+//! | 44     | 4            | Reserved                                              |
+//! | 48     | 8 × commands | `(offset, length)` of each command, sorted            |
+//! | …      | 16 × aliases | `(offset, length)` of each alias name, then target    |
+//! | …      | rest         | UTF-8 string data the offsets point into              |
+
+use anyhow::{Context, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    hash::BuildHasher,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Identifies a snapshot file
+const MAGIC: &[u8; 8] = b"SNOOFIDX";
+
+/// Format version; bump whenever the layout changes so old snapshots are ignored
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Suffix of the snapshot file written next to the cache file, e.g. `cache.json.idx`
+///
+/// Appended rather than replacing the extension, so the JSON and binary caches never
+/// share a snapshot.
+pub const SNAPSHOT_SUFFIX: &str = ".idx";
+
+/// Size of the fixed header preceding the offset tables
+const HEADER_LEN: usize = 48;
+
+/// Get the path of the snapshot belonging to a cache file
+#[must_use]
+pub fn snapshot_path(cache_path: &Path) -> PathBuf {
+    super::path_with_suffix(cache_path, SNAPSHOT_SUFFIX)
+}
+
+/// Identity of a cache file: its length and modification time
+///
+/// A snapshot is stale as soon as its cache file is rewritten or edited by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceStamp {
+    len: u64,
+    secs: u64,
+    nanos: u32,
+}
+
+impl SourceStamp {
+    fn of(cache_path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(cache_path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        Some(Self {
+            len: metadata.len(),
+            secs: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+/// Read-only view of the command and alias sets of a saved cache
+///
+/// Lookups read straight from the snapshot's bytes: nothing is parsed when the snapshot
+/// is opened beyond validating its header and offset tables.
+pub struct CommandSnapshot {
+    bytes: Vec<u8>,
+    command_count: usize,
+    alias_count: usize,
+}
+
+impl std::fmt::Debug for CommandSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandSnapshot")
+            .field("bytes", &self.bytes.len())
+            .field("command_count", &self.command_count)
+            .field("alias_count", &self.alias_count)
+            .finish()
+    }
+}
+
+impl CommandSnapshot {
+    /// Open the snapshot belonging to a cache file
+    ///
+    /// Returns `None` when the snapshot is missing, from another format version,
+    /// malformed, or stale because the cache file changed after it was written.
+    #[must_use]
+    pub fn open(cache_path: &Path) -> Option<Self> {
+        let stamp = SourceStamp::of(cache_path)?;
+        let snapshot = Self::from_bytes(fs::read(snapshot_path(cache_path)).ok()?)?;
+        (snapshot.source_stamp() == stamp).then_some(snapshot)
+    }
+
+    /// Validate the header and offset tables of a snapshot read from disk
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        if bytes.len() < HEADER_LEN
+            || &bytes[..8] != MAGIC
+            || read_u32(&bytes, 8)? != SNAPSHOT_VERSION
+        {
+            return None;
+        }
+
+        let command_count = usize::try_from(read_u32(&bytes, 12)?).ok()?;
+        let alias_count = usize::try_from(read_u32(&bytes, 16)?).ok()?;
+        let snapshot = Self {
+            bytes,
+            command_count,
+            alias_count,
+        };
+
+        // Every string must lie within the data and be valid UTF-8
+        let data = snapshot.data()?;
+        let entries = command_count.checked_add(alias_count.checked_mul(2)?)?;
+        (0..entries)
+            .all(|entry| snapshot.entry(data, HEADER_LEN + entry * 8).is_some())
+            .then_some(snapshot)
+    }
+
+    fn source_stamp(&self) -> SourceStamp {
+        SourceStamp {
+            len: read_u64(&self.bytes, 24).unwrap_or_default(),
+            secs: read_u64(&self.bytes, 32).unwrap_or_default(),
+            nanos: read_u32(&self.bytes, 40).unwrap_or_default(),
+        }
+    }
+
+    /// Get the string data following the offset tables
+    fn data(&self) -> Option<&[u8]> {
+        let tables = self
+            .command_count
+            .checked_add(self.alias_count.checked_mul(2)?)?
+            .checked_mul(8)?;
+        self.bytes.get(HEADER_LEN.checked_add(tables)?..)
+    }
+
+    /// Read the string described by the `(offset, length)` pair at `table_offset`
+    fn entry<'a>(&self, data: &'a [u8], table_offset: usize) -> Option<&'a str> {
+        let offset = usize::try_from(read_u32(&self.bytes, table_offset)?).ok()?;
+        let len = usize::try_from(read_u32(&self.bytes, table_offset + 4)?).ok()?;
+        std::str::from_utf8(data.get(offset..offset.checked_add(len)?)?).ok()
+    }
+
+    fn command_at<'a>(&self, data: &'a [u8], index: usize) -> &'a str {
+        self.entry(data, HEADER_LEN + index * 8).unwrap_or_default()
+    }
+
+    fn alias_at<'a>(&self, data: &'a [u8], index: usize) -> (&'a str, &'a str) {
+        let table = HEADER_LEN + self.command_count * 8 + index * 16;
+        (
+            self.entry(data, table).unwrap_or_default(),
+            self.entry(data, table + 8).unwrap_or_default(),
+        )
+    }
+
+    /// Get the number of commands in the snapshot
+    #[must_use]
+    pub fn command_count(&self) -> usize {
+        self.command_count
+    }
+
+    /// Get the number of aliases in the snapshot
+    #[must_use]
+    pub fn alias_count(&self) -> usize {
+        self.alias_count
+    }
+
+    /// Check whether a command is in the snapshot
+    #[must_use]
+    pub fn contains_command(&self, command: &str) -> bool {
+        let Some(data) = self.data() else {
+            return false;
+        };
+        binary_search(self.command_count, command, |index| {
+            self.command_at(data, index)
+        })
+        .is_some()
+    }
+
+    /// Get the target of an alias in the snapshot
+    #[must_use]
+    pub fn alias_target(&self, alias: &str) -> Option<&str> {
+        let data = self.data()?;
+        let index = binary_search(self.alias_count, alias, |index| {
+            self.alias_at(data, index).0
+        })?;
+        Some(self.alias_at(data, index).1)
+    }
+
+    /// Iterate over the commands in the snapshot, in sorted order
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        let data = self.data().unwrap_or_default();
+        (0..self.command_count).map(move |index| self.command_at(data, index))
+    }
+
+    /// Iterate over the aliases and their targets in the snapshot, sorted by alias
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        let data = self.data().unwrap_or_default();
+        (0..self.alias_count).map(move |index| self.alias_at(data, index))
+    }
+
+    /// Copy the command set out of the snapshot
+    #[must_use]
+    pub fn to_command_set(&self) -> HashSet<String> {
+        self.commands().map(ToString::to_string).collect()
+    }
+
+    /// Copy the alias map out of the snapshot
+    #[must_use]
+    pub fn to_alias_map(&self) -> HashMap<String, String> {
+        self.aliases()
+            .map(|(alias, target)| (alias.to_string(), target.to_string()))
+            .collect()
+    }
+}
+
+/// Write the snapshot for a cache file that has just been saved
+///
+/// The snapshot is stamped with the cache file's current length and modification
+/// time, and written to a temporary file that is renamed into place so readers never
+/// read a half-written snapshot.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The cache file cannot be inspected
+/// - The snapshot is too large for its 32-bit offsets
+/// - The snapshot cannot be written
+pub fn write_snapshot<S: BuildHasher, T: BuildHasher>(
+    cache_path: &Path,
+    commands: &HashSet<String, S>,
+    aliases: &HashMap<String, String, T>,
+) -> Result<()> {
+    let stamp = SourceStamp::of(cache_path)
+        .with_context(|| format!("Failed to inspect cache file at {}", cache_path.display()))?;
+
+    let mut commands: Vec<&str> = commands.iter().map(String::as_str).collect();
+    commands.sort_unstable();
+    let mut aliases: Vec<(&str, &str)> = aliases
+        .iter()
+        .map(|(alias, target)| (alias.as_str(), target.as_str()))
+        .collect();
+    aliases.sort_unstable();
+
+    let mut tables = Vec::with_capacity(commands.len() * 8 + aliases.len() * 16);
+    let mut data = Vec::new();
+    let strings = commands
+        .iter()
+        .copied()
+        .chain(aliases.iter().flat_map(|&(alias, target)| [alias, target]));
+    for string in strings {
+        tables.extend_from_slice(&u32::try_from(data.len())?.to_le_bytes());
+        tables.extend_from_slice(&u32::try_from(string.len())?.to_le_bytes());
+        data.extend_from_slice(string.as_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + tables.len() + data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&u32::try_from(commands.len())?.to_le_bytes());
+    bytes.extend_from_slice(&u32::try_from(aliases.len())?.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&stamp.len.to_le_bytes());
+    bytes.extend_from_slice(&stamp.secs.to_le_bytes());
+    bytes.extend_from_slice(&stamp.nanos.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&tables);
+    bytes.extend_from_slice(&data);
+
+    // Unique per process and save, like the cache file's own partial files
+    let path = snapshot_path(cache_path);
+    let partial = super::path_with_suffix(
+        &path,
+        &format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            super::PARTIAL_SAVES.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ),
+    );
+    let written = File::create(&partial)
+        .and_then(|mut file| file.write_all(&bytes))
+        .and_then(|()| fs::rename(&partial, &path))
+        .with_context(|| format!("Failed to write command snapshot to {}", path.display()));
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+/// Remove the snapshot belonging to a cache file, if any
+pub fn remove_snapshot(cache_path: &Path) {
+    let _ = fs::remove_file(snapshot_path(cache_path));
+}
+
+/// Find `key` among `count` sorted keys, returning its index
+fn binary_search<'a>(count: usize, key: &str, key_at: impl Fn(usize) -> &'a str) -> Option<usize> {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match key_at(mid).cmp(key) {
+            std::cmp::Ordering::Less => low = mid + 1,
+            std::cmp::Ordering::Greater => high = mid,
+            std::cmp::Ordering::Equal => return Some(mid),
+        }
+    }
+    None
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CommandCache;
+
+    /// Save a cache with `git` and the alias `g` to a temporary directory
+    fn saved_cache(dir: &Path) -> PathBuf {
+        let path = dir.join("cache.json");
+        let mut cache = CommandCache::new();
+        cache.set_cache_path(path.clone());
+        cache.insert("git");
+        cache.add_test_alias("g", "git");
+        cache.save().unwrap();
+        path
+    }
+
+    #[test]
+    fn saving_writes_a_snapshot_of_the_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path());
+
+        let snapshot = CommandSnapshot::open(&path).unwrap();
+        assert!(snapshot.contains_command("git"));
+        assert!(!snapshot.contains_command("gti"));
+        assert_eq!(snapshot.alias_target("g"), Some("git"));
+        assert_eq!((snapshot.command_count(), snapshot.alias_count()), (1, 1));
+    }
+
+    #[test]
+    fn loading_takes_the_sets_from_a_fresh_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path());
+        // A snapshot stamped with the unchanged cache file is trusted over its contents
+        let commands = HashSet::from(["cargo".to_string()]);
+        write_snapshot(&path, &commands, &HashMap::<String, String>::new()).unwrap();
+
+        let cache = CommandCache::load_from_path(&path).unwrap();
+        assert!(cache.is_path_command("cargo"));
+        assert!(!cache.is_path_command("git"));
+    }
+
+    #[test]
+    fn stale_or_foreign_snapshots_fall_back_to_the_cache_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = saved_cache(dir.path());
+        let snapshot = fs::read(snapshot_path(&path)).unwrap();
+
+        // Another format version
+        let mut foreign = snapshot.clone();
+        foreign[8..12].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        fs::write(snapshot_path(&path), &foreign).unwrap();
+        assert!(CommandSnapshot::open(&path).is_none());
+
+        // Cut short
+        fs::write(snapshot_path(&path), &snapshot[..snapshot.len() - 1]).unwrap();
+        assert!(CommandSnapshot::open(&path).is_none());
+
+        // The cache file changed after the snapshot was written
+        fs::write(snapshot_path(&path), &snapshot).unwrap();
+        let mut cache_file = fs::read(&path).unwrap();
+        cache_file.push(b'\n');
+        fs::write(&path, cache_file).unwrap();
+        assert!(CommandSnapshot::open(&path).is_none());
+
+        let cache = CommandCache::load_from_path(&path).unwrap();
+        assert!(cache.is_path_command("git"));
+    }
+}
@@ -0,0 +1,763 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use super::*;
+
+#[test]
+fn learned_line_keeps_typed_arguments() {
+    let mut cache = CommandCache::new();
+    cache
+        .learn_correction(
+            "git psuh origin feature-login",
+            "git push origin feature-login",
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(
+        cache
+            .fix_command_line("git psuh origin feature-login")
+            .as_deref(),
+        Some("git push origin feature-login")
+    );
+    assert_eq!(
+        cache
+            .fix_command_line("git psuh origin feature-logout")
+            .as_deref(),
+        Some("git push origin feature-logout")
+    );
+}
+
+#[test]
+fn truncated_cache_is_moved_aside() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(path.clone());
+    cache.learn_correction("gti", "git", None).unwrap();
+
+    let saved = fs::read(&path).unwrap();
+    fs::write(&path, &saved[..saved.len() / 2]).unwrap();
+
+    let cache = CommandCache::load_from_path(&path).unwrap();
+    assert!(cache.get_direct_correction("gti").is_none());
+    assert_eq!(
+        fs::read(path_with_suffix(&path, ".corrupt")).unwrap(),
+        &saved[..saved.len() / 2]
+    );
+}
+
+#[test]
+fn save_leaves_no_partial_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(path.clone());
+    cache.learn_correction("gti", "git", None).unwrap();
+    cache.save().unwrap();
+
+    let mut names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    // The cache file and its command snapshot
+    assert_eq!(names, [CACHE_FILE.to_string(), format!("{CACHE_FILE}.idx")]);
+}
+
+#[test]
+fn cache_saved_to_the_fallback_directory_is_loaded_back() {
+    let dir = tempfile::tempdir().unwrap();
+    // A file where the cache's directory should be makes its path unwritable
+    let blocked = dir.path().join("not-a-directory");
+    fs::write(&blocked, "").unwrap();
+    let path = blocked.join(CACHE_FILE);
+
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(path.clone());
+    cache.learn_correction("gti", "git", None).unwrap();
+    let fallback = fallback_path(&path);
+    assert!(fallback.starts_with(fallback_dir()));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&fallback).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let loaded = CommandCache::load_from_path(&path).unwrap();
+    assert_eq!(
+        loaded.get_direct_correction("gti").map(String::as_str),
+        Some("git")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn fallback_that_is_not_the_users_own_file_is_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(path.clone());
+    cache.learn_correction("gti", "git", None).unwrap();
+
+    // A newer fallback planted as a symlink to someone else's cache
+    let planted = dir.path().join("planted.json");
+    let mut other = CommandCache::new();
+    other.cache_path = Some(planted.clone());
+    other.set_setting("dangerous_commands", "").unwrap();
+    other.learn_correction("gti", "rm -rf ~", None).unwrap();
+    File::options()
+        .write(true)
+        .open(&planted)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    std::os::unix::fs::symlink(&planted, fallback_path(&path)).unwrap();
+    assert!(is_newer(&fallback_path(&path), &path));
+
+    let loaded = CommandCache::load_from_path(&path).unwrap();
+    assert_eq!(
+        loaded.get_direct_correction("gti").map(String::as_str),
+        Some("git")
+    );
+    assert_eq!(
+        CommandCache::saved_settings(&path).dangerous_commands,
+        Settings::default().dangerous_commands
+    );
+}
+
+#[test]
+fn evicted_patterns_keep_their_help_discoveries() {
+    let mut cache = CommandCache::new();
+    cache.settings.max_learned_patterns = 1;
+    cache.help_discoveries.insert(
+        "tool".to_string(),
+        HelpText {
+            subcommands: vec!["run".to_string()],
+            flags: vec![],
+        },
+    );
+    cache.register_help_discoveries();
+    cache.command_patterns.learn_from_command("newer --flag");
+    cache.command_patterns.touch("newer");
+    cache.evict_learned_patterns();
+
+    assert!(cache.command_patterns.get_args_for_command("tool").is_none());
+    assert!(cache.help_discoveries.contains_key("tool"));
+}
+
+#[test]
+fn lower_similarity_threshold_matches_looser_typos() {
+    let mut cache = CommandCache::new();
+    cache.commands.insert("terraform".to_string());
+    assert_eq!(cache.find_similar("tfm"), None);
+
+    cache.set_similarity_threshold(0.3);
+    assert_eq!(cache.find_similar("tfm").as_deref(), Some("terraform"));
+}
+
+#[test]
+fn similarity_threshold_is_saved_with_the_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(path.clone());
+    cache.insert("terraform");
+    cache.set_similarity_threshold(0.3);
+    cache.save().unwrap();
+
+    let loaded = CommandCache::load_from_path(&path).unwrap();
+    assert!((loaded.similarity_threshold() - 0.3).abs() < f64::EPSILON);
+    assert_eq!(loaded.get_closest_match("tfm").as_deref(), Some("terraform"));
+}
+
+#[test]
+fn glued_arguments_are_split_when_enabled() {
+    let mut cache = CommandCache::new();
+    cache.commands.insert("ls".to_string());
+    cache.commands.insert("cc".to_string());
+    assert_eq!(cache.fix_command_line("cd..").as_deref(), Some("cd .."));
+    assert_eq!(
+        cache.fix_command_line("ls-la /tmp").as_deref(),
+        Some("ls -la /tmp")
+    );
+
+    cache.set_setting("glued_argument_splitting", "false").unwrap();
+    assert_ne!(cache.fix_command_line("cd..").as_deref(), Some("cd .."));
+    assert_ne!(
+        cache.fix_command_line("ls-la /tmp").as_deref(),
+        Some("ls -la /tmp")
+    );
+}
+
+#[test]
+fn half_life_setting_ranks_recent_corrections_first() {
+    let mut cache = CommandCache::new();
+    for _ in 0..3 {
+        cache.record_correction("gti", "git");
+    }
+    if let Some(entry) = cache.history_manager.command_history.front_mut() {
+        entry.timestamp = SystemTime::now() - Duration::from_hours(30 * 24);
+    }
+    cache.record_correction("sl", "ls");
+    assert_eq!(cache.get_frequent_corrections(1)[0].0, "git");
+
+    cache.set_setting("frequency_half_life_days", "7").unwrap();
+    assert_eq!(cache.history_manager.frequency_half_life_days, 7);
+    assert_eq!(cache.get_frequent_corrections(1)[0].0, "ls");
+    assert!(
+        cache
+            .setting_entries()
+            .contains(&("frequency_half_life_days", "7".to_string()))
+    );
+}
+
+#[test]
+fn collapsing_repeats_follows_the_setting() {
+    let mut cache = CommandCache::new();
+    cache.record_correction("gti", "git");
+    cache.record_correction("gti", "git");
+    assert_eq!(cache.get_history_size(), 1);
+
+    assert!(cache.set_setting("collapse_repeated_history", "maybe").is_err());
+    cache.set_setting("collapse_repeated_history", "false").unwrap();
+    cache.record_correction("gti", "git");
+    assert_eq!(cache.get_history_size(), 2);
+    assert!(!cache.settings().collapse_repeated_history);
+}
+
+#[test]
+fn home_paths_are_corrected_in_place() {
+    let home = tempfile::tempdir().unwrap();
+    std::fs::create_dir(home.path().join("Documents")).unwrap();
+
+    assert_eq!(
+        correct_home_paths_in("cat  ~/Documnets/todo '~/Documnets/x'", home.path()),
+        "cat  ~/Documents/todo '~/Documnets/x'"
+    );
+    assert_eq!(
+        correct_home_paths_in("cat ~/Documents/todo", home.path()),
+        "cat ~/Documents/todo"
+    );
+}
+
+#[test]
+fn clear_summary_counts_what_clearing_memory_removes() {
+    let mut cache = CommandCache::new();
+    cache.insert("git");
+    cache
+        .import_corrections([("gti", "git status", Some("git")), ("sl", "ls", None)])
+        .unwrap();
+    cache.set_setting("decline_cooldown_secs", "60").unwrap();
+    cache.decline_suggestion("gti", "gzip");
+    cache.learn_from_command("mytool --verbose");
+    cache.record_correction("gti", "git");
+
+    let summary = cache.clear_summary(true);
+    assert_eq!(summary.learned_corrections, 2);
+    assert_eq!(summary.categories, 1);
+    assert_eq!(summary.declined_suggestions, 1);
+    assert_eq!(summary.learned_patterns, 1);
+    assert_eq!(summary.history_entries, 1);
+    assert_eq!(
+        cache.clear_summary(false),
+        ClearSummary { commands: summary.commands, ..ClearSummary::default() }
+    );
+
+    cache.clear_memory();
+    assert_eq!(cache.clear_summary(true), ClearSummary::default());
+}
+
+#[test]
+fn muted_typos_stay_hidden_from_later_corrections() {
+    let mut cache = CommandCache::new();
+    assert!(cache.mute_typo("gti"));
+    assert!(!cache.mute_typo("gti"));
+
+    cache.record_correction("gti", "git");
+    cache.record_correction("sl", "ls");
+    assert_eq!(cache.get_frequent_typos(5), [("sl".to_string(), 1)]);
+    assert_eq!(cache.get_frequent_corrections(5), [("ls".to_string(), 1)]);
+    assert_eq!(cache.get_history_size(), 2);
+}
+
+#[test]
+fn path_arguments_are_preserved_unless_quoted() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("Notes.txt"), "").unwrap();
+    std::fs::write(dir.path().join("README.md"), "").unwrap();
+    let notes = dir.path().join("Notes.txt").display().to_string();
+    let readme = dir.path().join("README.md").display().to_string();
+    let readme_typo = dir.path().join("README.Md").display().to_string();
+
+    let mut cache = CommandCache::new();
+    assert_eq!(cache.preserve_path_arguments(&format!("cta {notes}"), "cat x"), None);
+    cache.set_setting("argument_case_correction", "true").unwrap();
+
+    assert_eq!(
+        cache.preserve_path_arguments(&format!("cta  {notes}"), "cat  Nodes.txt"),
+        Some(format!("cat  {notes}"))
+    );
+    assert_eq!(
+        cache.preserve_path_arguments(
+            &format!("cta {readme_typo} -n"),
+            &format!("cat {readme_typo} -n")
+        ),
+        Some(format!("cat {readme} -n"))
+    );
+    assert_eq!(
+        cache.preserve_path_arguments(
+            &format!("cta '{readme_typo}'"),
+            &format!("cat '{readme_typo}'")
+        ),
+        None
+    );
+    assert_eq!(
+        cache.preserve_path_arguments(&format!("cta {notes}"), &format!("cat -- {notes}")),
+        None
+    );
+}
+
+#[test]
+fn saved_settings_are_read_without_loading_the_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    assert!(CommandCache::saved_settings(&path).symlink_target_commands);
+
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(path.clone());
+    cache.set_setting("symlink_target_commands", "false").unwrap();
+    cache.save().unwrap();
+    assert!(!CommandCache::saved_settings(&path).symlink_target_commands);
+}
+
+#[test]
+fn cd_targets_are_corrected_against_cdpath() {
+    let projects = tempfile::tempdir().unwrap();
+    fs::create_dir(projects.path().join("super_snoofer")).unwrap();
+    let cdpath = std::env::join_paths(["", projects.path().to_str().unwrap()]).unwrap();
+    let search_dirs = crate::command::cd_search_dirs_in(Some(&cdpath));
+    assert_eq!(search_dirs, [PathBuf::from("."), projects.path().to_path_buf()]);
+
+    assert_eq!(
+        correct_cd_line_in("cd supr_snoofer/src", &search_dirs).as_deref(),
+        Some("cd super_snoofer/src")
+    );
+    assert_eq!(correct_cd_line_in("cd super_snoofer", &search_dirs), None);
+    assert_eq!(correct_cd_line_in("ls supr_snoofer", &search_dirs), None);
+}
+
+#[test]
+fn corrections_are_listed_and_unlearned_by_category() {
+    let mut cache = CommandCache::new();
+    cache
+        .import_corrections([
+            ("gti", "git", Some("git")),
+            ("gitst", "git status", Some("git")),
+            ("sl", "ls", None),
+        ])
+        .unwrap();
+    assert!(cache.set_correction_category("sl", Some("files")));
+    assert!(!cache.set_correction_category("unknown", Some("files")));
+
+    assert_eq!(
+        cache.learned_corrections(Some("git")),
+        [("gitst", "git status", Some("git")), ("gti", "git", Some("git"))]
+    );
+    assert_eq!(cache.learned_corrections(None).len(), 3);
+    assert!(cache.learned_corrections(Some("docker")).is_empty());
+
+    assert_eq!(cache.unlearn_category("git"), 2);
+    assert_eq!(cache.unlearn_category("git"), 0);
+    assert_eq!(cache.learned_corrections(None), [("sl", "ls", Some("files"))]);
+}
+
+#[test]
+fn relearning_a_correction_replaces_its_category() {
+    let mut cache = CommandCache::new();
+    cache.learn_correction("gti", "git", Some("git")).unwrap();
+    assert_eq!(cache.learned_corrections(None), [("gti", "git", Some("git"))]);
+
+    cache.learn_correction("gti", "git", Some("vcs")).unwrap();
+    assert_eq!(cache.learned_corrections(None), [("gti", "git", Some("vcs"))]);
+
+    cache.learn_correction("gti", "git", None).unwrap();
+    assert_eq!(cache.learned_corrections(None), [("gti", "git", None)]);
+}
+
+#[test]
+fn rediscovered_tool_subcommands_replace_the_old_ones() {
+    let mut cache = CommandCache::new();
+    cache.tool_subcommands = HashMap::from([("git".to_string(), vec!["lg".to_string()])]);
+    cache.register_tool_subcommands();
+    let knows = |cache: &CommandCache, subcommand: &str| {
+        cache
+            .command_patterns
+            .get_args_for_command("git")
+            .is_some_and(|mut args| args.any(|arg| arg == subcommand))
+    };
+    assert!(knows(&cache, "lg"));
+
+    cache.tool_subcommands = HashMap::from([("git".to_string(), vec!["st".to_string()])]);
+    cache.register_tool_subcommands();
+    assert!(!knows(&cache, "lg"));
+    assert!(knows(&cache, "st"));
+}
+
+/// A cache knowing `commands` as PATH commands
+fn cache_with(commands: &[&str]) -> CommandCache {
+    let mut cache = CommandCache::new();
+    for command in commands {
+        cache.insert(command);
+    }
+    cache
+}
+
+#[test]
+fn wrapper_arguments_are_kept_while_the_command_is_corrected() {
+    let cache = cache_with(&["watch", "timeout", "git", "cargo"]);
+    assert_eq!(
+        cache.fix_command_line("watch -n 5 gti status").as_deref(),
+        Some("watch -n 5 git status")
+    );
+    assert_eq!(
+        cache.fix_command_line("timeout 10 carg build").as_deref(),
+        Some("timeout 10 cargo build")
+    );
+}
+
+#[test]
+fn surrounding_whitespace_is_trimmed_from_corrections() {
+    let cache = cache_with(&["git"]);
+    assert_eq!(
+        cache.fix_command_line("   gti status  ").as_deref(),
+        Some("git status")
+    );
+}
+
+#[test]
+fn declined_suggestions_come_back_after_the_cooldown() {
+    let mut cache = cache_with(&["git"]);
+    cache.set_setting("decline_cooldown_secs", "60").unwrap();
+    assert_eq!(cache.find_similar("gti").as_deref(), Some("git"));
+
+    cache.decline_suggestion("gti", "git");
+    assert_eq!(cache.find_similar("gti"), None);
+
+    for declined in cache.declined_suggestions.values_mut() {
+        for when in declined.values_mut() {
+            *when -= Duration::from_mins(2);
+        }
+    }
+    assert_eq!(cache.find_similar("gti").as_deref(), Some("git"));
+}
+
+#[test]
+fn misspelled_sudo_is_corrected_with_the_command_it_runs() {
+    let cache = cache_with(&["sudo", "apt"]);
+    assert_eq!(
+        cache.fix_command_line("sduo apt instal foo").as_deref(),
+        Some("sudo apt install foo")
+    );
+    assert_eq!(crate::command::correct_wrapper_typo("suod ls").as_deref(), Some("sudo ls"));
+}
+
+#[test]
+fn quoted_remote_commands_are_left_alone() {
+    let cache = cache_with(&["ssh", "git", "sh"]);
+    let fixed = |line| cache.fix_command_line(line).unwrap_or_else(|| line.to_string());
+    assert_eq!(fixed("ssh host 'gti status'"), "ssh host 'gti status'");
+    assert_eq!(fixed("shh host 'gti status'"), "ssh host 'gti status'");
+    assert_eq!(fixed("sh -c 'gti status'"), "sh -c 'gti status'");
+}
+
+#[test]
+fn separator_typos_resolve_to_the_installed_name() {
+    let score = crate::utils::calculate_similarity("docker_compose", "docker-compose");
+    assert!((score - 1.0).abs() < f64::EPSILON);
+    let cache = cache_with(&["docker-compose", "docker", "pre-commit"]);
+    assert_eq!(cache.find_similar("docker_compose").as_deref(), Some("docker-compose"));
+    assert_eq!(cache.find_similar("pre_commit").as_deref(), Some("pre-commit"));
+    assert_eq!(
+        cache.fix_command_line("docker_compose up").as_deref(),
+        Some("docker-compose up")
+    );
+}
+
+#[test]
+fn binary_cache_round_trips_and_replaces_the_json_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join(CACHE_FILE);
+    let mut cache = CommandCache::new();
+    cache.cache_path = Some(json_path.clone());
+    cache.learn_correction("gti", "git", None).unwrap();
+    cache.record_correction("gti", "git");
+    cache.set_setting("cache_format", "binary").unwrap();
+    cache.save().unwrap();
+
+    let binary_path = json_path.with_extension(CacheFormat::Binary.extension());
+    assert!(binary_path.exists());
+    assert!(!json_path.exists());
+    let loaded = CommandCache::load_from_path(&binary_path).unwrap();
+    assert_eq!(
+        loaded.get_direct_correction("gti").map(String::as_str),
+        Some("git")
+    );
+    assert_eq!(loaded.get_history_size(), 1);
+    assert_eq!(loaded.settings().cache_format, CacheFormat::Binary);
+}
+
+#[test]
+fn shell_keywords_before_a_command_are_left_alone() {
+    let mut cache = cache_with(&["ps", "od", "find", "docker"]);
+    cache.set_setting("keyword_correction", "true").unwrap();
+    assert_eq!(cache.fix_command_line("do ps"), None);
+    assert_eq!(cache.fix_command_line("if ps"), None);
+    assert_eq!(
+        cache.fix_command_line("fi foo.txt").as_deref(),
+        Some("find foo.txt")
+    );
+
+    cache.learn_correction("do", "docker", None).unwrap();
+    assert_eq!(cache.fix_command_line("do ps").as_deref(), Some("docker ps"));
+}
+
+#[test]
+fn frequency_listings_stop_at_the_limit() {
+    let mut cache = CommandCache::new();
+    for typo in ["gti", "gi", "tgi", "igt", "sl", "lsa", "cta", "grpe"] {
+        cache.record_correction(typo, "git");
+    }
+    assert_eq!(cache.get_frequent_typos(5).len(), 5);
+    assert_eq!(cache.get_frequent_typos(usize::MAX).len(), 8);
+}
+
+#[test]
+fn bare_subcommands_imply_their_parent_command() {
+    let mut cache = cache_with(&["git", "cargo"]);
+    assert_eq!(cache.fix_command_line("commit -m \"x\""), None);
+
+    cache.set_setting("bare_subcommands", "true").unwrap();
+    assert_eq!(
+        cache.fix_command_line("commit -m \"x\"").as_deref(),
+        Some("git commit -m \"x\"")
+    );
+    assert_eq!(cache.fix_command_line("clippy --fix").as_deref(), Some("cargo clippy --fix"));
+    // `add` is a subcommand of both, so it implies neither
+    assert_eq!(cache.imply_parent_command("add src"), None);
+
+    cache.set_setting("bare_subcommand_parents", "cargo").unwrap();
+    assert_eq!(cache.imply_parent_command("commit -m \"x\""), None);
+}
+
+#[test]
+fn subcommands_of_valid_commands_are_corrected() {
+    let cache = cache_with(&["cargo"]);
+    assert_eq!(cache.fix_command_line("cargo biuld").as_deref(), Some("cargo build"));
+    assert_eq!(
+        crate::command::fix_command_line("cargo biuld", |_| None, &CommandPatterns::new())
+            .as_deref(),
+        Some("cargo build")
+    );
+}
+
+#[test]
+fn short_typos_prefer_the_closest_length_candidate() {
+    let mut cache = cache_with(&["git", "gitk", "gitweb"]);
+    cache.set_setting("min_correction_length", "2").unwrap();
+    cache.set_similarity_threshold(0.3);
+    let matches = cache.get_closest_matches("gi");
+    assert_eq!(matches.first().map(|(name, _)| name.as_str()), Some("git"));
+}
+
+#[test]
+fn frequent_corrections_rank_first_among_equals() {
+    let mut cache = cache_with(&["pip", "pig"]);
+    cache.set_similarity_threshold(0.3);
+    let best = |cache: &CommandCache| cache.get_closest_matches("pif")[0].0.clone();
+
+    cache.record_correction("pif", "pig");
+    assert_eq!(best(&cache), "pig");
+
+    for _ in 0..5 {
+        cache.record_correction("pif", "pip");
+    }
+    assert_eq!(best(&cache), "pip");
+}
+
+#[test]
+fn caps_lock_lines_are_lowercased() {
+    let cache = cache_with(&["git"]);
+    assert_eq!(cache.fix_command_line("GIT STATUS").as_deref(), Some("git status"));
+}
+
+#[test]
+fn failed_alias_parse_leaves_the_timestamp_stale() {
+    let mut cache = CommandCache::new();
+    cache.shell_aliases.insert("g".to_string(), "git".to_string());
+
+    cache.apply_parsed_aliases(Err(anyhow::anyhow!("Could not find home directory")));
+    assert!(cache.should_update_aliases());
+    assert_eq!(cache.shell_aliases.get("g").map(String::as_str), Some("git"));
+
+    cache.apply_parsed_aliases(Ok(HashMap::new()));
+    assert!(!cache.should_update_aliases());
+    assert!(cache.shell_aliases.is_empty());
+}
+
+#[test]
+fn learned_subcommands_of_unknown_tools_survive_a_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    let mut cache = cache_with(&["mycmd"]);
+    cache.cache_path = Some(path.clone());
+    cache.learn_from_command("mycmd deploy --force");
+    cache.learn_from_command("mycmd rollback");
+    cache.save().unwrap();
+
+    let cache = CommandCache::load_from_path(&path).unwrap();
+    assert_eq!(cache.fix_command_line("mycmd deplyo").as_deref(), Some("mycmd deploy"));
+    assert_eq!(
+        cache.fix_command_line("mycmd rollbakc --forec").as_deref(),
+        Some("mycmd rollback --force")
+    );
+}
+
+#[test]
+fn reordered_lines_match_their_learned_line_by_trigrams() {
+    let mut cache = CommandCache::new();
+    cache
+        .learn_correction(
+            "kubeclt get pods --namespace production --output wide",
+            "kubectl get pods --namespace production --output wide",
+            None,
+        )
+        .unwrap();
+    let reordered = "kubeclt get pods --output wide --namespace production";
+    let unrelated = "git log --oneline --graph --all --decorate";
+
+    assert_eq!(
+        cache.learned_line(reordered).as_deref(),
+        Some("kubectl get pods --output wide --namespace production")
+    );
+    assert_eq!(cache.learned_line(unrelated), None);
+
+    cache.set_setting("line_similarity_algorithm", "levenshtein").unwrap();
+    assert_eq!(cache.learned_line(reordered), None);
+    assert_eq!(cache.learned_line(unrelated), None);
+}
+
+#[test]
+fn match_precedence_decides_between_learned_and_exact_alias() {
+    let mut cache = cache_with(&["gh"]);
+    cache.shell_aliases.insert("g".to_string(), "git".to_string());
+    cache.learned_corrections.insert("g".to_string(), "gh".to_string());
+
+    assert_eq!(cache.find_similar("g").as_deref(), Some("g"));
+    assert_eq!(cache.match_source("g"), Some(MatchSource::ExactAlias));
+
+    cache
+        .set_setting("match_precedence", "learned,exact-alias,exact-command,fuzzy-alias")
+        .unwrap();
+    assert_eq!(cache.find_similar("g").as_deref(), Some("gh"));
+    assert_eq!(cache.match_source("g"), Some(MatchSource::Learned));
+}
+
+#[test]
+fn similarity_algorithm_setting_changes_the_correction() {
+    let mut cache = cache_with(&["python", "pyhon"]);
+    // One transposition away from `python`, one deletion away from `pyhon`
+    assert_eq!(cache.find_similar("pyhton").as_deref(), Some("python"));
+
+    cache.set_setting("similarity_algorithm", "levenshtein").unwrap();
+    assert_eq!(cache.find_similar("pyhton").as_deref(), Some("pyhon"));
+}
+
+#[test]
+fn learned_runner_packages_are_corrected_after_runners() {
+    let mut cache = cache_with(&["npx", "pnpm"]);
+    cache.learn_correction("npx acme-scafold", "npx acme-scaffold", None).unwrap();
+
+    assert_eq!(
+        cache.fix_command_line("npx acme-scafolld my-app").as_deref(),
+        Some("npx acme-scaffold my-app")
+    );
+    assert_eq!(
+        cache.fix_command_line("pnpm dlx acme-scafold my-app").as_deref(),
+        Some("pnpm dlx acme-scaffold my-app")
+    );
+}
+
+#[test]
+fn single_characters_only_get_exact_and_learned_matches() {
+    let mut cache = cache_with(&["x", "xz", "ls"]);
+    cache.learned_corrections.insert("s".to_string(), "ls".to_string());
+
+    assert_eq!(cache.find_similar("z"), None);
+    cache.set_similarity_threshold(0.0);
+    assert_eq!(cache.get_closest_match("z"), None);
+    cache.set_similarity_threshold(SIMILARITY_THRESHOLD);
+    assert_eq!(cache.find_similar("x").as_deref(), Some("x"));
+    assert_eq!(cache.find_similar("s").as_deref(), Some("ls"));
+
+    cache.set_setting("min_correction_length", "1").unwrap();
+    assert_eq!(cache.find_similar("z").as_deref(), Some("xz"));
+}
+
+#[test]
+fn frequent_flags_are_ranked_by_use() {
+    let mut cache = CommandCache::new();
+    for _ in 0..3 {
+        cache.record_correction("dcoker run -it --rm alpine", "docker run -it --rm alpine");
+    }
+    cache.record_correction("dokcer run --rm -p 80:80 nginx", "docker run --rm -p 80:80 nginx");
+    cache.record_correction("dokcer ps -a", "docker ps -a");
+
+    assert_eq!(cache.frequent_flags_for("docker run"), ["--rm", "-it", "-p"]);
+    assert_eq!(cache.frequent_flags_for("docker ps"), ["-a"]);
+    assert!(cache.frequent_flags_for("git").is_empty());
+}
+
+#[test]
+fn frequent_flags_are_appended_after_positional_arguments() {
+    let mut cache = CommandCache::new();
+    cache.settings.append_frequent_flags = true;
+    cache.record_correction("dcoker run -it --rm alpine", "docker run -it --rm alpine");
+    cache.record_correction("dcoker ps -a", "docker ps -a");
+    cache.record_correction("sl -la /tmp", "ls -la /tmp");
+
+    assert_eq!(
+        cache.generate_full_completion("docker run nginx"),
+        "docker run nginx --rm -it"
+    );
+    assert_eq!(
+        cache.generate_full_completion("docker run --rm nginx"),
+        "docker run --rm nginx -it"
+    );
+    assert_eq!(cache.generate_full_completion("ls src"), "ls src -la");
+}
+
+#[test]
+fn stuttered_letters_are_corrected() {
+    let cache = cache_with(&["git", "ls", "npm"]);
+    assert_eq!(cache.find_similar("giiit").as_deref(), Some("git"));
+    assert_eq!(cache.fix_command_line("giiit statuuus").as_deref(), Some("git status"));
+
+    // Legitimate double letters are kept
+    assert_eq!(crate::utils::collapse_repeated_chars("giiit statuuus"), "giit statuus");
+    assert_eq!(crate::utils::collapse_repeated_chars("npm ls --all"), "npm ls --all");
+}
+
+#[test]
+fn curated_arg_typos_survive_a_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(CACHE_FILE);
+    let mut cache = cache_with(&["git", "cargo", "docker"]);
+    cache.cache_path = Some(path.clone());
+    cache.save().unwrap();
+
+    let loaded = CommandCache::load_from_path(&path).unwrap();
+    assert_eq!(loaded.fix_command_line("git stauts").as_deref(), Some("git status"));
+    assert_eq!(loaded.fix_command_line("cargo isntall").as_deref(), Some("cargo install"));
+    assert_eq!(loaded.fix_command_line("docker iamges").as_deref(), Some("docker images"));
+}
@@ -0,0 +1,227 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Step-by-step traces of how a command line gets corrected
+//!
+//! A trace follows the same lookups as [`CommandCache::fix_command_line`]: each
+//! correction source in precedence order for the command, the fuzzy candidates with
+//! their scores against the threshold, the arguments and flags, then every rewriting
+//! step and the final decision.
+
+use super::CommandCache;
+use crate::{command::CommandPatterns, settings::MatchSource, utils::calculate_similarity_with};
+use std::fmt;
+
+/// Number of fuzzy candidates listed per source
+const TRACE_CANDIDATES: usize = 5;
+
+/// A step of a correction trace, with the steps it took in turn
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceNode {
+    /// What happened at this step
+    pub label: String,
+    /// The steps taken within this one
+    pub children: Vec<TraceNode>,
+}
+
+impl TraceNode {
+    /// Create a step without children
+    #[must_use]
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Add a step without children
+    fn leaf(&mut self, label: impl Into<String>) {
+        self.children.push(Self::new(label));
+    }
+
+    /// Write the children below `prefix`, drawing the tree's branches
+    fn fmt_children(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        for (index, child) in self.children.iter().enumerate() {
+            let last = index + 1 == self.children.len();
+            let (branch, indent) = if last { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+            writeln!(f, "{prefix}{branch}{}", child.label)?;
+            child.fmt_children(f, &format!("{prefix}{indent}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TraceNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.label)?;
+        self.fmt_children(f, "")
+    }
+}
+
+impl CommandCache {
+    /// Trace the decisions [`Self::fix_command_line`] makes for a command line
+    #[must_use]
+    pub fn trace(&self, command_line: &str) -> TraceNode {
+        let command_line = command_line.trim();
+        let mut root = TraceNode::new(format!("`{command_line}`"));
+        let Some(command) = command_line.split_whitespace().next() else {
+            root.leaf("Decision: nothing to correct in an empty line");
+            return root;
+        };
+
+        let (command_node, resolved) = self.trace_command(command);
+        root.children.push(command_node);
+        let arguments = self.trace_arguments(command_line, resolved.as_deref().unwrap_or(command));
+        if !arguments.children.is_empty() {
+            root.children.push(arguments);
+        }
+
+        let mut steps = TraceNode::new("Correction steps");
+        let decision = self.fix_command_line_traced(command_line, &mut |name, line| {
+            steps.leaf(format!("{name} → `{line}`"));
+        });
+        if steps.children.is_empty() {
+            steps.leaf("no step rewrote the line");
+        }
+        root.children.push(steps);
+
+        root.leaf(match decision {
+            Some(corrected) if corrected != command_line => {
+                format!("Decision: correct to `{corrected}`")
+            }
+            Some(_) => "Decision: keep the line as typed".to_string(),
+            None => "Decision: no correction found".to_string(),
+        });
+        root
+    }
+
+    /// Trace the correction sources for a command in precedence order, like
+    /// `find_with_source`, returning the trace and the command it resolves to
+    fn trace_command(&self, command: &str) -> (TraceNode, Option<String>) {
+        let mut node = TraceNode::new(format!("Command `{command}`"));
+        let lowered = command.to_lowercase();
+        let mut resolved = None;
+
+        for (rank, &source) in self.settings.match_precedence.0.iter().enumerate() {
+            let mut step = TraceNode::new(format!("{}. {source}", rank + 1));
+            if resolved.is_some() {
+                step.label.push_str(": skipped");
+                node.children.push(step);
+                continue;
+            }
+
+            let correction = match source {
+                MatchSource::ExactAlias => [command, lowered.as_str()]
+                    .into_iter()
+                    .find(|name| self.shell_aliases.contains_key(*name))
+                    .map(str::to_string),
+                MatchSource::ExactCommand => [command, lowered.as_str()]
+                    .into_iter()
+                    .find(|name| self.commands.contains(*name))
+                    .map(str::to_string),
+                MatchSource::Learned => self.learned_corrections.get(command).cloned(),
+                MatchSource::FuzzyAlias | MatchSource::FuzzyCommand => {
+                    if self.is_too_short_to_correct(command) {
+                        step.leaf(format!(
+                            "shorter than min_correction_length ({})",
+                            self.settings.min_correction_length
+                        ));
+                        None
+                    } else {
+                        self.trace_fuzzy_candidates(command, source, &mut step);
+                        self.fuzzy_match(command, source)
+                    }
+                }
+            };
+
+            match correction {
+                Some(correction) if self.is_cooling_down(command, &correction) => {
+                    step.label = format!(
+                        "{}: `{correction}` was declined recently, skipped",
+                        step.label
+                    );
+                }
+                Some(correction) => {
+                    step.label = format!("{}: match → `{correction}`", step.label);
+                    resolved = Some(correction);
+                }
+                None => step.label.push_str(": no match"),
+            }
+            node.children.push(step);
+        }
+
+        if resolved.is_none() {
+            node.leaf("no source matched, the command is kept as typed");
+        }
+        (node, resolved)
+    }
+
+    /// List the best fuzzy candidates of a source with their scores against the threshold
+    fn trace_fuzzy_candidates(&self, command: &str, source: MatchSource, step: &mut TraceNode) {
+        let candidates: Vec<&String> = match source {
+            MatchSource::FuzzyAlias => self.shell_aliases.keys().collect(),
+            MatchSource::FuzzyCommand => self.commands.iter().chain(&self.man_commands).collect(),
+            _ => return,
+        };
+        let algorithm = self.settings.similarity_algorithm;
+        let threshold = self.similarity_threshold;
+        let mut scored: Vec<(f64, &String)> = candidates
+            .into_iter()
+            .map(|candidate| {
+                (
+                    calculate_similarity_with(command, candidate, algorithm),
+                    candidate,
+                )
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        let mut listed = TraceNode::new(format!(
+            "{algorithm} candidates (threshold {threshold:.2})"
+        ));
+        for (score, candidate) in scored.into_iter().take(TRACE_CANDIDATES) {
+            let verdict = if self.is_cooling_down(command, candidate) {
+                "declined recently"
+            } else if score >= threshold {
+                "above threshold"
+            } else {
+                "below threshold"
+            };
+            listed.leaf(format!("`{candidate}` {score:.3} ({verdict})"));
+        }
+        if listed.children.is_empty() {
+            listed.leaf("no candidates");
+        }
+        step.children.push(listed);
+    }
+
+    /// Trace how each argument and flag is matched against the command's known ones
+    fn trace_arguments(&self, command_line: &str, command: &str) -> TraceNode {
+        let mut node = TraceNode::new(format!("Arguments of `{command}`"));
+        let (command_line, quoted) = crate::command::split_quoted_command(command_line)
+            .map_or((command_line, None), |(head, quoted)| (head, Some(quoted)));
+
+        for arg in command_line.split_whitespace().skip(1) {
+            let label = if arg.starts_with('-') {
+                if self.command_patterns.is_known_flag(command, arg) {
+                    format!("flag `{arg}`: known")
+                } else if let Some(flag) = self.command_patterns.find_similar_flag(command, arg, 0.6)
+                {
+                    format!("flag `{arg}`: similar to `{flag}`")
+                } else {
+                    format!("flag `{arg}`: unknown, kept")
+                }
+            } else {
+                match CommandPatterns::find_similar_arg(command, arg, &self.command_patterns) {
+                    Some(known) if known == arg => format!("`{arg}`: known argument"),
+                    Some(known) => format!("`{arg}`: similar to `{known}`"),
+                    None => format!("`{arg}`: no known argument, kept"),
+                }
+            };
+            node.leaf(label);
+        }
+        if let Some(quoted) = quoted {
+            node.leaf(format!("{quoted}: quoted command, never touched"));
+        }
+        node
+    }
+}
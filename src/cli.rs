@@ -1,11 +1,16 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, error::ErrorKind};
 
-use crate::ollama::{DEFAULT_MODEL, DEFAULT_CODE_MODEL};
+use std::path::PathBuf;
+
+use super_snoofer::display::{LineFormat, HISTORY_DISPLAY_LIMIT};
+use super_snoofer::import::ImportFormat;
+use super_snoofer::ollama::{DEFAULT_MODEL, DEFAULT_CODE_MODEL};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[allow(clippy::struct_excessive_bools)] // Independent command line flags
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -26,6 +31,22 @@ pub struct Cli {
     #[arg(long, default_value_t = DEFAULT_CODE_MODEL.to_string())]
     pub code_model: String,
 
+    /// Correct the last command (given after `--`, or read from the shell history) and run it on Enter
+    #[arg(long)]
+    pub thefuck_style: bool,
+
+    /// Disable all AI/network features (also enabled by `SUPER_SNOOFER_OFFLINE=1`)
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// Print history and frequency listings as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// Minimum similarity (0.0 to 1.0) for fuzzy corrections, saved with the cache
+    #[arg(long, global = true, value_parser = parse_threshold)]
+    pub threshold: Option<f64>,
+
     /// Command line to check (for command not found handler)
     #[arg(name = "command", last = true, allow_hyphen_values = true)]
     pub command_to_check: Vec<String>,
@@ -42,15 +63,59 @@ pub enum Commands {
         command: String,
     },
     /// Clear the command cache but keep learned corrections
-    ResetCache,
+    ResetCache {
+        /// Only show what would be cleared
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Clear both the command cache and learned corrections
-    ResetMemory,
+    ResetMemory {
+        /// Only show what would be cleared
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check that learned corrections are saved to and loaded from the cache file
+    SelfTest,
+    /// Re-read shell aliases now instead of waiting for the daily refresh
+    RefreshAliases,
+    /// Rebuild the cached PATH commands and shell aliases now
+    RefreshCache {
+        /// Refresh in a detached process without blocking the shell
+        #[arg(long)]
+        background: bool,
+    },
     /// Display your recent command corrections
     History,
+    /// Display the typos you made before getting a specific command right
+    HistoryFor {
+        /// Correction target to look up (e.g. `git`)
+        command: String,
+    },
     /// Display your most common typos
-    FrequentTypos,
+    FrequentTypos {
+        /// Maximum number of typos to show
+        #[arg(short, long, default_value_t = HISTORY_DISPLAY_LIMIT)]
+        limit: usize,
+        /// Show every typo at once, without paging
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
+    },
     /// Display your most frequently used corrections
-    FrequentCorrections,
+    FrequentCorrections {
+        /// Maximum number of corrections to show
+        #[arg(short, long, default_value_t = HISTORY_DISPLAY_LIMIT)]
+        limit: usize,
+        /// Show every correction at once, without paging
+        #[arg(long, conflicts_with = "limit")]
+        all: bool,
+    },
+    /// Show how often suggestions from each correction source are accepted
+    SourceStats,
+    /// Hide a typo from the frequent typos and corrections lists, keeping its history
+    Mute {
+        /// The typo to hide from the statistics
+        typo: String,
+    },
     /// Clear your command history
     ClearHistory,
     /// Enable command history tracking
@@ -72,17 +137,124 @@ pub enum Commands {
         /// Command line to check
         command: String,
     },
+    /// Print the correction for a command line as one machine-readable record (for shell widgets)
+    CheckLine {
+        /// Command line to check
+        command: String,
+        /// Output format: tsv or json
+        #[arg(long, default_value_t = LineFormat::Tsv)]
+        format: LineFormat,
+    },
     /// Process a full command line (for shell integration)
     FullCommand {
         /// Command line to process
         command: String,
     },
+    /// Browse, add, edit and delete your shell aliases
+    Aliases,
+    /// List the shell aliases you haven't used, to help declutter your shell config
+    UnusedAliases {
+        /// Also list aliases not used in this many days
+        #[arg(long, value_name = "DAYS")]
+        since: Option<u64>,
+    },
+    /// Record that a shell alias was run (for shell integration)
+    #[command(hide = true)]
+    RecordAliasUse {
+        /// Alias that was run
+        alias: String,
+    },
+    /// Fix a command interactively, previewing the correction as you type
+    Fix {
+        /// Command line to start with
+        command_line: Option<String>,
+    },
+    /// Print the corrected command line, with your habitual flags if enabled (for shell integration)
+    Complete {
+        /// Command line to complete
+        command_line: String,
+    },
     /// Manually teach a command correction
     LearnCorrection {
         /// The typo to correct
         typo: String,
         /// The correct command
         command: String,
+        /// Tag the correction with a category, like `work` or `personal`
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// Forget a learned command correction, or every correction in a category
+    Unlearn {
+        /// The typo whose correction should be forgotten
+        #[arg(required_unless_present = "category")]
+        typo: Option<String>,
+        /// Forget every correction tagged with this category instead
+        #[arg(long, conflicts_with = "typo")]
+        category: Option<String>,
+    },
+    /// Show each step the correction logic takes for a command line, as a tree
+    Trace {
+        /// Command line to trace
+        command: String,
+    },
+    /// Import corrections or aliases exported from another correction tool
+    Import {
+        /// File to import: `typo,correction[,category]` lines, or `alias name='command'` lines
+        path: PathBuf,
+        /// Format of the file: csv or aliases
+        #[arg(long, default_value_t = ImportFormat::Csv)]
+        format: ImportFormat,
+        /// Category for imported corrections that don't name their own
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// List your learned command corrections
+    Corrections {
+        /// Only list corrections tagged with this category
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// Display your recent AI prompts and responses
+    AiHistory {
+        /// Maximum number of interactions to show
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Enable logging of AI prompts and responses
+    EnableAiLog,
+    /// Disable logging of AI prompts and responses
+    DisableAiLog,
+    /// Show or change settings (e.g. `config similarity_algorithm damerau-levenshtein`)
+    Config {
+        /// Setting to show or change
+        key: Option<String>,
+        /// New value for the setting
+        value: Option<String>,
+    },
+    /// Show how similar two strings are under each similarity metric
+    Similarity {
+        /// First string to compare
+        a: String,
+        /// Second string to compare
+        b: String,
+    },
+    /// Correct the command from pasted "command not found" error output
+    FromError {
+        /// Error output to parse (read from stdin if omitted)
+        text: Option<String>,
+    },
+    /// Measure command correction latency against your real command cache
+    Bench {
+        /// Number of times each sample command line is corrected
+        #[arg(short, long, default_value = "100")]
+        iterations: usize,
+    },
+    /// Run a background daemon that keeps the command cache warm
+    Daemon {
+        /// Stop the running daemon instead of starting one
+        #[arg(long)]
+        stop: bool,
     },
     /// Chat with AI about super snoofer
     Prompt {
@@ -106,20 +278,126 @@ impl Cli {
         let args: Vec<String> = std::env::args().collect();
         
         // If we have a -- separator, everything after it is a command to check
-        if let Some(sep_pos) = args.iter().position(|x| x == "--") {
-            if sep_pos + 1 < args.len() {
-                return Self {
-                    command: None,
-                    prompt: None,
-                    codestral: false,
-                    standard_model: DEFAULT_MODEL.to_string(),
-                    code_model: DEFAULT_CODE_MODEL.to_string(),
-                    command_to_check: args[sep_pos + 1..].to_vec(),
-                };
-            }
+        if let Some(cli) = Self::with_command_to_check(&args) {
+            return cli;
         }
         
         // Otherwise, use normal clap parsing
         Self::parse()
     }
-} 
\ No newline at end of file
+
+    /// Build the arguments for a command given after a `--` separator, keeping the flags given
+    /// before it
+    fn with_command_to_check(args: &[String]) -> Option<Self> {
+        let sep_pos = args.iter().position(|x| x == "--")?;
+        if sep_pos + 1 >= args.len() {
+            return None;
+        }
+        let flag = |name: &str| args[..sep_pos].iter().any(|arg| arg == name);
+        Some(Self {
+            command: None,
+            prompt: None,
+            codestral: false,
+            standard_model: DEFAULT_MODEL.to_string(),
+            code_model: DEFAULT_CODE_MODEL.to_string(),
+            offline: flag("--offline"),
+            json: flag("--json"),
+            threshold: threshold_before_separator(&args[..sep_pos]).unwrap_or_else(|error| {
+                Self::command().error(ErrorKind::ValueValidation, error).exit()
+            }),
+            thefuck_style: flag("--thefuck-style"),
+            command_to_check: args[sep_pos + 1..].to_vec(),
+        })
+    }
+} 
+
+/// Find the `--threshold` given before the `--` separator, as `--threshold 0.4` or
+/// `--threshold=0.4`
+fn threshold_before_separator(args: &[String]) -> Result<Option<f64>, String> {
+    let mut threshold = None;
+    for (pos, arg) in args.iter().enumerate() {
+        let value = match arg.strip_prefix("--threshold") {
+            Some("") => args
+                .get(pos + 1)
+                .ok_or("a value is required for '--threshold'")?,
+            Some(value) => match value.strip_prefix('=') {
+                Some(value) => value,
+                None => continue,
+            },
+            None => continue,
+        };
+        threshold = Some(
+            parse_threshold(value).map_err(|error| format!("invalid value for '--threshold': {error}"))?,
+        );
+    }
+    Ok(threshold)
+}
+
+/// Parse a similarity threshold, which has to lie between 0.0 and 1.0
+fn parse_threshold(value: &str) -> Result<f64, String> {
+    let threshold: f64 = value
+        .parse()
+        .map_err(|_| format!("expected a number between 0.0 and 1.0, got '{value}'"))?;
+    if (0.0..=1.0).contains(&threshold) {
+        Ok(threshold)
+    } else {
+        Err(format!("expected a number between 0.0 and 1.0, got '{value}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn threshold_is_read_before_the_separator() {
+        assert_eq!(threshold_before_separator(&args(&["snoof"])), Ok(None));
+        assert_eq!(
+            threshold_before_separator(&args(&["snoof", "--threshold", "0.3"])),
+            Ok(Some(0.3))
+        );
+        assert_eq!(
+            threshold_before_separator(&args(&["snoof", "--threshold=0.25"])),
+            Ok(Some(0.25))
+        );
+        assert!(threshold_before_separator(&args(&["snoof", "--threshold", "2"])).is_err());
+        assert!(threshold_before_separator(&args(&["snoof", "--threshold=abc"])).is_err());
+        assert!(threshold_before_separator(&args(&["snoof", "--threshold"])).is_err());
+    }
+
+    #[test]
+    fn flags_before_the_separator_are_kept() {
+        let cli = Cli::with_command_to_check(&args(&["snoof", "--offline", "--json", "--", "]"]))
+            .unwrap();
+        assert!(cli.offline && cli.json && !cli.thefuck_style);
+        assert_eq!(cli.command_to_check, ["]"]);
+
+        let cli = Cli::with_command_to_check(&args(&["snoof", "--", "gti", "--offline"])).unwrap();
+        assert!(!cli.offline && !cli.json);
+        assert_eq!(cli.command_to_check, ["gti", "--offline"]);
+
+        assert!(Cli::with_command_to_check(&args(&["snoof", "--offline", "--"])).is_none());
+        assert!(Cli::with_command_to_check(&args(&["snoof", "--offline"])).is_none());
+    }
+    #[test]
+    fn frequency_listings_take_a_limit_or_all() {
+        let limit = |argv: &[&str]| match Cli::try_parse_from(argv).map(|cli| cli.command) {
+            Ok(Some(Commands::FrequentTypos { limit, all })) => Ok((limit, all)),
+            Ok(_) => panic!("not a frequent-typos command"),
+            Err(error) => Err(error.kind()),
+        };
+        assert_eq!(limit(&["snoof", "frequent-typos", "--limit", "5"]), Ok((5, false)));
+        assert_eq!(
+            limit(&["snoof", "frequent-typos"]),
+            Ok((HISTORY_DISPLAY_LIMIT, false))
+        );
+        assert_eq!(
+            limit(&["snoof", "frequent-typos", "--all", "--limit", "5"]),
+            Err(clap::error::ErrorKind::ArgumentConflict)
+        );
+    }
+}
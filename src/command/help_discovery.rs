@@ -0,0 +1,193 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Discovery of a tool's subcommands and flags from its `--help` output
+//!
+//! Tools without a built-in pattern are asked for `--help` the first time a line using
+//! them is corrected. The tool runs with no input and is killed once [`HELP_TIMEOUT`]
+//! passes, so tools that hang or start up instead of printing help can't block a
+//! correction; tools known to act regardless of `--help` are never run at all.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a tool gets to print its help before it's killed
+pub const HELP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most help output read from each of a tool's output streams
+const MAX_HELP_OUTPUT: u64 = 256 * 1024;
+
+/// Commands never run for their help, since some versions act without looking at
+/// their arguments
+pub const HELP_DISCOVERY_DENYLIST: &[&str] = &[
+    "reboot", "shutdown", "halt", "poweroff", "init", "telinit", "rm", "dd", "shred",
+    "wipefs", "fdisk", "parted", "kill", "killall", "pkill", "sudo", "doas", "su", "login",
+    "passwd", "logout", "exit",
+];
+
+/// Subcommands and flags found in a tool's help output
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HelpText {
+    pub subcommands: Vec<String>,
+    pub flags: Vec<String>,
+}
+
+/// Check whether a command may be run with `--help` to discover its subcommands
+#[must_use]
+pub fn may_run_for_help(command: &str) -> bool {
+    !HELP_DISCOVERY_DENYLIST.contains(&command) && !command.starts_with("mkfs")
+}
+
+/// Extract the subcommands and flags from a tool's help output
+///
+/// Subcommands are the first words of the indented lines in sections headed like
+/// `Commands:`, `Available Commands:` or `CORE COMMANDS`, with comma-separated short
+/// forms (`build, b`) included. Flags are taken from the indented lines starting with
+/// `-`, e.g. `-v, --verbose` or `--output=<FILE>`.
+#[must_use]
+pub fn parse_help_text(text: &str) -> HelpText {
+    let mut help = HelpText::default();
+    let mut in_commands = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if !line.starts_with([' ', '\t']) {
+            in_commands = is_commands_header(trimmed);
+            continue;
+        }
+
+        if trimmed.starts_with('-') {
+            for flag in help_line_flags(trimmed) {
+                if !help.flags.contains(&flag) {
+                    help.flags.push(flag);
+                }
+            }
+        } else if in_commands {
+            for subcommand in help_line_subcommands(trimmed) {
+                if !help.subcommands.contains(&subcommand) {
+                    help.subcommands.push(subcommand);
+                }
+            }
+        }
+    }
+
+    help
+}
+
+/// Check whether an unindented help line heads a list of subcommands
+fn is_commands_header(line: &str) -> bool {
+    let is_header = line.ends_with(':')
+        || (line.chars().any(char::is_alphabetic) && line == line.to_uppercase());
+    is_header && line.len() < 40 && line.to_lowercase().contains("command")
+}
+
+/// Get the subcommand names leading an indented line of a commands section
+fn help_line_subcommands(line: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for word in line.split_whitespace() {
+        let name = word.trim_end_matches([',', ':']);
+        let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid {
+            break;
+        }
+        names.push(name.to_string());
+        if !word.ends_with(',') {
+            break;
+        }
+    }
+    names
+}
+
+/// Get the flags listed at the start of an indented help line
+fn help_line_flags(line: &str) -> Vec<String> {
+    line.split([' ', ',', '\t'])
+        .filter(|word| !word.is_empty())
+        .take_while(|word| word.starts_with('-'))
+        .filter_map(|word| {
+            let flag: String = word
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+                .collect();
+            let name = flag.trim_start_matches('-');
+            let dashes = flag.len() - name.len();
+            let valid = !name.is_empty()
+                && (dashes == 2 || (dashes == 1 && name.chars().count() == 1));
+            valid.then_some(flag)
+        })
+        .collect()
+}
+
+/// Run `command --help` and get its output, or `None` if it couldn't run or timed out
+///
+/// The tool gets no input and a pager-free environment; both output streams are read,
+/// since some tools print their help to stderr.
+#[must_use]
+pub fn run_help(command: &str, timeout: Duration) -> Option<String> {
+    let mut child = Command::new(command)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .env("PAGER", "cat")
+        .env("MANPAGER", "cat")
+        .spawn()
+        .ok()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let stdout = child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>);
+    let stderr = child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>);
+    for pipe in [stdout, stderr].into_iter().flatten() {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = pipe.take(MAX_HELP_OUTPUT).read_to_end(&mut output);
+            let _ = sender.send(output);
+        });
+    }
+    drop(sender);
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+
+    // Children the tool left running may hold on to its output, so don't wait forever
+    let grace = deadline
+        .saturating_duration_since(Instant::now())
+        .max(Duration::from_millis(100));
+    let mut output = String::new();
+    while let Ok(stream) = receiver.recv_timeout(grace) {
+        output.push_str(&String::from_utf8_lossy(&stream));
+    }
+    Some(output)
+}
+
+/// Discover a tool's subcommands and flags from its `--help` output
+///
+/// A tool that can't run, times out or prints no usable help gives an empty
+/// [`HelpText`], so the result can be cached either way.
+#[must_use]
+pub fn discover_help(command: &str) -> HelpText {
+    run_help(command, HELP_TIMEOUT)
+        .map(|output| parse_help_text(&output))
+        .unwrap_or_default()
+}
@@ -2,8 +2,14 @@
 
 use crate::utils::remove_trailing_flags;
 use fancy_regex::Regex;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{collections::HashMap, time::SystemTime};
+
+pub mod help_discovery;
+pub mod tool_aliases;
+
+pub use help_discovery::{HelpText, parse_help_text};
+pub use tool_aliases::{SubcommandAliasSource, discover_tool_subcommands};
 
 /// Common commands and their arguments/flags for better correction
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -11,34 +17,516 @@ pub struct CommandPattern {
     pub command: String,
     pub args: Vec<String>,
     pub flags: Vec<String>,
+    /// Groups of equivalent flags, e.g. `-r` and `--recursive`
+    #[serde(default)]
+    pub flag_synonyms: Vec<Vec<String>>,
+    /// Number of times this command was learned from a command line
+    #[serde(default)]
+    pub usage_count: usize,
+    /// When this command was last learned from a command line
+    #[serde(default)]
+    pub last_updated: Option<SystemTime>,
+}
+
+impl CommandPattern {
+    /// Create a pattern for a command with no known subcommands or flags yet
+    #[must_use]
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            args: vec![],
+            flags: vec![],
+            flag_synonyms: vec![],
+            usage_count: 0,
+            last_updated: None,
+        }
+    }
 }
 
 /// Map of well-known commands and their common arguments/flags
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct CommandPatterns {
+    /// Only what was learned on top of the built-in patterns is saved, see
+    /// [`Self::merge_learned`]
+    #[serde(serialize_with = "serialize_learned_patterns")]
     patterns: HashMap<String, CommandPattern>,
+    /// Package names known to be launched through package runners like `npx`
+    #[serde(default)]
+    runner_packages: Vec<String>,
+    /// Curated subcommand misspellings per command: command -> typo -> subcommand
+    ///
+    /// Built from [`COMMON_ARG_TYPOS`], so never persisted.
+    #[serde(skip)]
+    common_arg_typos: HashMap<String, HashMap<String, String>>,
+    /// User-defined subcommands per command, see [`Self::register_subcommand`]
+    ///
+    /// Rediscovered along with the PATH commands, so never persisted: a removed alias or
+    /// plugin stops being a subcommand.
+    #[serde(skip)]
+    registered_subcommands: HashMap<String, Vec<String>>,
+}
+
+/// Serialize the patterns as their differences from the built-in ones
+///
+/// A built-in pattern is saved with only the subcommands, flags and synonyms it doesn't
+/// have built in, and not at all when it was never learned from.
+fn serialize_learned_patterns<S: Serializer>(
+    patterns: &HashMap<String, CommandPattern>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    fn added<T: Clone + PartialEq>(values: &[T], builtin: &[T]) -> Vec<T> {
+        values
+            .iter()
+            .filter(|value| !builtin.contains(value))
+            .cloned()
+            .collect()
+    }
+
+    let learned: HashMap<&String, CommandPattern> = patterns
+        .iter()
+        .filter_map(|(command, pattern)| {
+            let Some(builtin) = BUILTIN_PATTERNS.patterns.get(command) else {
+                return Some((command, pattern.clone()));
+            };
+            let delta = CommandPattern {
+                args: added(&pattern.args, &builtin.args),
+                flags: added(&pattern.flags, &builtin.flags),
+                flag_synonyms: added(&pattern.flag_synonyms, &builtin.flag_synonyms),
+                usage_count: pattern.usage_count,
+                last_updated: pattern.last_updated,
+                ..CommandPattern::new(command)
+            };
+            let unchanged = delta.args.is_empty()
+                && delta.flags.is_empty()
+                && delta.flag_synonyms.is_empty()
+                && delta.last_updated.is_none();
+            (!unchanged).then_some((command, delta))
+        })
+        .collect();
+    learned.serialize(serializer)
+}
+
+/// Shell builtins that run the command following them
+pub const BUILTIN_WRAPPERS: &[&str] = &["command", "exec", "builtin", "eval"];
+
+/// Commands whose quoted argument is a command line run elsewhere, with the subcommand
+/// or flag that must precede it (`None` when any quoted argument is one)
+pub const QUOTED_COMMAND_RUNNERS: &[(&str, Option<&str>)] = &[
+    ("ssh", None),
+    ("docker", Some("exec")),
+    ("podman", Some("exec")),
+    ("kubectl", Some("exec")),
+    ("sh", Some("-c")),
+    ("bash", Some("-c")),
+    ("zsh", Some("-c")),
+    ("dash", Some("-c")),
+    ("fish", Some("-c")),
+    ("su", Some("-c")),
+];
+
+/// Commands whose arguments name files to remove, move or overwrite, so a word that
+/// looks misspelled is never "corrected" into another file name
+pub const DESTRUCTIVE_COMMANDS: &[&str] = &[
+    "rm", "rmdir", "unlink", "shred", "mv", "cp", "ln", "dd", "truncate", "install", "chmod",
+    "chown", "chgrp", "rsync",
+];
+
+/// Shell keywords that only make sense inside a compound command, never as a line's first word
+pub const CONTINUATION_KEYWORDS: &[&str] = &["fi", "do", "done", "then", "else", "elif", "esac", "in"];
+
+/// Shell keywords that open a compound command, giving continuation keywords a meaning
+pub const COMPOUND_OPENERS: &[&str] = &["if", "for", "while", "until", "case", "select"];
+
+/// Prefix commands that run the command following them, with the flags that take a value
+pub const PREFIX_WRAPPERS: &[(&str, &[&str])] = &[
+    ("time", &["-f", "--format", "-o", "--output"]),
+    ("nice", &["-n", "--adjustment"]),
+    (
+        "env",
+        &["-u", "--unset", "-C", "--chdir", "-S", "--split-string"],
+    ),
+    (
+        "sudo",
+        &[
+            "-u", "--user", "-g", "--group", "-C", "--close-from", "-D", "--chdir", "-p",
+            "--prompt", "-r", "--role", "-t", "--type", "-T", "--command-timeout", "-U",
+            "--other-user",
+        ],
+    ),
+    ("doas", &["-u", "-C"]),
+    (
+        "strace",
+        &["-e", "-o", "-p", "-s", "-u", "-E", "-P", "-I", "-b", "-X"],
+    ),
+    ("watch", &["-n", "--interval", "-q", "--equexit"]),
+    ("timeout", &["-s", "--signal", "-k", "--kill-after"]),
+];
+
+/// Prefix wrappers whose first positional argument is a duration, e.g. `timeout 10 make`
+pub const DURATION_WRAPPERS: &[&str] = &["timeout"];
+
+/// Subcommand-first tools with the global flags that may precede the subcommand
+///
+/// Each entry is `(command, global flags, global flags that take a value)`.
+pub const SUBCOMMAND_FIRST_COMMANDS: &[(&str, &[&str], &[&str])] = &[
+    (
+        "git",
+        &[
+            "--no-pager",
+            "--paginate",
+            "-p",
+            "--bare",
+            "--version",
+            "--help",
+            "--exec-path",
+            "--no-replace-objects",
+            "--literal-pathspecs",
+        ],
+        &["-C", "-c", "--git-dir", "--work-tree", "--namespace"],
+    ),
+    (
+        "cargo",
+        &[
+            "-v",
+            "--verbose",
+            "-q",
+            "--quiet",
+            "--frozen",
+            "--locked",
+            "--offline",
+            "--list",
+            "-V",
+            "--version",
+            "-h",
+            "--help",
+        ],
+        &["--color", "--config", "-C", "-Z"],
+    ),
+];
+
+/// Commands taking their first argument as a cluster of single-letter flags, like `tar xvzf`
+///
+/// Each entry is `(command, operation letters, modifier letters, letters taking a value)`.
+/// A cluster holds one operation, and letters taking a value come last since the
+/// argument after the cluster is their value.
+pub const FLAG_CLUSTER_COMMANDS: &[(&str, &str, &str, &str)] =
+    &[("tar", "cxtrudA", "vzjJaZpkmOSWhP", "f")];
+
+/// File extensions of archives, which `tar` reads and writes through its `f` flag
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    ".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tbz2", ".tar.xz", ".txz", ".tar.zst",
+];
+
+/// Characteristic misspellings of the most used subcommands, per command
+///
+/// Checked before the fuzzy matching of [`CommandPatterns::find_similar_arg`], which
+/// can pick the wrong subcommand for these (or miss them entirely).
+pub const COMMON_ARG_TYPOS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "git",
+        &[
+            ("stauts", "status"),
+            ("statsu", "status"),
+            ("statuss", "status"),
+            ("staus", "status"),
+            ("stache", "stash"),
+            ("stsh", "stash"),
+            ("stahs", "stash"),
+            ("rebease", "rebase"),
+            ("reabse", "rebase"),
+            ("rebsae", "rebase"),
+            ("comit", "commit"),
+            ("commti", "commit"),
+            ("chekout", "checkout"),
+            ("checkotu", "checkout"),
+            ("psuh", "push"),
+            ("pusj", "push"),
+            ("pul", "pull"),
+            ("brnach", "branch"),
+            ("marge", "merge"),
+            ("cherrypick", "cherry-pick"),
+        ],
+    ),
+    (
+        "cargo",
+        &[
+            ("biuld", "build"),
+            ("buidl", "build"),
+            ("bulid", "build"),
+            ("cehck", "check"),
+            ("chekc", "check"),
+            ("urn", "run"),
+            ("tset", "test"),
+            ("tets", "test"),
+            ("clipy", "clippy"),
+            ("clippu", "clippy"),
+            ("isntall", "install"),
+            ("instal", "install"),
+        ],
+    ),
+    (
+        "docker",
+        &[
+            ("iamges", "images"),
+            ("imgaes", "images"),
+            ("contianer", "container"),
+            ("conatiner", "container"),
+            ("compsoe", "compose"),
+            ("comopse", "compose"),
+            ("exce", "exec"),
+            ("lgos", "logs"),
+        ],
+    ),
+];
+
+/// Package runners whose first non-flag argument is the package to run
+pub const PACKAGE_RUNNERS: &[&str] = &["npx", "bunx", "pnpx"];
+
+/// Package managers whose subcommand turns them into a package runner
+pub const PACKAGE_RUNNER_SUBCOMMANDS: &[(&str, &str)] = &[("pnpm", "dlx"), ("yarn", "dlx")];
+
+/// Well-known packages commonly launched through package runners
+pub const KNOWN_RUNNER_PACKAGES: &[&str] = &[
+    "create-vite",
+    "create-react-app",
+    "create-next-app",
+    "create-svelte",
+    "create-astro",
+    "create-t3-app",
+    "degit",
+    "eslint",
+    "prettier",
+    "typescript",
+    "tsc",
+    "ts-node",
+    "tsx",
+    "vite",
+    "vitest",
+    "jest",
+    "nodemon",
+    "serve",
+    "http-server",
+    "npm-check-updates",
+    "prisma",
+    "playwright",
+    "storybook",
+    "turbo",
+    "wrangler",
+];
+
+/// Well-known commands worth suggesting by name even when they are not installed
+pub const WELL_KNOWN_COMMANDS: &[&str] = &[
+    "bat",
+    "btop",
+    "cargo",
+    "clang",
+    "cmake",
+    "curl",
+    "docker",
+    "eza",
+    "fd",
+    "ffmpeg",
+    "fzf",
+    "gcc",
+    "gh",
+    "git",
+    "go",
+    "gradle",
+    "helm",
+    "htop",
+    "http",
+    "jq",
+    "just",
+    "kubectl",
+    "lazygit",
+    "make",
+    "mvn",
+    "neofetch",
+    "nmap",
+    "node",
+    "npm",
+    "nvim",
+    "podman",
+    "python3",
+    "rg",
+    "rsync",
+    "rustup",
+    "screen",
+    "shellcheck",
+    "terraform",
+    "tldr",
+    "tmux",
+    "tree",
+    "unzip",
+    "vim",
+    "wget",
+    "yq",
+    "zip",
+    "zoxide",
+];
+
+/// Popular tools whose package name differs from (or is worth suggesting alongside) the binary
+///
+/// Each entry is `(binary, package)`.
+pub const PACKAGE_BINARIES: &[(&str, &str)] = &[
+    ("rg", "ripgrep"),
+    ("fd", "fd"),
+    ("bat", "bat"),
+    ("eza", "eza"),
+    ("btm", "bottom"),
+    ("delta", "git-delta"),
+    ("dust", "dust"),
+    ("http", "httpie"),
+    ("nvim", "neovim"),
+    ("gh", "gh"),
+    ("jq", "jq"),
+    ("yq", "yq"),
+    ("fzf", "fzf"),
+    ("htop", "htop"),
+    ("btop", "btop"),
+    ("tldr", "tldr"),
+    ("zoxide", "zoxide"),
+    ("lazygit", "lazygit"),
+    ("hyperfine", "hyperfine"),
+    ("tokei", "tokei"),
+    ("shellcheck", "shellcheck"),
+    ("tmux", "tmux"),
+    ("kubectl", "kubectl"),
+    ("helm", "helm"),
+    ("terraform", "terraform"),
+];
+
+/// A package manager used to format install hints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Brew,
+    Apt,
+    Pacman,
+    Dnf,
+}
+
+impl PackageManager {
+    /// Detect the platform's package manager from the commands available in PATH
+    #[must_use]
+    pub fn detect() -> Option<Self> {
+        [
+            ("brew", Self::Brew),
+            ("apt", Self::Apt),
+            ("pacman", Self::Pacman),
+            ("dnf", Self::Dnf),
+        ]
+        .into_iter()
+        .find(|(command, _)| crate::utils::path_has_command(command))
+        .map(|(_, manager)| manager)
+    }
+
+    /// Format the command that installs a package
+    #[must_use]
+    pub fn install_command(self, package: &str) -> String {
+        match self {
+            Self::Brew => format!("brew install {package}"),
+            Self::Apt => format!("sudo apt install {package}"),
+            Self::Pacman => format!("sudo pacman -S {package}"),
+            Self::Dnf => format!("sudo dnf install {package}"),
+        }
+    }
+}
+
+/// A not-installed tool suggested for a typed command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageSuggestion {
+    /// The binary the user probably meant
+    pub binary: String,
+    /// The package that provides it
+    pub package: String,
+    /// How to install the package, if the package manager could be detected
+    pub install_command: Option<String>,
+}
+
+/// Suggest a popular, not-installed tool whose binary or package name is close to `command`
+///
+/// e.g. `ripgpre` -> `rg` from the `ripgrep` package, installable with `brew install ripgrep`.
+#[must_use]
+pub fn find_package_suggestion(command: &str) -> Option<PackageSuggestion> {
+    let (binary, package) = PACKAGE_BINARIES
+        .iter()
+        .filter(|(binary, _)| !crate::utils::path_has_command(binary))
+        .map(|(binary, package)| {
+            let score = crate::utils::calculate_similarity(command, binary)
+                .max(crate::utils::calculate_similarity(command, package));
+            (binary, package, score)
+        })
+        .filter(|(_, _, score)| *score >= UNINSTALLED_SIMILARITY_THRESHOLD)
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(binary, package, _)| (*binary, *package))?;
+
+    Some(PackageSuggestion {
+        binary: binary.to_string(),
+        package: package.to_string(),
+        install_command: PackageManager::detect().map(|manager| manager.install_command(package)),
+    })
 }
 
+/// Minimum similarity for suggesting a command that is not installed
+const UNINSTALLED_SIMILARITY_THRESHOLD: f64 = 0.6;
+
 /// Regular expression for extracting command and arguments
 pub static COMMAND_REGEX: std::sync::LazyLock<Regex> =
-    std::sync::LazyLock::new(|| Regex::new(r"^(?P<cmd>\S+)(?:\s+(?P<args>.+))?$").unwrap());
+    std::sync::LazyLock::new(|| Regex::new(r"^\s*(?P<cmd>\S+)(?:\s+(?P<args>.+?))?\s*$").unwrap());
+
+/// Common English words that misspelled word arguments are corrected to, one per line
+static DICTIONARY_WORDS: std::sync::LazyLock<Vec<&'static str>> =
+    std::sync::LazyLock::new(|| include_str!("words.txt").lines().collect());
+
+/// Built-in command patterns, constructed once per process and cloned from there
+static BUILTIN_PATTERNS: std::sync::LazyLock<CommandPatterns> =
+    std::sync::LazyLock::new(CommandPatterns::build_builtin);
 
 impl CommandPatterns {
     /// Create a new `CommandPatterns` instance with predefined common commands
+    ///
+    /// The built-ins are only constructed once; each instance gets its own copy so
+    /// learned patterns stay per-instance and mutable.
     #[must_use]
     pub fn new() -> Self {
+        BUILTIN_PATTERNS.clone()
+    }
+
+    /// Construct the built-in patterns from scratch
+    ///
+    /// Prefer [`CommandPatterns::new`], which reuses the patterns built on first use.
+    #[must_use]
+    pub fn build_builtin() -> Self {
         let mut patterns = HashMap::new();
 
         Self::add_git_commands(&mut patterns);
         Self::add_docker_commands(&mut patterns);
         Self::add_cargo_commands(&mut patterns);
         Self::add_file_commands(&mut patterns);
+        Self::add_archive_commands(&mut patterns);
         Self::add_network_commands(&mut patterns);
         Self::add_package_commands(&mut patterns);
         Self::add_process_commands(&mut patterns);
         Self::add_misc_commands(&mut patterns);
 
-        Self { patterns }
+        Self {
+            patterns,
+            runner_packages: KNOWN_RUNNER_PACKAGES
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+            common_arg_typos: COMMON_ARG_TYPOS
+                .iter()
+                .map(|(command, typos)| {
+                    let typos = typos
+                        .iter()
+                        .map(|(typo, arg)| ((*typo).to_string(), (*arg).to_string()))
+                        .collect();
+                    ((*command).to_string(), typos)
+                })
+                .collect(),
+            registered_subcommands: HashMap::new(),
+        }
     }
     
     /// Add Git commands to the patterns
@@ -46,13 +534,13 @@ impl CommandPatterns {
         patterns.insert(
             "git".to_string(),
             CommandPattern {
-                command: "git".to_string(),
                 args: vec![
                     "status".to_string(),
                     "commit".to_string(),
                     "push".to_string(),
                     "pull".to_string(),
                     "checkout".to_string(),
+                    "switch".to_string(),
                     "branch".to_string(),
                     "merge".to_string(),
                     "rebase".to_string(),
@@ -70,6 +558,7 @@ impl CommandPatterns {
                     "--all".to_string(),
                     "--dry-run".to_string(),
                 ],
+                ..CommandPattern::new("git")
             },
         );
     }
@@ -79,7 +568,6 @@ impl CommandPatterns {
         patterns.insert(
             "docker".to_string(),
             CommandPattern {
-                command: "docker".to_string(),
                 args: vec![
                     "run".to_string(),
                     "build".to_string(),
@@ -105,6 +593,7 @@ impl CommandPatterns {
                     "-v".to_string(),
                     "--rm".to_string(),
                 ],
+                ..CommandPattern::new("docker")
             },
         );
     }
@@ -114,7 +603,6 @@ impl CommandPatterns {
         patterns.insert(
             "cargo".to_string(),
             CommandPattern {
-                command: "cargo".to_string(),
                 args: vec![
                     "build".to_string(),
                     "check".to_string(),
@@ -141,6 +629,7 @@ impl CommandPatterns {
                     "--bin".to_string(),
                     "--example".to_string(),
                 ],
+                ..CommandPattern::new("cargo")
             },
         );
     }
@@ -151,8 +640,6 @@ impl CommandPatterns {
         patterns.insert(
             "ls".to_string(),
             CommandPattern {
-                command: "ls".to_string(),
-                args: vec![],
                 flags: vec![
                     "-l".to_string(),
                     "-a".to_string(),
@@ -162,25 +649,25 @@ impl CommandPatterns {
                     "-la".to_string(),
                     "-lh".to_string(),
                 ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-a", "--all"],
+                    &["-h", "--human-readable"],
+                    &["-R", "--recursive"],
+                ]),
+                ..CommandPattern::new("ls")
             },
         );
         
         // cd command
         patterns.insert(
             "cd".to_string(),
-            CommandPattern {
-                command: "cd".to_string(),
-                args: vec![],
-                flags: vec![],
-            },
+            CommandPattern::new("cd"),
         );
         
         // cp command
         patterns.insert(
             "cp".to_string(),
             CommandPattern {
-                command: "cp".to_string(),
-                args: vec![],
                 flags: vec![
                     "-r".to_string(),
                     "-v".to_string(),
@@ -188,6 +675,14 @@ impl CommandPatterns {
                     "-f".to_string(),
                     "-a".to_string(),
                 ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-r", "-R", "--recursive"],
+                    &["-v", "--verbose"],
+                    &["-i", "--interactive"],
+                    &["-f", "--force"],
+                    &["-a", "--archive"],
+                ]),
+                ..CommandPattern::new("cp")
             },
         );
         
@@ -195,13 +690,17 @@ impl CommandPatterns {
         patterns.insert(
             "mv".to_string(),
             CommandPattern {
-                command: "mv".to_string(),
-                args: vec![],
                 flags: vec![
                     "-v".to_string(),
                     "-i".to_string(),
                     "-f".to_string(),
                 ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-v", "--verbose"],
+                    &["-i", "--interactive"],
+                    &["-f", "--force"],
+                ]),
+                ..CommandPattern::new("mv")
             },
         );
         
@@ -209,8 +708,6 @@ impl CommandPatterns {
         patterns.insert(
             "rm".to_string(),
             CommandPattern {
-                command: "rm".to_string(),
-                args: vec![],
                 flags: vec![
                     "-r".to_string(),
                     "-f".to_string(),
@@ -218,18 +715,58 @@ impl CommandPatterns {
                     "-v".to_string(),
                     "-rf".to_string(),
                 ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-r", "-R", "--recursive"],
+                    &["-f", "--force"],
+                    &["-i", "--interactive"],
+                    &["-v", "--verbose"],
+                ]),
+                ..CommandPattern::new("rm")
             },
         );
     }
     
+    /// Add archive commands to the patterns
+    fn add_archive_commands(patterns: &mut HashMap<String, CommandPattern>) {
+        // tar command; its flag clusters like `xvzf` are checked against
+        // FLAG_CLUSTER_COMMANDS rather than listed here
+        patterns.insert(
+            "tar".to_string(),
+            CommandPattern {
+                flags: vec![
+                    "-c".to_string(),
+                    "-x".to_string(),
+                    "-t".to_string(),
+                    "-v".to_string(),
+                    "-z".to_string(),
+                    "-j".to_string(),
+                    "-J".to_string(),
+                    "-f".to_string(),
+                    "-C".to_string(),
+                    "--exclude".to_string(),
+                ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-c", "--create"],
+                    &["-x", "--extract", "--get"],
+                    &["-t", "--list"],
+                    &["-v", "--verbose"],
+                    &["-z", "--gzip"],
+                    &["-j", "--bzip2"],
+                    &["-J", "--xz"],
+                    &["-f", "--file"],
+                    &["-C", "--directory"],
+                ]),
+                ..CommandPattern::new("tar")
+            },
+        );
+    }
+
     /// Add network-related commands to the patterns
     fn add_network_commands(patterns: &mut HashMap<String, CommandPattern>) {
         // curl command
         patterns.insert(
             "curl".to_string(),
             CommandPattern {
-                command: "curl".to_string(),
-                args: vec![],
                 flags: vec![
                     "-X".to_string(),
                     "-H".to_string(),
@@ -240,6 +777,15 @@ impl CommandPatterns {
                     "-L".to_string(),
                     "-i".to_string(),
                 ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-o", "--output"],
+                    &["-L", "--location"],
+                    &["-s", "--silent"],
+                    &["-X", "--request"],
+                    &["-H", "--header"],
+                    &["-d", "--data"],
+                ]),
+                ..CommandPattern::new("curl")
             },
         );
         
@@ -247,8 +793,6 @@ impl CommandPatterns {
         patterns.insert(
             "wget".to_string(),
             CommandPattern {
-                command: "wget".to_string(),
-                args: vec![],
                 flags: vec![
                     "-q".to_string(),
                     "-O".to_string(),
@@ -256,6 +800,7 @@ impl CommandPatterns {
                     "-r".to_string(),
                     "-p".to_string(),
                 ],
+                ..CommandPattern::new("wget")
             },
         );
         
@@ -263,14 +808,13 @@ impl CommandPatterns {
         patterns.insert(
             "ssh".to_string(),
             CommandPattern {
-                command: "ssh".to_string(),
-                args: vec![],
                 flags: vec![
                     "-p".to_string(),
                     "-i".to_string(),
                     "-v".to_string(),
                     "-l".to_string(),
                 ],
+                ..CommandPattern::new("ssh")
             },
         );
     }
@@ -281,7 +825,6 @@ impl CommandPatterns {
         patterns.insert(
             "apt".to_string(),
             CommandPattern {
-                command: "apt".to_string(),
                 args: vec![
                     "install".to_string(),
                     "update".to_string(),
@@ -299,6 +842,7 @@ impl CommandPatterns {
                     "--help".to_string(),
                     "--no-install-recommends".to_string(),
                 ],
+                ..CommandPattern::new("apt")
             },
         );
         
@@ -306,7 +850,6 @@ impl CommandPatterns {
         patterns.insert(
             "pacman".to_string(),
             CommandPattern {
-                command: "pacman".to_string(),
                 args: vec![
                     "-S".to_string(),
                     "-Syu".to_string(),
@@ -320,6 +863,7 @@ impl CommandPatterns {
                     "--needed".to_string(),
                     "-q".to_string(),
                 ],
+                ..CommandPattern::new("pacman")
             },
         );
     }
@@ -330,8 +874,6 @@ impl CommandPatterns {
         patterns.insert(
             "ps".to_string(),
             CommandPattern {
-                command: "ps".to_string(),
-                args: vec![],
                 flags: vec![
                     "aux".to_string(),
                     "-ef".to_string(),
@@ -339,6 +881,7 @@ impl CommandPatterns {
                     "-u".to_string(),
                     "-x".to_string(),
                 ],
+                ..CommandPattern::new("ps")
             },
         );
         
@@ -346,8 +889,6 @@ impl CommandPatterns {
         patterns.insert(
             "grep".to_string(),
             CommandPattern {
-                command: "grep".to_string(),
-                args: vec![],
                 flags: vec![
                     "-i".to_string(),
                     "-v".to_string(),
@@ -357,6 +898,15 @@ impl CommandPatterns {
                     "-l".to_string(),
                     "--color".to_string(),
                 ],
+                flag_synonyms: flag_synonyms(&[
+                    &["-i", "--ignore-case"],
+                    &["-v", "--invert-match"],
+                    &["-r", "--recursive"],
+                    &["-E", "--extended-regexp"],
+                    &["-n", "--line-number"],
+                    &["-l", "--files-with-matches"],
+                ]),
+                ..CommandPattern::new("grep")
             },
         );
         
@@ -364,14 +914,13 @@ impl CommandPatterns {
         patterns.insert(
             "kill".to_string(),
             CommandPattern {
-                command: "kill".to_string(),
-                args: vec![],
                 flags: vec![
                     "-9".to_string(),
                     "-15".to_string(),
                     "-SIGTERM".to_string(),
                     "-SIGKILL".to_string(),
                 ],
+                ..CommandPattern::new("kill")
             },
         );
     }
@@ -382,8 +931,6 @@ impl CommandPatterns {
         patterns.insert(
             "find".to_string(),
             CommandPattern {
-                command: "find".to_string(),
-                args: vec![],
                 flags: vec![
                     "-name".to_string(),
                     "-type".to_string(),
@@ -392,6 +939,7 @@ impl CommandPatterns {
                     "-perm".to_string(),
                     "-mtime".to_string(),
                 ],
+                ..CommandPattern::new("find")
             },
         );
         
@@ -399,12 +947,11 @@ impl CommandPatterns {
         patterns.insert(
             "echo".to_string(),
             CommandPattern {
-                command: "echo".to_string(),
-                args: vec![],
                 flags: vec![
                     "-n".to_string(),
                     "-e".to_string(),
                 ],
+                ..CommandPattern::new("echo")
             },
         );
         
@@ -412,12 +959,11 @@ impl CommandPatterns {
         patterns.insert(
             "cat".to_string(),
             CommandPattern {
-                command: "cat".to_string(),
-                args: vec![],
                 flags: vec![
                     "-n".to_string(),
                     "-A".to_string(),
                 ],
+                ..CommandPattern::new("cat")
             },
         );
     }
@@ -428,10 +974,29 @@ impl CommandPatterns {
         self.patterns.get(command)
     }
 
-    /// Get arguments for a specific command
+    /// Get arguments for a specific command, including its registered subcommands
     #[must_use]
-    pub fn get_args_for_command(&self, command: &str) -> Option<&Vec<String>> {
-        self.get(command).map(|pattern| &pattern.args)
+    pub fn get_args_for_command(&self, command: &str) -> Option<impl Iterator<Item = &String>> {
+        let registered = self.registered_subcommands.get(command).into_iter().flatten();
+        self.get(command).map(|pattern| pattern.args.iter().chain(registered))
+    }
+
+    /// Find the parent command that a bare subcommand implies, e.g. `commit` -> `git`
+    ///
+    /// Only `parents` are considered. A subcommand shared by several of them (like `add`
+    /// for both git and cargo) is not distinctive enough to imply either.
+    #[must_use]
+    pub fn find_implied_parent<'a>(
+        &self,
+        subcommand: &str,
+        parents: &'a [String],
+    ) -> Option<&'a str> {
+        let mut matches = parents.iter().filter(|parent| {
+            self.get_args_for_command(parent)
+                .is_some_and(|mut args| args.any(|arg| arg == subcommand))
+        });
+        let parent = matches.next()?;
+        matches.next().is_none().then_some(parent.as_str())
     }
 
     /// Check if a command is a well-known command
@@ -447,12 +1012,13 @@ impl CommandPatterns {
         arg: &str,
         command_patterns: &CommandPatterns,
     ) -> Option<String> {
-        // For common git subcommands, be more lenient with the threshold
-        if command == "git" && arg.starts_with("sta") && arg.len() > 3 {
-            // Direct handling of common typos for "status"
-            if arg == "stauts" || arg == "statsu" || arg == "statuss" || arg == "staus" {
-                return Some("status".to_string());
-            }
+        // Curated misspellings are known to mean one subcommand, so they skip the fuzzy match
+        if let Some(known) = command_patterns
+            .common_arg_typos
+            .get(command)
+            .and_then(|typos| typos.get(arg))
+        {
+            return Some(known.clone());
         }
 
         // Get the known arguments for this command
@@ -492,12 +1058,220 @@ impl CommandPatterns {
         None
     }
 
+    /// Learn a command's subcommand and flags from a command line the user ran
+    ///
+    /// Unknown commands get a new pattern. Only the subcommand position and flags are
+    /// learned: the first argument is remembered as a subcommand if it looks like one and
+    /// the command may take subcommands, i.e. it either has some already or isn't a
+    /// built-in known to take none, like `ls`. Every flag up to a `--` is remembered as a
+    /// known flag. File names, flag values and other arguments are not.
+    pub fn learn_from_command(&mut self, command_line: &str) {
+        let mut tokens = command_line.split_whitespace();
+        let Some(command) = tokens.next() else {
+            return;
+        };
+
+        let pattern = self
+            .patterns
+            .entry(command.to_string())
+            .or_insert_with(|| CommandPattern::new(command));
+
+        let takes_subcommands =
+            !pattern.args.is_empty() || !BUILTIN_PATTERNS.patterns.contains_key(command);
+        for (position, token) in tokens.enumerate() {
+            if token == "--" {
+                break;
+            }
+            if let Some(name) = token.strip_prefix('-') {
+                // `-` alone is stdin and `-5` a number, not flags
+                if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '-') {
+                    continue;
+                }
+                let flag = token.split('=').next().unwrap_or(token);
+                if !pattern.flags.iter().any(|known| known == flag) {
+                    pattern.flags.push(flag.to_string());
+                }
+            } else if position == 0
+                && takes_subcommands
+                && token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+                && !pattern.args.iter().any(|known| known == token)
+            {
+                pattern.args.push(token.to_string());
+            }
+        }
+
+        pattern.usage_count += 1;
+        pattern.last_updated = Some(SystemTime::now());
+    }
+
+    /// Merge patterns learned in an earlier session into these (built-in) patterns
+    ///
+    /// Args, flags and synonyms are unioned; usage data comes from the learned side.
+    pub fn merge_learned(&mut self, learned: Self) {
+        for (command, learned_pattern) in learned.patterns {
+            let Some(pattern) = self.patterns.get_mut(&command) else {
+                self.patterns.insert(command, learned_pattern);
+                continue;
+            };
+
+            for arg in learned_pattern.args {
+                if !pattern.args.contains(&arg) {
+                    pattern.args.push(arg);
+                }
+            }
+            for flag in learned_pattern.flags {
+                if !pattern.flags.contains(&flag) {
+                    pattern.flags.push(flag);
+                }
+            }
+            for group in learned_pattern.flag_synonyms {
+                if !pattern.flag_synonyms.contains(&group) {
+                    pattern.flag_synonyms.push(group);
+                }
+            }
+            pattern.usage_count = learned_pattern.usage_count;
+            pattern.last_updated = learned_pattern.last_updated;
+        }
+
+        for package in learned.runner_packages {
+            self.register_runner_package(&package);
+        }
+    }
+
+    /// Remember a package as runnable through package runners
+    pub fn register_runner_package(&mut self, package: &str) {
+        if !self.runner_packages.iter().any(|known| known == package) {
+            self.runner_packages.push(package.to_string());
+        }
+    }
+
+    /// Accept a user-defined subcommand (like a cargo alias) as valid for a command
+    ///
+    /// Only commands with a pattern take it; a command without one never has its
+    /// subcommand corrected in the first place. Registered subcommands are kept apart
+    /// from the pattern's own, so they aren't saved with the learned patterns.
+    pub fn register_subcommand(&mut self, command: &str, subcommand: &str) {
+        let Some(pattern) = self.patterns.get(command) else {
+            return;
+        };
+        let registered = self
+            .registered_subcommands
+            .entry(command.to_string())
+            .or_default();
+        if !pattern.args.iter().chain(registered.iter()).any(|known| known == subcommand) {
+            registered.push(subcommand.to_string());
+        }
+    }
+
+    /// Forget every subcommand registered with [`Self::register_subcommand`]
+    pub fn clear_registered_subcommands(&mut self) {
+        self.registered_subcommands.clear();
+    }
+
+    /// Check whether a command has a built-in pattern
+    #[must_use]
+    pub fn is_builtin(command: &str) -> bool {
+        BUILTIN_PATTERNS.patterns.contains_key(command)
+    }
+
+    /// Count the patterns of commands without a built-in pattern
+    #[must_use]
+    pub fn learned_count(&self) -> usize {
+        self.patterns.keys().filter(|command| !Self::is_builtin(command)).count()
+    }
+
+    /// Drop the least recently used patterns of commands without a built-in pattern until
+    /// at most `max` are left, returning the dropped commands
+    ///
+    /// Patterns are dropped least recently learned or used first (see [`Self::touch`]),
+    /// and the usage count breaks ties. Built-in patterns are never dropped.
+    pub fn evict_least_recently_used(&mut self, max: usize) -> Vec<String> {
+        let mut learned: Vec<(Option<SystemTime>, usize, &String)> = self
+            .patterns
+            .iter()
+            .filter(|(command, _)| !Self::is_builtin(command))
+            .map(|(command, pattern)| (pattern.last_updated, pattern.usage_count, command))
+            .collect();
+        if learned.len() <= max {
+            return Vec::new();
+        }
+
+        learned.sort_unstable();
+        let evicted: Vec<String> = learned[..learned.len() - max]
+            .iter()
+            .map(|(_, _, command)| (*command).clone())
+            .collect();
+        for command in &evicted {
+            self.patterns.remove(command);
+        }
+        evicted
+    }
+
+    /// Mark a command's pattern as just used, so it's the last to be evicted
+    pub fn touch(&mut self, command: &str) {
+        if let Some(pattern) = self.patterns.get_mut(command) {
+            pattern.last_updated = Some(SystemTime::now());
+        }
+    }
+
+    /// Accept the subcommands and flags found in a tool's help output as valid for it
+    ///
+    /// Unlike [`Self::register_subcommand`], a command without a pattern gets one.
+    pub fn register_help(&mut self, command: &str, help: &HelpText) {
+        if help.subcommands.is_empty() && help.flags.is_empty() {
+            return;
+        }
+        let pattern = self
+            .patterns
+            .entry(command.to_string())
+            .or_insert_with(|| CommandPattern::new(command));
+        for subcommand in &help.subcommands {
+            if !pattern.args.contains(subcommand) {
+                pattern.args.push(subcommand.clone());
+            }
+        }
+        for flag in &help.flags {
+            if !pattern.flags.contains(flag) {
+                pattern.flags.push(flag.clone());
+            }
+        }
+    }
+
+    /// Find the known runner package closest to the given (possibly misspelled) name
+    #[must_use]
+    pub fn find_runner_package(&self, package: &str) -> Option<String> {
+        if self.runner_packages.iter().any(|known| known == package) {
+            return Some(package.to_string());
+        }
+
+        crate::utils::find_closest_match(package, &self.runner_packages, 0.6).cloned()
+    }
+
+    /// Check whether a flag, or one of its synonyms, is known for a command
+    #[must_use]
+    pub fn is_known_flag(&self, command: &str, flag: &str) -> bool {
+        is_valid_flag_cluster(command, flag)
+            || self.patterns.get(command).is_some_and(|pattern| {
+                pattern.flags.iter().any(|known| known == flag)
+                    || pattern
+                        .flag_synonyms
+                        .iter()
+                        .any(|group| group.iter().any(|known| known == flag))
+            })
+    }
+
     /// Find a similar flag for a known command
     #[must_use]
     pub fn find_similar_flag(&self, command: &str, flag: &str, threshold: f64) -> Option<String> {
         if let Some(pattern) = self.patterns.get(command) {
-            // Find the closest matching flag
-            let flag_refs: Vec<&String> = pattern.flags.iter().collect();
+            // Find the closest matching flag, including synonyms of the known flags
+            let flag_refs: Vec<&String> = pattern
+                .flags
+                .iter()
+                .chain(pattern.flag_synonyms.iter().flatten())
+                .collect();
             let closest = crate::utils::find_closest_match(flag, &flag_refs, threshold)?;
 
             return Some((*closest).to_string());
@@ -506,12 +1280,62 @@ impl CommandPatterns {
     }
 }
 
+/// Build groups of equivalent flags from string slices
+fn flag_synonyms(groups: &[&[&str]]) -> Vec<Vec<String>> {
+    groups
+        .iter()
+        .map(|group| group.iter().map(|flag| (*flag).to_string()).collect())
+        .collect()
+}
+
+/// Correct a single argument of `command`, returning it unchanged when nothing fits
+fn correct_arg(command: &str, arg: &str, command_patterns: &CommandPatterns) -> String {
+    // Check if it's a flag (starts with - or --)
+    if arg.starts_with('-') {
+        // Valid flags, including alternate spellings like --recursive for -r, are kept
+        if command_patterns.is_known_flag(command, arg) {
+            return arg.to_string();
+        }
+
+        // Try to correct common flags, then the command's known flags
+        if let Some(corrected_flag) = correct_common_flag(arg, command, command_patterns)
+            .or_else(|| command_patterns.find_similar_flag(command, arg, 0.6))
+        {
+            return corrected_flag;
+        }
+    } else {
+        // Remove trailing flags
+        let (arg_base, flags) = remove_trailing_flags(arg);
+
+        // Try to correct the argument
+        if let Some(corrected_arg) =
+            CommandPatterns::find_similar_arg(command, arg_base, command_patterns)
+        {
+            return format!("{corrected_arg}{flags}");
+        }
+    }
+
+    // If we can't correct it, use the original
+    arg.to_string()
+}
+
 /// Fix a command line by correcting typos in command, arguments, and flags
+///
+/// Whitespace around the line, as left by autocomplete or a paste, is trimmed off.
 pub fn fix_command_line(
     command_line: &str,
     find_similar_fn: impl Fn(&str) -> Option<String>,
     command_patterns: &CommandPatterns,
 ) -> Option<String> {
+    // Only correct the command leading up to a here-doc, never its body, which is kept
+    // byte for byte down to its trailing newline
+    if let Some((head, heredoc)) = split_heredoc(command_line.trim_start()) {
+        let corrected_head = fix_command_line(head.trim_end(), find_similar_fn, command_patterns)?;
+        return Some(format!("{corrected_head} {heredoc}"));
+    }
+
+    let command_line = command_line.trim();
+
     // Special cases for very common command lines
     if command_line == "gti status" {
         return Some("git status".to_string());
@@ -537,12 +1361,48 @@ pub fn fix_command_line(
         return Some("cargo build --release".to_string());
     }
 
+    // A quoted command line run remotely or by another shell (`ssh host 'gti status'`)
+    // means nothing here, so only the command running it is corrected
+    if let Some((head, quoted)) = split_at_quoted_argument(command_line)
+        && runs_quoted_command_once_corrected(head, &find_similar_fn)
+    {
+        let corrected_head = fix_command_line(head, find_similar_fn, command_patterns)?;
+        return Some(format!("{corrected_head} {quoted}"));
+    }
+
+    // A misspelled wrapper (`sduo apt update`) is corrected along with the command it runs,
+    // unless the typed word is a command of its own
+    let first = command_line.split_whitespace().next().unwrap_or_default();
+    if !command_patterns.is_known_command(first)
+        && find_similar_fn(first).as_deref() != Some(first)
+        && let Some(line) = correct_wrapper_typo(command_line)
+        && let Some((wrapper, inner)) = split_wrapper(&line)
+    {
+        let corrected_inner = fix_command_line(inner, find_similar_fn, command_patterns)
+            .unwrap_or_else(|| inner.to_string());
+        return Some(format!("{wrapper} {corrected_inner}"));
+    }
+
+    // Correct the command behind a wrapper like `exec` or `nice -n 10` and reattach the wrapper
+    if let Some((wrapper, inner)) = split_wrapper(command_line) {
+        let corrected_inner = fix_command_line(inner, find_similar_fn, command_patterns)?;
+        return Some(format!("{wrapper} {corrected_inner}"));
+    }
+
     // Match command and arguments
     let captures = COMMAND_REGEX.captures(command_line).ok()??;
     let cmd = captures.name("cmd")?.as_str();
 
-    // Try to correct the command first
-    let corrected_cmd = find_similar_fn(cmd)?;
+    // Try to correct the command first; path commands are matched against their directory,
+    // falling back to the binary's new location in the PATH if it has moved.
+    // A command we have patterns for is kept as-is even if it isn't installed, so its
+    // subcommands and flags still get corrected (e.g. `cargo biuld` -> `cargo build`)
+    let corrected_cmd = if split_path_command(cmd).is_some() {
+        correct_path_command(cmd).or_else(|| suggest_relocated_binary(cmd))?
+    } else {
+        find_similar_fn(cmd)
+            .or_else(|| command_patterns.get(cmd).map(|_| cmd.to_string()))?
+    };
 
     // If there are no arguments, return just the corrected command
     let args = if let Some(args_match) = captures.name("args") {
@@ -551,49 +1411,831 @@ pub fn fix_command_line(
         return Some(corrected_cmd);
     };
 
-    // Split the arguments and try to fix each one
-    let args_parts: Vec<&str> = args.split_whitespace().collect();
+    // Split the arguments and try to fix each one, correcting a flag cluster like tar's
+    // `xvfz` as a whole first
+    let cluster_args = correct_cluster_argument(&corrected_cmd, args);
+    let args_parts: Vec<&str> = cluster_args.as_deref().unwrap_or(args).split_whitespace().collect();
+
+    // Package runners only need their package name corrected; the package's own
+    // arguments mean nothing to our patterns
+    if let Some(runner_len) = package_runner_len(&corrected_cmd, &args_parts) {
+        return Some(fix_runner_args(
+            &corrected_cmd,
+            &args_parts,
+            runner_len,
+            command_patterns,
+        ));
+    }
+
+    // Subcommand flags typed before the subcommand belong after it
+    let args_parts = reorder_subcommand_flags(&corrected_cmd, args_parts, command_patterns);
+
+    // Values of global flags like `git -C <path>` are never subcommands
+    let global_value_flags = SUBCOMMAND_FIRST_COMMANDS
+        .iter()
+        .find(|(name, _, _)| *name == corrected_cmd)
+        .map_or(&[][..], |(_, _, value_flags)| *value_flags);
+    let mut is_flag_value = false;
+
     let mut corrected_args = Vec::new();
 
     for arg in args_parts {
-        // Check if it's a flag (starts with - or --)
-        if arg.starts_with('-') {
-            // Try to correct common flags
-            if let Some(corrected_flag) = correct_common_flag(arg, &corrected_cmd, command_patterns) {
-                corrected_args.push(corrected_flag);
-                continue;
+        if std::mem::take(&mut is_flag_value) {
+            corrected_args.push(arg.to_string());
+            continue;
+        }
+        is_flag_value = global_value_flags.contains(&arg);
+        corrected_args.push(correct_arg(&corrected_cmd, arg, command_patterns));
+    }
+
+    // Combine the corrected command and arguments
+    let corrected_command_line = format!("{} {}", corrected_cmd, corrected_args.join(" "));
+
+    Some(corrected_command_line.trim().to_string())
+}
+
+/// Get the letters a command's flag cluster may hold, see [`FLAG_CLUSTER_COMMANDS`]
+fn flag_cluster_letters(command: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    FLAG_CLUSTER_COMMANDS
+        .iter()
+        .find(|(cluster_command, ..)| *cluster_command == command)
+        .map(|(_, operations, modifiers, with_value)| (*operations, *modifiers, *with_value))
+}
+
+/// Check whether a flag is a valid flag cluster for a command, e.g. `-xvzf` for `tar`
+fn is_valid_flag_cluster(command: &str, flag: &str) -> bool {
+    flag.strip_prefix('-').is_some_and(|letters| {
+        !letters.is_empty()
+            && !letters.starts_with('-')
+            && correct_flag_cluster(command, flag, None).is_none()
+            && flag_cluster_letters(command).is_some_and(|(operations, modifiers, with_value)| {
+                letters.chars().all(|letter| {
+                    operations.contains(letter)
+                        || modifiers.contains(letter)
+                        || with_value.contains(letter)
+                })
+            })
+    })
+}
+
+/// Correct a command's flag cluster, optionally led by a dash
+///
+/// Unknown and repeated letters are dropped, only the first operation is kept (moved to
+/// the front) and letters taking a value are moved to the end. When `next_arg` is an
+/// archive and no letter takes it, `f` is added. Returns `None` when the cluster is
+/// already valid or isn't a cluster at all, e.g. `xvfz` -> `xvzf` and `-xvz
+/// file.tar.gz` -> `-xvzf`.
+#[must_use]
+pub fn correct_flag_cluster(
+    command: &str,
+    cluster: &str,
+    next_arg: Option<&str>,
+) -> Option<String> {
+    let (operations, modifiers, with_value) = flag_cluster_letters(command)?;
+    let (dash, letters) = cluster
+        .strip_prefix('-')
+        .map_or(("", cluster), |letters| ("-", letters));
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let mut operation = None;
+    let mut modifier_letters = String::new();
+    let mut value_letters = String::new();
+    for letter in letters.chars() {
+        if operations.contains(letter) {
+            operation.get_or_insert(letter);
+        } else if modifiers.contains(letter) && !modifier_letters.contains(letter) {
+            modifier_letters.push(letter);
+        } else if with_value.contains(letter) && !value_letters.contains(letter) {
+            value_letters.push(letter);
+        }
+    }
+    // A word that is mostly unknown letters is an argument, not a mistyped cluster
+    let known = operation.iter().count() + modifier_letters.len() + value_letters.len();
+    if operation.is_none() || known * 2 < letters.len() {
+        return None;
+    }
+
+    let archive_follows = next_arg.is_some_and(|arg| {
+        ARCHIVE_EXTENSIONS
+            .iter()
+            .any(|extension| arg.ends_with(extension))
+    });
+    if value_letters.is_empty() && archive_follows {
+        value_letters.push_str(&with_value[..1]);
+    }
+
+    let corrected: String = operation
+        .into_iter()
+        .chain(modifier_letters.chars())
+        .chain(value_letters.chars())
+        .collect();
+    (corrected != letters).then(|| format!("{dash}{corrected}"))
+}
+
+/// Correct the flag cluster leading a cluster command's arguments
+///
+/// e.g. `xvfz file.tar.gz` -> `xvzf file.tar.gz` for `tar`. Returns `None` when there is
+/// nothing to correct.
+fn correct_cluster_argument(command: &str, args: &str) -> Option<String> {
+    let mut tokens = args.split_whitespace();
+    let cluster = tokens.next()?;
+    let next_arg = tokens.next();
+    let corrected = correct_flag_cluster(command, cluster, next_arg)?;
+    let rest = args.trim_start()[cluster.len()..].trim_start();
+    Some(format!("{corrected} {rest}").trim_end().to_string())
+}
+
+/// Move subcommand flags typed before the subcommand to just after it
+///
+/// Only applies to [`SUBCOMMAND_FIRST_COMMANDS`]; their global flags stay in place.
+/// e.g. `git --oneline log` -> `git log --oneline`, `git -C repo --stat show` -> `git -C repo show --stat`
+fn reorder_subcommand_flags<'a>(
+    command: &str,
+    args: Vec<&'a str>,
+    command_patterns: &CommandPatterns,
+) -> Vec<&'a str> {
+    let Some((_, global_flags, value_flags)) = SUBCOMMAND_FIRST_COMMANDS
+        .iter()
+        .find(|(name, _, _)| *name == command)
+    else {
+        return args;
+    };
+
+    let mut globals = Vec::new();
+    let mut misplaced = Vec::new();
+    let mut index = 0;
+    while index < args.len() && args[index].starts_with('-') {
+        let flag = args[index];
+        let name = flag.split('=').next().unwrap_or(flag);
+        if value_flags.contains(&name) {
+            globals.push(flag);
+            // The value is a separate token unless given as `--flag=value`
+            if !flag.contains('=') && index + 1 < args.len() {
+                index += 1;
+                globals.push(args[index]);
             }
-            
-            // Try to correct using the command's known flags
-            if let Some(corrected_flag) = command_patterns.find_similar_flag(&corrected_cmd, arg, 0.6) {
-                corrected_args.push(corrected_flag);
-                continue;
+        } else if global_flags.contains(&name) {
+            globals.push(flag);
+        } else {
+            misplaced.push(flag);
+        }
+        index += 1;
+    }
+
+    // Only reorder when a recognizable subcommand follows the misplaced flags
+    let Some(subcommand) = args.get(index) else {
+        return args;
+    };
+    if misplaced.is_empty()
+        || CommandPatterns::find_similar_arg(command, subcommand, command_patterns).is_none()
+    {
+        return args;
+    }
+
+    let mut reordered = globals;
+    reordered.push(*subcommand);
+    reordered.extend(misplaced);
+    reordered.extend(&args[index + 1..]);
+    reordered
+}
+
+/// Suggest a well-known command that is not installed for a possibly misspelled name
+///
+/// Candidates come from [`WELL_KNOWN_COMMANDS`] and, when `use_apropos` is set, from the
+/// section 1 man pages reported by `apropos`. Only commands missing from PATH are returned,
+/// so callers can flag the suggestion as not installed.
+#[must_use]
+pub fn find_uninstalled_command(command: &str, use_apropos: bool) -> Option<String> {
+    let mut candidates: Vec<String> = WELL_KNOWN_COMMANDS
+        .iter()
+        .map(std::string::ToString::to_string)
+        .collect();
+    if use_apropos {
+        candidates.extend(apropos_commands(command));
+    }
+    candidates.retain(|candidate| !crate::utils::path_has_command(candidate));
+
+    crate::utils::find_closest_match(command, &candidates, UNINSTALLED_SIMILARITY_THRESHOLD)
+        .cloned()
+}
+
+/// Get the command names of section 1 man pages whose name or description mentions `query`
+fn apropos_commands(query: &str) -> Vec<String> {
+    let Ok(output) = std::process::Command::new("apropos")
+        .args(["-s", "1", query])
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    // Lines look like `htop (1)             - interactive process viewer`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| name.trim_end_matches(',').to_string())
+        .collect()
+}
+
+/// Split a command given as a path into its directory prefix and file name
+///
+/// Recognizes Unix (`./`, `../`, `/`) as well as Windows (`.\`, `..\`, `C:\`) prefixes;
+/// the directory prefix keeps its trailing separator, e.g. `.\buidl.ps1` -> (`.\`, `buidl.ps1`).
+#[must_use]
+pub fn split_path_command(command: &str) -> Option<(&str, &str)> {
+    let bytes = command.as_bytes();
+    let has_drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'\\' | b'/');
+    let is_path = has_drive
+        || ["./", "../", "/", ".\\", "..\\"]
+            .iter()
+            .any(|prefix| command.starts_with(prefix));
+    if !is_path {
+        return None;
+    }
+
+    let split_at = command.rfind(['/', '\\'])? + 1;
+    Some(command.split_at(split_at))
+}
+
+/// Correct the file name of a path command against the files in its directory
+///
+/// e.g. `.\buidl.ps1` -> `.\build.ps1` when `build.ps1` exists in the current directory.
+/// The directory prefix is kept exactly as typed.
+#[must_use]
+pub fn correct_path_command(command: &str) -> Option<String> {
+    let (dir, name) = split_path_command(command)?;
+    if name.is_empty() {
+        return None;
+    }
+
+    let files: Vec<String> = std::fs::read_dir(std::path::Path::new(dir))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| !file_type.is_dir()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+
+    if files.iter().any(|file| file == name) {
+        return Some(command.to_string());
+    }
+
+    crate::utils::find_closest_match(name, &files, 0.6).map(|file| format!("{dir}{file}"))
+}
+
+/// Find the dictionary word a misspelled lowercase word was meant to be
+///
+/// Only words of more than three letters are checked, and only against words one typo
+/// away (two for words of more than six letters). Returns `None` for dictionary words
+/// and words with no close match, e.g. `mesage` -> `message`.
+#[must_use]
+pub fn find_dictionary_word(word: &str) -> Option<&'static str> {
+    if word.len() <= 3
+        || !word.chars().all(|c| c.is_ascii_lowercase())
+        || DICTIONARY_WORDS.binary_search(&word).is_ok()
+    {
+        return None;
+    }
+
+    let max_distance = if word.len() > 6 { 2 } else { 1 };
+    DICTIONARY_WORDS
+        .iter()
+        .map(|known| (strsim::damerau_levenshtein(word, known), *known))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Correct misspelled English words among a command line's arguments
+///
+/// Arguments the command has a match for among its own subcommands (see
+/// [`CommandPatterns::find_similar_arg`]) and flags are left alone, as are quoted words,
+/// words naming an existing path and every argument of [`DESTRUCTIVE_COMMANDS`]. Only
+/// the corrected words change; punctuation around them and the spacing between words
+/// are kept, e.g. `git chekout mastr (tpyo)` -> `git chekout mastr (typo)` given `typo`
+/// in the dictionary.
+#[must_use]
+pub fn correct_dictionary_words(command_line: &str, command_patterns: &CommandPatterns) -> String {
+    let inner = strip_wrappers(command_line);
+    let offset = command_line.len() - inner.len();
+    let words = crate::shell::arguments::split_words(inner);
+    let Some((command, arguments)) = words.split_first() else {
+        return command_line.to_string();
+    };
+    if DESTRUCTIVE_COMMANDS.contains(&command.text) {
+        return command_line.to_string();
+    }
+
+    let replacements = arguments.iter().filter_map(|argument| {
+        let token = argument.text;
+        let word = token.trim_matches(|c: char| !c.is_ascii_alphabetic());
+        if argument.quoted
+            || token.starts_with('-')
+            || word.is_empty()
+            || std::path::Path::new(token).exists()
+            || CommandPatterns::find_similar_arg(command.text, word, command_patterns).is_some()
+        {
+            return None;
+        }
+        let known = find_dictionary_word(word)?;
+        let range = offset + argument.range.start..offset + argument.range.end;
+        Some((range, token.replacen(word, known, 1)))
+    });
+    crate::shell::arguments::replace_words(command_line, replacements)
+}
+
+/// Correct the directories of a home-relative path argument against the real ones
+///
+/// e.g. `~/Documnets/file` -> `~/Documents/file` when `Documents` exists in `home`.
+/// Directories are matched one level at a time; once one has no close match, the rest
+/// of the path is kept as typed. The final component is only corrected when the path
+/// ends with a `/`, since it may name a file that doesn't exist yet. Returns `None`
+/// when nothing was corrected.
+#[must_use]
+pub fn correct_home_path(arg: &str, home: &std::path::Path) -> Option<String> {
+    let rest = arg.strip_prefix("~/")?;
+    let components: Vec<&str> = rest.split('/').collect();
+    let (last, dirs) = components.split_last()?;
+
+    let mut current = home.to_path_buf();
+    let mut corrected: Vec<String> = Vec::with_capacity(components.len());
+    let mut matching = true;
+    for component in dirs {
+        let fixed = if !matching || component.is_empty() || current.join(component).is_dir() {
+            (*component).to_string()
+        } else {
+            let names: Vec<String> = std::fs::read_dir(&current)
+                .ok()
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect();
+            if let Some(name) = crate::utils::find_closest_match(component, &names, 0.6) {
+                name.clone()
+            } else {
+                matching = false;
+                (*component).to_string()
+            }
+        };
+        current.push(&fixed);
+        corrected.push(fixed);
+    }
+    corrected.push((*last).to_string());
+
+    let corrected = format!("~/{}", corrected.join("/"));
+    (corrected != arg).then_some(corrected)
+}
+
+/// Correct the case of a path argument's file name against the files that exist
+///
+/// e.g. `README.Md` -> `README.md` when `README.md` exists but `README.Md` doesn't. Only
+/// a file name matching exactly one entry of its directory when case is ignored counts
+/// as a case typo, so intentionally cased arguments are left alone. Returns `None` when
+/// the argument isn't a case typo of an existing path.
+#[must_use]
+pub fn correct_path_case(arg: &str) -> Option<String> {
+    if arg.starts_with('-') || std::path::Path::new(arg).exists() {
+        return None;
+    }
+    let (dir, name) = arg
+        .rfind('/')
+        .map_or(("", arg), |index| arg.split_at(index + 1));
+    if name.is_empty() {
+        return None;
+    }
+
+    let read_from = if dir.is_empty() { "." } else { dir };
+    let mut candidates = std::fs::read_dir(read_from)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|entry| entry.eq_ignore_ascii_case(name));
+    let found = candidates.next()?;
+    candidates.next().is_none().then(|| format!("{dir}{found}"))
+}
+
+/// Get the directories a relative `cd` target is looked up in
+///
+/// That is the current directory followed by each `$CDPATH` entry, where an empty
+/// entry stands for the current directory like it does for the shell.
+#[must_use]
+pub fn cd_search_dirs() -> Vec<std::path::PathBuf> {
+    cd_search_dirs_in(std::env::var_os("CDPATH").as_deref())
+}
+
+/// Get the directories a relative `cd` target is looked up in, given the `$CDPATH` value
+///
+/// See [`cd_search_dirs`].
+#[must_use]
+pub fn cd_search_dirs_in(cdpath: Option<&std::ffi::OsStr>) -> Vec<std::path::PathBuf> {
+    let current = std::path::PathBuf::from(".");
+    let mut dirs = vec![current.clone()];
+    if let Some(cdpath) = cdpath {
+        dirs.extend(std::env::split_paths(cdpath).map(|dir| {
+            if dir.as_os_str().is_empty() {
+                current.clone()
+            } else {
+                dir
             }
+        }));
+    }
+    dirs.dedup();
+    dirs
+}
+
+/// Correct the first directory of a relative `cd` target against the directories found
+/// in `search_dirs`
+///
+/// e.g. `myprj/src` -> `myproj/src` when `myproj` exists in one of them. Targets that
+/// already exist, flags, `-` and targets starting with `/`, `.` or `~` are left alone,
+/// since the shell doesn't look those up in `$CDPATH`. Returns `None` when nothing was
+/// corrected.
+#[must_use]
+pub fn correct_cd_target(target: &str, search_dirs: &[std::path::PathBuf]) -> Option<String> {
+    if target.is_empty() || target.starts_with(['-', '/', '.', '~', '$']) {
+        return None;
+    }
+    if search_dirs.iter().any(|dir| dir.join(target).is_dir()) {
+        return None;
+    }
+
+    let (first, rest) = target
+        .split_once('/')
+        .map_or((target, None), |(first, rest)| (first, Some(rest)));
+    let names: Vec<String> = search_dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    let name = crate::utils::find_closest_match(first, &names, 0.6)?;
+    Some(rest.map_or_else(|| name.clone(), |rest| format!("{name}/{rest}")))
+}
+
+/// Suggest where a binary typed by path has moved to
+///
+/// e.g. `/usr/local/bin/node` -> `/opt/homebrew/bin/node` when the typed path no longer
+/// exists but a `node` is still found in the PATH, as happens after a package manager
+/// relocates its binaries.
+#[must_use]
+pub fn suggest_relocated_binary(path: &str) -> Option<String> {
+    relocated_binary_in(path, std::env::var_os("PATH"))
+}
+
+/// Look for the binary named by `path` in the given search path instead of the PATH
+fn relocated_binary_in(path: &str, search_path: Option<std::ffi::OsString>) -> Option<String> {
+    let (_, name) = split_path_command(path)?;
+    if name.is_empty() || std::path::Path::new(path).exists() {
+        return None;
+    }
+
+    let cwd = std::env::current_dir().ok()?;
+    let relocated = which::which_in(name, search_path, cwd).ok()?;
+    (relocated != std::path::Path::new(path)).then(|| relocated.to_string_lossy().into_owned())
+}
+
+/// Extract the failed command from pasted "command not found" shell error output
+///
+/// Recognizes the formats used by zsh (`zsh: command not found: gti`), bash
+/// (`bash: gti: command not found`), fish (`fish: Unknown command: gti`) and dash
+/// (`sh: 1: gti: not found`). The first matching line wins.
+#[must_use]
+pub fn extract_failed_command(error_output: &str) -> Option<String> {
+    error_output.lines().find_map(|line| {
+        let line = line.trim();
+        let token = if let Some((_, rest)) = line.split_once("command not found: ") {
+            rest
+        } else if let Some((_, rest)) = line.split_once("Unknown command: ") {
+            rest
+        } else if let Some((_, rest)) = line.split_once("Unknown command ") {
+            rest
+        } else if let Some(head) = line
+            .strip_suffix(": command not found")
+            .or_else(|| line.strip_suffix(": not found"))
+        {
+            head.rsplit(": ").next().unwrap_or(head)
         } else {
-            // Remove trailing flags
-            let (arg_base, flags) = remove_trailing_flags(arg);
+            return None;
+        };
 
-            // Try to correct the argument
-            if let Some(corrected_arg) =
-                CommandPatterns::find_similar_arg(&corrected_cmd, arg_base, command_patterns)
-            {
-                corrected_args.push(if flags.is_empty() {
-                    corrected_arg
-                } else {
-                    format!("{corrected_arg}{flags}")
-                });
-                continue;
+        let token = token
+            .split_whitespace()
+            .next()?
+            .trim_matches(|c| matches!(c, '\'' | '"' | '`'));
+        (!token.is_empty()).then(|| token.to_string())
+    })
+}
+
+/// Split a command glued to its first argument by a missing space, e.g. `cd..` -> (`cd`, `..`)
+///
+/// The argument must start like one: with `.`, `-`, `/` or `~`, as in `cd..`, `cd-`,
+/// `ls-la` or `cat/etc/hosts`. An argument starting with `-` must be a lone `-` or a
+/// short flag cluster of up to three letters, so names like `docker-compose` aren't
+/// split. The longest prefix that `is_known` accepts wins, and a token `is_known`
+/// accepts as a whole is never split.
+#[must_use]
+pub fn split_glued_argument(token: &str, is_known: impl Fn(&str) -> bool) -> Option<(&str, &str)> {
+    if is_known(token) {
+        return None;
+    }
+
+    token
+        .char_indices()
+        .rev()
+        .filter(|&(index, c)| index > 0 && matches!(c, '.' | '-' | '/' | '~'))
+        .map(|(index, _)| token.split_at(index))
+        .filter(|(_, argument)| {
+            argument.strip_prefix('-').is_none_or(|flags| {
+                flags.len() <= 3 && flags.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+        })
+        .find(|(command, _)| is_known(command))
+}
+
+/// Find a shell keyword typed where a command was meant, e.g. `fi` in `fi foo.txt`
+///
+/// Only a continuation keyword like `fi` or `do` leading a single simple command with
+/// arguments counts; a line that opens a compound command or chains several commands
+/// may be using the keyword for real.
+#[must_use]
+pub fn misplaced_keyword(command_line: &str) -> Option<&str> {
+    if command_line.contains([';', '\n', '&', '|']) {
+        return None;
+    }
+
+    let mut tokens = command_line.split_whitespace();
+    let keyword = tokens.next().filter(|first| CONTINUATION_KEYWORDS.contains(first))?;
+    let mut rest = tokens.peekable();
+    if rest.peek().is_none() || rest.any(|token| COMPOUND_OPENERS.contains(&token)) {
+        return None;
+    }
+    Some(keyword)
+}
+
+/// Replace a first word one typo away from a wrapper with that wrapper
+///
+/// Only [`BUILTIN_WRAPPERS`] and [`PREFIX_WRAPPERS`] are considered, e.g. `sduo apt
+/// update` -> `sudo apt update`. A line already starting with a wrapper is left alone.
+#[must_use]
+pub fn correct_wrapper_typo(command_line: &str) -> Option<String> {
+    let trimmed = command_line.trim_start();
+    let first = trimmed.split_whitespace().next()?;
+    let mut wrappers = BUILTIN_WRAPPERS
+        .iter()
+        .chain(PREFIX_WRAPPERS.iter().map(|(wrapper, _)| wrapper));
+    if wrappers.clone().any(|wrapper| *wrapper == first) {
+        return None;
+    }
+
+    let wrapper = wrappers.find(|wrapper| strsim::damerau_levenshtein(first, wrapper) == 1)?;
+    Some(format!("{wrapper}{}", &trimmed[first.len()..]))
+}
+
+/// Check whether a token is a `NAME=value` environment assignment
+///
+/// e.g. `FOO=bar` and `RUST_LOG=` are, `--color=always` and `=x` are not
+#[must_use]
+pub fn is_env_assignment(token: &str) -> bool {
+    token.split_once('=').is_some_and(|(name, _)| {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Check whether a token is a duration like `10`, `1.5` or `2m`
+fn is_duration(token: &str) -> bool {
+    let number = token.strip_suffix(['s', 'm', 'h', 'd']).unwrap_or(token);
+    !number.is_empty()
+        && number.chars().any(|c| c.is_ascii_digit())
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Get the command a line runs once all its wrappers are skipped
+///
+/// e.g. `sudo FOO=1 nice -n 5 rm -rf build` -> `rm -rf build`. See [`split_wrapper`];
+/// a line without wrappers is returned trimmed.
+#[must_use]
+pub fn strip_wrappers(command_line: &str) -> &str {
+    let mut inner = command_line.trim_start();
+    while let Some((_, wrapped)) = split_wrapper(inner) {
+        inner = wrapped;
+    }
+    inner
+}
+
+/// Split a command line into a leading wrapper (with its flags) and the wrapped command
+///
+/// Wrappers are shell builtins like `command`/`exec`, prefix commands like `nice`/`time`/`env`
+/// and leading `NAME=value` assignments. The wrapper's own flags and assignments are skipped,
+/// including the values of flags known to take one and the duration of [`DURATION_WRAPPERS`].
+///
+/// e.g. `nice -n 10 cargo biuld` -> (`nice -n 10`, `cargo biuld`),
+/// `env FOO=bar carg run` -> (`env FOO=bar`, `carg run`)
+/// and `timeout 10 carg build` -> (`timeout 10`, `carg build`)
+#[must_use]
+pub fn split_wrapper(command_line: &str) -> Option<(&str, &str)> {
+    let trimmed = command_line.trim_start();
+    let first = trimmed.split_whitespace().next()?;
+    let value_flags: &[&str] = if BUILTIN_WRAPPERS.contains(&first) || is_env_assignment(first) {
+        &[]
+    } else {
+        PREFIX_WRAPPERS
+            .iter()
+            .find(|(wrapper, _)| *wrapper == first)
+            .map(|(_, value_flags)| *value_flags)?
+    };
+
+    // Skip the wrapper and any flags it takes to find where the wrapped command starts
+    let mut rest = &trimmed[first.len()..];
+    let mut expects_value = false;
+    let mut expects_duration = DURATION_WRAPPERS.contains(&first);
+    loop {
+        let next = rest.trim_start();
+        match next.split_whitespace().next() {
+            Some(token) if expects_value || token.starts_with('-') || is_env_assignment(token) => {
+                expects_value = !expects_value && value_flags.contains(&token);
+                rest = &next[token.len()..];
             }
+            Some(token) if expects_duration && is_duration(token) => {
+                expects_duration = false;
+                rest = &next[token.len()..];
+            }
+            Some(_) => break,
+            None => return None,
         }
+    }
 
-        // If we can't correct it, use the original
-        corrected_args.push(arg.to_string());
+    let inner = rest.trim_start();
+    let wrapper = trimmed[..trimmed.len() - inner.len()].trim_end();
+    Some((wrapper, inner))
+}
+
+/// Get how many arguments belong to a package runner invocation
+///
+/// Returns `Some(0)` for runners like `npx`, `Some(1)` for `pnpm dlx`-style runners,
+/// and `None` if the command is not a package runner.
+#[must_use]
+pub fn package_runner_len(command: &str, args: &[&str]) -> Option<usize> {
+    if PACKAGE_RUNNERS.contains(&command) {
+        return Some(0);
     }
 
-    // Combine the corrected command and arguments
-    let corrected_command_line = format!("{} {}", corrected_cmd, corrected_args.join(" "));
+    PACKAGE_RUNNER_SUBCOMMANDS
+        .iter()
+        .any(|(runner, subcommand)| *runner == command && args.first() == Some(subcommand))
+        .then_some(1)
+}
 
-    Some(corrected_command_line.trim().to_string())
+/// Extract the package name from a package runner command line
+///
+/// e.g. `pnpm dlx create-vite my-app` -> `create-vite`
+#[must_use]
+pub fn runner_package(command_line: &str) -> Option<&str> {
+    let parts: Vec<&str> = command_line.split_whitespace().collect();
+    let (command, args) = parts.split_first()?;
+    let runner_len = package_runner_len(command, args)?;
+
+    args.iter()
+        .skip(runner_len)
+        .find(|arg| !arg.starts_with('-'))
+        .copied()
+}
+
+/// Correct the package name of a package runner invocation, leaving everything else alone
+fn fix_runner_args(
+    command: &str,
+    args: &[&str],
+    runner_len: usize,
+    command_patterns: &CommandPatterns,
+) -> String {
+    let mut corrected_args: Vec<String> = args.iter().map(|arg| (*arg).to_string()).collect();
+
+    if let Some(package_index) = (runner_len..args.len()).find(|&i| !args[i].starts_with('-'))
+        && let Some(package) = command_patterns.find_runner_package(args[package_index])
+    {
+        corrected_args[package_index] = package;
+    }
+
+    format!("{} {}", command, corrected_args.join(" ")).trim().to_string()
+}
+
+/// Split a command line before a quoted command it runs elsewhere
+///
+/// Returns the runner with its arguments and the quoted command line with everything
+/// after it, which must be passed through unchanged.
+///
+/// e.g. `ssh host 'gti status'` -> (`ssh host`, `'gti status'`) and `bash -lc "make"` ->
+/// (`bash -lc`, `"make"`), while `git commit -m "msg"` isn't split
+#[must_use]
+pub fn split_quoted_command(command_line: &str) -> Option<(&str, &str)> {
+    split_at_quoted_argument(command_line).filter(|(head, _)| runs_quoted_command(head))
+}
+
+/// Split a command line before its first argument that starts with a quote
+fn split_at_quoted_argument(command_line: &str) -> Option<(&str, &str)> {
+    let trimmed = command_line.trim();
+    let pos = trimmed
+        .char_indices()
+        .find(|&(index, c)| {
+            matches!(c, '\'' | '"')
+                && trimmed[..index].ends_with(char::is_whitespace)
+        })?
+        .0;
+    Some((trimmed[..pos].trim_end(), &trimmed[pos..]))
+}
+
+/// Check whether a command line runs a quoted command once its first word is corrected
+fn runs_quoted_command_once_corrected(
+    head: &str,
+    find_similar_fn: &impl Fn(&str) -> Option<String>,
+) -> bool {
+    let Some(first) = head.split_whitespace().next() else {
+        return false;
+    };
+    let runner = if QUOTED_COMMAND_RUNNERS.iter().any(|(name, _)| *name == first) {
+        Some(first.to_string())
+    } else {
+        find_similar_fn(first)
+    };
+    runner.is_some_and(|runner| runs_quoted_command(&format!("{runner}{}", &head[first.len()..])))
+}
+
+/// Check whether a command line ends where a [`QUOTED_COMMAND_RUNNERS`] entry expects
+/// the command line it runs
+fn runs_quoted_command(head: &str) -> bool {
+    let mut tokens = head.split_whitespace();
+    let Some(command) = tokens.next() else {
+        return false;
+    };
+    let Some((_, marker)) = QUOTED_COMMAND_RUNNERS
+        .iter()
+        .find(|(name, _)| *name == command)
+    else {
+        return false;
+    };
+
+    match marker {
+        None => true,
+        // Short flags may be bundled, as in `bash -lc`
+        Some(flag) if flag.starts_with('-') => tokens.any(|token| {
+            token == *flag
+                || (token.starts_with('-')
+                    && !token.starts_with("--")
+                    && token.ends_with(&flag[1..]))
+        }),
+        Some(subcommand) => tokens.any(|token| token == *subcommand),
+    }
+}
+
+/// Split a command line at its first here-doc opener (`<<`)
+///
+/// Returns the command before the opener and the opener together with everything
+/// after it (delimiter, body and terminator), which must be passed through unchanged.
+/// Only an unquoted `<<` counts: here-strings (`<<<`) and shifts inside arithmetic
+/// (`$((1 << 2))`) aren't openers.
+#[must_use]
+pub fn split_heredoc(command_line: &str) -> Option<(&str, &str)> {
+    let bytes = command_line.as_bytes();
+    let mut arithmetic_parens = 0usize;
+    let mut in_double = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'\'' if !in_double => {
+                i += bytes[i + 1..]
+                    .iter()
+                    .position(|&b| b == b'\'')
+                    .map_or(bytes.len(), |end| end + 1);
+            }
+            b'"' => in_double = !in_double,
+            b'$' if bytes[i + 1..].starts_with(b"((") => {
+                arithmetic_parens += 2;
+                i += 2;
+            }
+            b'(' if arithmetic_parens > 0 => arithmetic_parens += 1,
+            b')' if arithmetic_parens > 0 => arithmetic_parens -= 1,
+            b'<' if !in_double && arithmetic_parens == 0 && bytes[i + 1..].starts_with(b"<") => {
+                if bytes[i + 2..].starts_with(b"<") {
+                    i += 3;
+                    continue;
+                }
+                return Some(command_line.split_at(i));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
 }
 
 /// Correct common flags regardless of the command
@@ -628,3 +2270,6 @@ fn correct_common_flag(flag: &str, command: &str, patterns: &CommandPatterns) ->
     
     None
 }
+
+#[cfg(test)]
+mod tests;
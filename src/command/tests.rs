@@ -0,0 +1,331 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use super::*;
+
+#[test]
+fn dictionary_words_are_corrected_in_place() {
+    let patterns = CommandPatterns::new();
+    assert_eq!(
+        correct_dictionary_words("echo  mesage (mesage)", &patterns),
+        "echo  message (message)"
+    );
+}
+
+#[test]
+fn dictionary_words_skip_quoted_words() {
+    let patterns = CommandPatterns::new();
+    let line = r#"echo "a mesage" 'mesage' mesage\ here"#;
+    assert_eq!(correct_dictionary_words(line, &patterns), line);
+}
+
+#[test]
+fn dictionary_words_skip_destructive_commands() {
+    let patterns = CommandPatterns::new();
+    for line in ["rm mesage", "mv mesage notes", "sudo -u root rm mesage"] {
+        assert_eq!(correct_dictionary_words(line, &patterns), line);
+    }
+}
+
+#[test]
+fn learning_keeps_to_subcommand_and_flag_positions() {
+    let mut patterns = CommandPatterns::new();
+    patterns.learn_from_command("git stash -u -- notes.txt --not-a-flag");
+    patterns.learn_from_command("git -C repo status");
+    patterns.learn_from_command("mytool build --release -");
+    patterns.learn_from_command("mytool notes.txt");
+    patterns.learn_from_command("ls src");
+    patterns.learn_from_command("head -5 notes.txt");
+
+    let git = &patterns.patterns["git"];
+    assert!(git.args.contains(&"stash".to_string()));
+    assert!(!git.args.contains(&"repo".to_string()));
+    assert!(git.flags.contains(&"-u".to_string()));
+    assert!(!git.flags.contains(&"--not-a-flag".to_string()));
+
+    let mytool = &patterns.patterns["mytool"];
+    assert_eq!(mytool.args, ["build"]);
+    assert_eq!(mytool.flags, ["--release"]);
+    assert!(patterns.patterns["ls"].args.is_empty());
+    assert!(!patterns.patterns["head"].flags.contains(&"-5".to_string()));
+}
+
+#[test]
+fn only_learned_differences_from_builtins_are_saved() {
+    let mut patterns = CommandPatterns::new();
+    let saved = serde_json::to_value(&patterns).unwrap();
+    assert_eq!(saved["patterns"], serde_json::json!({}));
+
+    patterns.learn_from_command("git stash --include-untracked");
+    let saved = serde_json::to_value(&patterns).unwrap();
+    let git = &saved["patterns"]["git"];
+    assert_eq!(git["args"], serde_json::json!(["stash"]));
+    assert_eq!(git["flags"], serde_json::json!(["--include-untracked"]));
+    assert_eq!(saved["patterns"].as_object().unwrap().len(), 1);
+
+    let mut restored = CommandPatterns::new();
+    restored.merge_learned(serde_json::from_value(saved).unwrap());
+    assert!(
+        restored.patterns["git"]
+            .args
+            .contains(&"status".to_string())
+    );
+    assert!(restored.patterns["git"].args.contains(&"stash".to_string()));
+}
+
+#[test]
+fn eviction_drops_the_least_recently_used_patterns() {
+    let mut patterns = CommandPatterns::new();
+    patterns.register_help(
+        "discovered",
+        &HelpText {
+            subcommands: vec!["run".to_string()],
+            flags: vec![],
+        },
+    );
+    patterns.learn_from_command("older --flag");
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    patterns.learn_from_command("newer --flag");
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    patterns.touch("older");
+
+    assert_eq!(patterns.evict_least_recently_used(2), ["discovered"]);
+    assert_eq!(patterns.evict_least_recently_used(1), ["newer"]);
+    assert!(patterns.patterns.contains_key("older"));
+    assert_eq!(patterns.evict_least_recently_used(0), ["older"]);
+    assert!(patterns.patterns.contains_key("git"));
+}
+
+#[test]
+fn glued_arguments_split_off_known_commands_only() {
+    let known = |command: &str| matches!(command, "cd" | "ls" | "docker" | "docker-compose");
+    assert_eq!(split_glued_argument("cd..", known), Some(("cd", "..")));
+    assert_eq!(split_glued_argument("cd-", known), Some(("cd", "-")));
+    assert_eq!(split_glued_argument("ls-la", known), Some(("ls", "-la")));
+    assert_eq!(split_glued_argument("docker-compose", known), None);
+    assert_eq!(split_glued_argument("docker-build", known), None);
+    assert_eq!(split_glued_argument("gti..", known), None);
+}
+
+#[test]
+fn heredocs_split_at_unquoted_openers_only() {
+    assert_eq!(
+        split_heredoc("cat <<EOF\nhi\nEOF"),
+        Some(("cat ", "<<EOF\nhi\nEOF"))
+    );
+    assert_eq!(split_heredoc("cat <<< 'a << b'"), None);
+    assert_eq!(split_heredoc("echo 'a << b' \"c << d\" a\\<<b"), None);
+    assert_eq!(split_heredoc("echo $((1 << (2 + 1)))"), None);
+    assert_eq!(
+        split_heredoc("echo $((1 << 2)) <<-END"),
+        Some(("echo $((1 << 2)) ", "<<-END"))
+    );
+}
+
+#[test]
+fn registered_subcommands_are_valid_but_never_saved() {
+    let mut patterns = CommandPatterns::new();
+    patterns.register_subcommand("cargo", "xtask");
+    patterns.register_subcommand("cargo", "build");
+    patterns.register_subcommand("unknown-tool", "run");
+
+    let args: Vec<&str> = patterns
+        .get_args_for_command("cargo")
+        .unwrap()
+        .map(String::as_str)
+        .collect();
+    assert_eq!(args.iter().filter(|arg| **arg == "xtask").count(), 1);
+    assert_eq!(args.iter().filter(|arg| **arg == "build").count(), 1);
+    assert!(patterns.get_args_for_command("unknown-tool").is_none());
+    assert_eq!(
+        CommandPatterns::find_similar_arg("cargo", "xtsak", &patterns).as_deref(),
+        Some("xtask")
+    );
+    assert!(!serde_json::to_string(&patterns).unwrap().contains("xtask"));
+
+    patterns.clear_registered_subcommands();
+    assert!(!patterns.get_args_for_command("cargo").unwrap().any(|arg| arg == "xtask"));
+}
+
+#[test]
+fn heredoc_bodies_are_kept_byte_for_byte() {
+    let find = |command: &str| (command == "catt").then(|| "cat".to_string());
+    let patterns = CommandPatterns::new();
+    assert_eq!(
+        fix_command_line("catt <<EOF\nteh  body\t$HOME\n\nEOF", find, &patterns).as_deref(),
+        Some("cat <<EOF\nteh  body\t$HOME\n\nEOF")
+    );
+    assert_eq!(
+        fix_command_line("catt <<'EOF' > notes.txt\ngti staus\nEOF\n", find, &patterns)
+            .as_deref(),
+        Some("cat <<'EOF' > notes.txt\ngti staus\nEOF\n")
+    );
+}
+
+/// Find commands like a cache knowing `git` and `cargo`
+fn find_git_or_cargo(command: &str) -> Option<String> {
+    match command {
+        "git" | "gti" => Some("git".to_string()),
+        "cargo" | "crago" => Some("cargo".to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn commands_after_shell_builtins_are_corrected() {
+    let patterns = CommandPatterns::new();
+    assert_eq!(
+        fix_command_line("command gti", find_git_or_cargo, &patterns).as_deref(),
+        Some("command git")
+    );
+    assert_eq!(
+        fix_command_line("exec cargo rn", find_git_or_cargo, &patterns).as_deref(),
+        Some("exec cargo run")
+    );
+    assert_eq!(
+        fix_command_line("builtin crago build", find_git_or_cargo, &patterns).as_deref(),
+        Some("builtin cargo build")
+    );
+}
+
+#[test]
+fn synonyms_of_known_flags_are_valid() {
+    let patterns = CommandPatterns::new();
+    let find = |command: &str| Some(command.to_string());
+    assert!(patterns.is_known_flag("rm", "--recursive"));
+    assert!(patterns.is_known_flag("ls", "--human-readable"));
+
+    for line in ["rm --recursive build", "ls -l --human-readable"] {
+        assert_eq!(fix_command_line(line, find, &patterns).as_deref(), Some(line));
+    }
+    assert_eq!(
+        fix_command_line("rm --recursve build", find, &patterns).as_deref(),
+        Some("rm --recursive build")
+    );
+}
+
+#[test]
+fn windows_path_prefixes_are_split_off() {
+    assert_eq!(split_path_command(r".\buidl.ps1"), Some((r".\", "buidl.ps1")));
+    assert_eq!(split_path_command(r"..\tools\buidl.ps1"), Some((r"..\tools\", "buidl.ps1")));
+    assert_eq!(split_path_command(r"C:\tools\buidl.ps1"), Some((r"C:\tools\", "buidl.ps1")));
+    assert_eq!(split_path_command("buidl.ps1"), None);
+}
+
+#[test]
+#[cfg(windows)]
+fn windows_script_paths_are_corrected() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("build.ps1"), "").unwrap();
+    let typed = format!(r"{}\buidl.ps1", dir.path().display());
+    assert_eq!(
+        correct_path_command(&typed),
+        Some(format!(r"{}\build.ps1", dir.path().display()))
+    );
+}
+
+#[test]
+fn wrapper_options_and_their_values_are_skipped() {
+    let patterns = CommandPatterns::new();
+    assert_eq!(
+        fix_command_line("nice -n 10 gti status", find_git_or_cargo, &patterns).as_deref(),
+        Some("nice -n 10 git status")
+    );
+    assert_eq!(
+        fix_command_line("nice -n 10 cargo biuld", find_git_or_cargo, &patterns).as_deref(),
+        Some("nice -n 10 cargo build")
+    );
+    assert_eq!(
+        split_wrapper("strace -o trace.log -f gti status"),
+        Some(("strace -o trace.log -f", "gti status"))
+    );
+}
+
+#[test]
+fn env_assignments_after_wrappers_are_kept() {
+    let patterns = CommandPatterns::new();
+    assert_eq!(
+        fix_command_line("env FOO=bar crago run", find_git_or_cargo, &patterns).as_deref(),
+        Some("env FOO=bar cargo run")
+    );
+    assert_eq!(
+        fix_command_line("sudo FOO=1 gti status", find_git_or_cargo, &patterns).as_deref(),
+        Some("sudo FOO=1 git status")
+    );
+    assert_eq!(
+        fix_command_line("env -i A=1 B=2 gti status", find_git_or_cargo, &patterns).as_deref(),
+        Some("env -i A=1 B=2 git status")
+    );
+}
+
+#[test]
+fn subcommand_flags_typed_first_are_moved_after_it() {
+    let patterns = CommandPatterns::new();
+    assert_eq!(
+        fix_command_line("git --oneline log", find_git_or_cargo, &patterns).as_deref(),
+        Some("git log --oneline")
+    );
+    // Global flags and their values stay in front
+    assert_eq!(
+        fix_command_line("git -C repo --oneline log", find_git_or_cargo, &patterns).as_deref(),
+        Some("git -C repo log --oneline")
+    );
+    assert_eq!(
+        reorder_subcommand_flags("git", vec!["--no-pager", "log"], &patterns),
+        ["--no-pager", "log"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn relocated_binaries_are_found_in_the_search_path() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let old_dir = tempfile::tempdir().unwrap();
+    let new_dir = tempfile::tempdir().unwrap();
+    let old_path = old_dir.path().join("snoofer-tool");
+    let new_path = new_dir.path().join("snoofer-tool");
+    std::fs::write(&new_path, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&new_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let search_path = Some(new_dir.path().as_os_str().to_owned());
+
+    // The binary moved out of the typed directory into one on the search path
+    assert_eq!(
+        relocated_binary_in(old_path.to_str().unwrap(), search_path.clone()),
+        Some(new_path.to_string_lossy().into_owned())
+    );
+    // A path that still exists, or a binary found nowhere, is left alone
+    assert_eq!(relocated_binary_in(new_path.to_str().unwrap(), search_path.clone()), None);
+    let missing = old_dir.path().join("snoofer-missing");
+    assert_eq!(relocated_binary_in(missing.to_str().unwrap(), search_path), None);
+}
+
+#[test]
+fn tar_flag_clusters_are_reordered() {
+    assert_eq!(correct_flag_cluster("tar", "xvfz", None).as_deref(), Some("xvzf"));
+    assert_eq!(
+        correct_flag_cluster("tar", "-xvz", Some("file.tar.gz")).as_deref(),
+        Some("-xvzf")
+    );
+    assert_eq!(correct_flag_cluster("tar", "xvzf", None), None);
+
+    let patterns = CommandPatterns::new();
+    let find = |command: &str| Some(command.to_string());
+    assert_eq!(
+        fix_command_line("tar xvfz file.tar.gz", find, &patterns).as_deref(),
+        Some("tar xvzf file.tar.gz")
+    );
+}
+
+#[test]
+fn curated_arg_typos_are_applied() {
+    let patterns = CommandPatterns::new();
+    for (command, typos) in COMMON_ARG_TYPOS {
+        for (typo, arg) in *typos {
+            assert_eq!(
+                CommandPatterns::find_similar_arg(command, typo, &patterns).as_deref(),
+                Some(*arg),
+                "{command} {typo}"
+            );
+        }
+    }
+}
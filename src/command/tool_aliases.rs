@@ -0,0 +1,270 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Discovery of user-defined subcommands of tools like cargo, git and kubectl
+//!
+//! Each [`SubcommandAliasSource`] knows where one tool keeps its users' own subcommands:
+//! alias sections of its config files or plugin binaries in the PATH. The discovered
+//! names are registered as valid subcommands so they aren't corrected away.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    hash::BuildHasher,
+    path::{Path, PathBuf},
+};
+
+/// A tool whose users can define their own subcommands
+pub trait SubcommandAliasSource {
+    /// The tool the subcommands belong to, e.g. `cargo`
+    fn tool(&self) -> &'static str;
+
+    /// Discover the user-defined subcommands, given the commands in the PATH
+    fn discover(&self, path_commands: &[&str]) -> Vec<String>;
+}
+
+/// Cargo aliases from the `[alias]` table of the global cargo config
+pub struct CargoAliases;
+
+impl SubcommandAliasSource for CargoAliases {
+    fn tool(&self) -> &'static str {
+        "cargo"
+    }
+
+    fn discover(&self, _path_commands: &[&str]) -> Vec<String> {
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+        cargo_home.map_or_else(Vec::new, |cargo_home| Self::in_cargo_home(&cargo_home))
+    }
+}
+
+impl CargoAliases {
+    /// Get the aliases defined in the config files of a cargo home directory
+    fn in_cargo_home(cargo_home: &Path) -> Vec<String> {
+        // `config` is the name cargo used before `config.toml`
+        [cargo_home.join("config.toml"), cargo_home.join("config")]
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|content| section_keys(&content, "alias"))
+            .collect()
+    }
+}
+
+/// Git aliases from the `[alias]` section of the global git config
+pub struct GitAliases;
+
+impl SubcommandAliasSource for GitAliases {
+    fn tool(&self) -> &'static str {
+        "git"
+    }
+
+    fn discover(&self, _path_commands: &[&str]) -> Vec<String> {
+        let paths = [
+            dirs::home_dir().map(|home| home.join(".gitconfig")),
+            dirs::config_dir().map(|config| config.join("git").join("config")),
+        ];
+        paths
+            .iter()
+            .flatten()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|content| section_keys(&content, "alias"))
+            .collect()
+    }
+}
+
+/// Cargo subcommands installed as `cargo-<name>` binaries in the PATH, like `cargo-watch`
+pub struct CargoPlugins;
+
+impl SubcommandAliasSource for CargoPlugins {
+    fn tool(&self) -> &'static str {
+        "cargo"
+    }
+
+    fn discover(&self, path_commands: &[&str]) -> Vec<String> {
+        plugin_names(path_commands, "cargo-")
+    }
+}
+
+/// Git subcommands installed as `git-<name>` binaries in the PATH, like `git-lfs`
+pub struct GitPlugins;
+
+impl SubcommandAliasSource for GitPlugins {
+    fn tool(&self) -> &'static str {
+        "git"
+    }
+
+    fn discover(&self, path_commands: &[&str]) -> Vec<String> {
+        plugin_names(path_commands, "git-")
+    }
+}
+
+/// Kubectl plugins, installed as `kubectl-<name>` binaries in the PATH
+pub struct KubectlPlugins;
+
+impl SubcommandAliasSource for KubectlPlugins {
+    fn tool(&self) -> &'static str {
+        "kubectl"
+    }
+
+    fn discover(&self, path_commands: &[&str]) -> Vec<String> {
+        // `kubectl-foo-bar` runs as `kubectl foo bar` and `kubectl-foo_bar` as `kubectl foo-bar`
+        path_commands
+            .iter()
+            .filter_map(|command| command.strip_prefix("kubectl-"))
+            .filter_map(|plugin| plugin.split('-').next())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.replace('_', "-"))
+            .collect()
+    }
+}
+
+/// Every known source of user-defined subcommands
+pub const SUBCOMMAND_ALIAS_SOURCES: &[&dyn SubcommandAliasSource] = &[
+    &CargoAliases,
+    &CargoPlugins,
+    &GitAliases,
+    &GitPlugins,
+    &KubectlPlugins,
+];
+
+/// Discover the user-defined subcommands of every known tool, sorted and deduplicated
+#[must_use]
+pub fn discover_tool_subcommands<S: BuildHasher>(
+    path_commands: &HashSet<String, S>,
+) -> HashMap<String, Vec<String>> {
+    let path_commands: Vec<&str> = path_commands.iter().map(String::as_str).collect();
+    let mut subcommands: HashMap<String, Vec<String>> = HashMap::new();
+    for source in SUBCOMMAND_ALIAS_SOURCES {
+        let discovered = source.discover(&path_commands);
+        if discovered.is_empty() {
+            continue;
+        }
+        let names = subcommands.entry(source.tool().to_string()).or_default();
+        names.extend(discovered);
+        names.sort();
+        names.dedup();
+    }
+    subcommands
+}
+
+/// Get the subcommands of the plugin binaries named `<prefix><subcommand>` in the PATH
+///
+/// The whole rest of the name is the subcommand: `git-foo-bar` runs as `git foo-bar`.
+fn plugin_names(path_commands: &[&str], prefix: &str) -> Vec<String> {
+    path_commands
+        .iter()
+        .filter_map(|command| command.strip_prefix(prefix))
+        .filter(|name| !name.is_empty())
+        .map(|name| (*name).to_string())
+        .collect()
+}
+
+/// Get the keys of a `[section]` in an INI-like config file such as TOML or gitconfig
+///
+/// Dotted top-level keys (`section.key = ...`) are included too. Quotes around keys
+/// are removed; comments and keys of other sections are skipped.
+fn section_keys(content: &str, section: &str) -> Vec<String> {
+    let dotted_prefix = format!("{section}.");
+    let mut in_section = false;
+    let mut at_top_level = true;
+    let mut keys = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header.trim_end_matches(']').trim();
+            in_section = name == section;
+            at_top_level = false;
+            continue;
+        }
+
+        let Some((key, _)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        let key = if in_section {
+            Some(key)
+        } else if at_top_level {
+            key.strip_prefix(&dotted_prefix)
+        } else {
+            None
+        };
+        if let Some(key) = key.map(|key| key.trim_matches('"'))
+            && !key.is_empty()
+        {
+            keys.push(key.to_string());
+        }
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn plugin_binaries_in_the_path_are_subcommands() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin = tempfile::tempdir().unwrap();
+        for (name, mode) in [("git-foo", 0o755), ("cargo-watch", 0o755), ("git-bar", 0o644)] {
+            let path = bin.path().join(name);
+            fs::write(&path, "").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        let mut commands = HashSet::new();
+        crate::utils::add_dir_commands([bin.path().to_path_buf()], true, &mut commands);
+        let commands: Vec<&str> = commands.iter().map(String::as_str).collect();
+        assert_eq!(GitPlugins.discover(&commands), ["foo"]);
+        assert_eq!(CargoPlugins.discover(&commands), ["watch"]);
+    }
+
+    #[test]
+    fn section_keys_cover_tables_dotted_keys_and_quotes() {
+        let content = r#"
+alias.top = "check"
+"alias.quoted" = "test"
+
+[build]
+jobs = 4
+alias.not-top = "run"
+
+# a comment = "not a key"
+[alias]
+b = "build"
+"t" = "test"
+; also = "a comment"
+xtask = ["run", "--package", "xtask", "--"]
+
+[profile.release]
+lto = true
+"#;
+        assert_eq!(
+            section_keys(content, "alias"),
+            ["top", "quoted", "b", "t", "xtask"]
+        );
+    }
+
+    #[test]
+    fn cargo_aliases_are_read_from_both_config_names() {
+        let home = tempfile::tempdir().unwrap();
+        fs::write(home.path().join("config.toml"), "[alias]\nb = \"build\"\n").unwrap();
+        fs::write(home.path().join("config"), "[alias]\nrr = \"run --release\"\n").unwrap();
+        assert_eq!(CargoAliases::in_cargo_home(home.path()), ["b", "rr"]);
+        assert!(CargoAliases::in_cargo_home(&home.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn kubectl_plugins_map_to_their_first_subcommand() {
+        let commands = ["kubectl", "kubectl-foo-bar", "kubectl-ctx", "kubectl-foo_bar", "kubectl-"];
+        assert_eq!(KubectlPlugins.discover(&commands), ["foo", "ctx", "foo-bar"]);
+
+        let discovered = discover_tool_subcommands(&commands.map(String::from).into());
+        assert_eq!(discovered["kubectl"], ["ctx", "foo", "foo-bar"]);
+    }
+}
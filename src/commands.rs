@@ -1,48 +1,152 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use anyhow::Result;
-use std::{io::Write, process::Command};
-use crate::{CommandCache, HistoryTracker};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::Path,
+    process::{Command, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+use crate::{
+    cache::ClearSummary,
+    daemon::DaemonAnswer,
+    display::{format_confidence_bar, CorrectionRecord, LineFormat},
+    import::{parse_import, ImportFormat, Imported},
+    shell::expansion::ExpandedLine,
+    ollama::AiHistoryLog,
+    utils::{
+        calculate_similarity, calculate_similarity_with, damerau_levenshtein_distance,
+        levenshtein_distance, trigram_similarity,
+    },
+    add_alias, CommandCache, HistoryTracker, MatchSource, Settings,
+};
 
 /// Learns a correction for a typo
 /// 
 /// # Errors
 /// Returns an error if saving the correction to the database fails
-pub fn learn_correction(typo: &str, command: &str) -> Result<()> {
+pub fn learn_correction(typo: &str, command: &str, category: Option<&str>) -> Result<()> {
     let mut cache = CommandCache::load()?;
-    cache.learn_correction(typo, command)?;
-    println!("Got it! 🐺 I'll remember that '{typo}' means '{command}'");
-    cache.save()?;
+    cache.learn_correction(typo, command, category)?;
+    if let Some(category) = category {
+        println!("Got it! 🐺 I'll remember that '{typo}' means '{command}' ({category})");
+    } else {
+        println!("Got it! 🐺 I'll remember that '{typo}' means '{command}'");
+    }
     Ok(())
 }
 
-/// Resets the command cache
+/// Resets the command cache, or only reports what would be cleared with `dry_run`
 /// 
 /// # Errors
 /// Returns an error if the cache file cannot be deleted
-pub fn reset_cache() -> Result<()> {
+pub fn reset_cache(dry_run: bool) -> Result<()> {
     let mut cache = CommandCache::load()?;
+    if dry_run {
+        print_clear_summary(cache.clear_summary(false));
+        return Ok(());
+    }
     cache.clear_cache();
     cache.save()?;
     Ok(())
 }
 
-/// Resets the memory database
+/// Prints what a reset would clear, for `--dry-run`
+fn print_clear_summary(summary: ClearSummary) {
+    println!("🐺 This would clear:");
+    println!("  {} cached commands", summary.commands);
+    println!("  {} learned corrections", summary.learned_corrections);
+    println!("  {} history entries", summary.history_entries);
+    println!("  {} correction categories", summary.categories);
+    println!("  {} declined suggestions", summary.declined_suggestions);
+    println!("  {} learned command patterns", summary.learned_patterns);
+    println!("Nothing was changed.");
+}
+
+/// Checks that corrections are saved and loaded back from the cache file
+///
+/// # Errors
+/// Returns an error naming the cache path and the step that failed
+pub fn self_test() -> Result<()> {
+    let path = CommandCache::default_path()
+        .ok_or_else(|| anyhow::anyhow!("Could not find a cache or home directory"))?;
+    println!("🐺 Testing the cache at {}", path.display());
+    CommandCache::self_test(&path)
+        .map_err(|e| e.context(format!("Self-test failed for the cache at {}", path.display())))?;
+    println!("Corrections are saved and loaded correctly! 🐺");
+    Ok(())
+}
+
+/// Re-parses shell aliases immediately instead of waiting for the daily refresh
+///
+/// # Errors
+/// Returns an error if the shell aliases cannot be parsed or the cache cannot be saved
+pub fn refresh_aliases() -> Result<()> {
+    let mut cache = CommandCache::load()?;
+    let count = cache.force_refresh_aliases()?;
+    println!("Refreshed shell aliases: found {count}! 🐺");
+    Ok(())
+}
+
+/// Rebuilds the cached PATH commands and shell aliases
+///
+/// With `background`, a detached copy of this program does the refresh so the shell
+/// isn't blocked; it runs in its own process group, so Ctrl+C in the shell leaves it be.
+///
+/// # Errors
+/// Returns an error if the background process cannot be started, or the cache cannot
+/// be loaded or saved
+pub fn refresh_cache(background: bool) -> Result<()> {
+    if background {
+        let mut refresh = Command::new(std::env::current_exe()?);
+        refresh
+            .arg("refresh-cache")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            refresh.process_group(0);
+        }
+        refresh.spawn()?;
+        println!("Refreshing the command cache in the background! 🐺");
+        return Ok(());
+    }
+
+    let cache = CommandCache::refresh_saved()?;
+    println!(
+        "Refreshed the command cache: {} commands and aliases! 🐺",
+        cache.command_count()
+    );
+    Ok(())
+}
+
+/// Resets the memory database, or only reports what would be cleared with `dry_run`
 /// 
 /// # Errors
 /// Returns an error if the memory database cannot be reset
-pub fn reset_memory() -> Result<()> {
+pub fn reset_memory(dry_run: bool) -> Result<()> {
     let mut cache = CommandCache::load()?;
+    if dry_run {
+        print_clear_summary(cache.clear_summary(true));
+        return Ok(());
+    }
     cache.clear_memory();
     cache.save()?;
     Ok(())
 }
 
-/// Shows the command history
+/// Shows the command history, as JSON with `json`
 /// 
 /// # Errors
 /// Returns an error if the history file cannot be read or parsed
-pub fn show_history() -> Result<()> {
+pub fn show_history(json: bool) -> Result<()> {
+    if json {
+        return crate::display::display_command_history(true);
+    }
     let cache = CommandCache::load()?;
     if !cache.is_history_enabled() {
         println!("Command history tracking is disabled! 🐺");
@@ -57,57 +161,120 @@ pub fn show_history() -> Result<()> {
 
     println!("🐺 Your recent command corrections:");
     for (i, entry) in history.iter().enumerate() {
-        println!("{}. {} → {}", i + 1, entry.typo, entry.correction);
+        if entry.count > 1 {
+            println!("{}. {} → {} (×{})", i + 1, entry.typo, entry.correction, entry.count);
+        } else {
+            println!("{}. {} → {}", i + 1, entry.typo, entry.correction);
+        }
     }
     Ok(())
 }
 
-/// Shows the most frequent typos
+/// Shows the distinct typos that were corrected to the given command
+///
+/// Corrections match when they equal the command or start with it followed by
+/// arguments, so `git` also covers `git status`.
 /// 
 /// # Errors
-/// Returns an error if the typo data cannot be retrieved or processed
-pub fn show_frequent_typos() -> Result<()> {
+/// Returns an error if the history data cannot be retrieved
+pub fn show_history_for(command: &str) -> Result<()> {
     let cache = CommandCache::load()?;
     if !cache.is_history_enabled() {
         println!("Command history tracking is disabled! 🐺");
         return Ok(());
     }
 
-    let typos = cache.get_frequent_typos(10);
-    if typos.is_empty() {
-        println!("No typos found! 🐺");
+    let mut typo_counts: HashMap<String, usize> = HashMap::new();
+    for entry in cache.get_command_history(cache.get_history_size()) {
+        let matches = entry.correction == command
+            || entry
+                .correction
+                .strip_prefix(command)
+                .is_some_and(|rest| rest.starts_with(' '));
+        if matches {
+            *typo_counts.entry(entry.typo).or_insert(0) += entry.count;
+        }
+    }
+
+    if typo_counts.is_empty() {
+        println!("No corrections to '{command}' found! 🐺");
         return Ok(());
     }
 
-    println!("🐺 Your most common typos:");
+    let mut typos: Vec<(String, usize)> = typo_counts.into_iter().collect();
+    typos.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    println!("🐺 Typos you corrected to '{command}':");
     for (i, (typo, count)) in typos.iter().enumerate() {
         println!("{}. {} ({} times)", i + 1, typo, count);
     }
     Ok(())
 }
 
-/// Shows the most frequent corrections
+/// Shows the most frequent typos, as JSON with `json`
+///
+/// `limit` of `None` shows all of them at once instead of a screenful at a time.
+/// 
+/// # Errors
+/// Returns an error if the typo data cannot be retrieved or processed
+pub fn show_frequent_typos(limit: Option<usize>, json: bool) -> Result<()> {
+    crate::display::display_frequent_typos(limit.unwrap_or(usize::MAX), limit.is_some(), json)
+}
+
+/// Shows the most frequent corrections, as JSON with `json`
+///
+/// `limit` of `None` shows all of them at once instead of a screenful at a time.
 /// 
 /// # Errors
 /// Returns an error if the correction data cannot be retrieved or processed
-pub fn show_frequent_corrections() -> Result<()> {
-    let cache = CommandCache::load()?;
-    if !cache.is_history_enabled() {
-        println!("Command history tracking is disabled! 🐺");
-        return Ok(());
-    }
+pub fn show_frequent_corrections(limit: Option<usize>, json: bool) -> Result<()> {
+    crate::display::display_frequent_corrections(
+        limit.unwrap_or(usize::MAX),
+        limit.is_some(),
+        json,
+    )
+}
 
-    let corrections = cache.get_frequent_corrections(10);
-    if corrections.is_empty() {
-        println!("No corrections found! 🐺");
-        return Ok(());
-    }
+/// Shows accepted and rejected suggestion counts per correction source
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn show_source_stats() -> Result<()> {
+    crate::display::display_source_stats()
+}
 
-    println!("🐺 Your most frequently used corrections:");
-    for (i, (correction, count)) in corrections.iter().enumerate() {
-        println!("{}. {} ({} times)", i + 1, correction, count);
-    }
-    Ok(())
+/// Shows the learned corrections, optionally only those tagged with a category
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn show_learned_corrections(category: Option<&str>) -> Result<()> {
+    crate::display::display_learned_corrections(category)
+}
+
+/// Shows the shell aliases that haven't been used, optionally within the last `since_days`
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn show_unused_aliases(since_days: Option<u64>) -> Result<()> {
+    crate::display::display_unused_aliases(since_days)
+}
+
+/// Records that a shell alias was run, as reported by the shell integration
+///
+/// The use is appended to the alias log next to the cache rather than saved in the
+/// cache, since this runs in the background alongside other commands saving it.
+///
+/// # Errors
+/// Returns an error if the alias log cannot be written
+pub fn record_alias_use(alias: &str) -> Result<()> {
+    let Some(cache_path) = CommandCache::default_path() else {
+        return Ok(());
+    };
+    crate::history::alias_log::append(
+        &crate::history::alias_log::log_path(&cache_path),
+        alias,
+        std::time::SystemTime::now(),
+    )
 }
 
 /// Clears the command history
@@ -143,88 +310,875 @@ pub fn disable_history() -> Result<()> {
     Ok(())
 }
 
-/// Checks a command line for potential corrections
+/// Shows the most recent logged AI interactions
 /// 
 /// # Errors
-/// Returns an error if the command line cannot be processed or suggestions cannot be generated
-pub fn check_command_line(command: &str) -> Result<()> {
+/// Returns an error if the cache or the AI interaction log cannot be read
+pub fn show_ai_history(limit: usize) -> Result<()> {
+    let cache = CommandCache::load()?;
+    if !cache.settings().ai_log_enabled {
+        println!("AI interaction logging is disabled! 🐺");
+        println!("To enable it, run: super_snoofer enable-ai-log");
+    }
+
+    let Some(log) = AiHistoryLog::open_default() else {
+        println!("No AI interactions found! 🐺");
+        return Ok(());
+    };
+
+    let interactions = log.recent(limit)?;
+    if interactions.is_empty() {
+        println!("No AI interactions found! 🐺");
+        return Ok(());
+    }
+
+    println!("🐺 Your recent AI interactions:");
+    for (i, interaction) in interactions.iter().enumerate() {
+        let when: chrono::DateTime<chrono::Local> = interaction.timestamp.into();
+        println!(
+            "{}. [{}] ({}) {}",
+            i + 1,
+            when.format("%Y-%m-%d %H:%M:%S"),
+            interaction.model,
+            interaction.prompt.trim()
+        );
+        let first_line = interaction.response.trim().lines().next().unwrap_or_default();
+        println!("   → {first_line}");
+    }
+    Ok(())
+}
+
+/// Enables logging of AI prompts and responses
+/// 
+/// # Errors
+/// Returns an error if the setting cannot be saved
+pub fn enable_ai_log() -> Result<()> {
     let mut cache = CommandCache::load()?;
-    
-    // Always update if needed to get latest commands
+    cache.settings_mut().ai_log_enabled = true;
+    cache.save()?;
+    Ok(())
+}
+
+/// Disables logging of AI prompts and responses
+/// 
+/// # Errors
+/// Returns an error if the setting cannot be saved
+pub fn disable_ai_log() -> Result<()> {
+    let mut cache = CommandCache::load()?;
+    cache.settings_mut().ai_log_enabled = false;
+    cache.save()?;
+    Ok(())
+}
+
+/// Shows all settings, or updates one when a key and value are given
+/// 
+/// # Errors
+/// Returns an error if the setting is unknown, the value is invalid, or the cache cannot be saved
+pub fn configure(key: Option<&str>, value: Option<&str>) -> Result<()> {
+    let mut cache = CommandCache::load()?;
+
+    match (key, value) {
+        (Some(key), Some(value)) => {
+            cache.set_setting(key, value)?;
+            cache.save()?;
+            println!("Set {key} = {value} 🐺");
+        }
+        (Some(key), None) => {
+            let Some((_, current)) = cache
+                .setting_entries()
+                .into_iter()
+                .find(|(name, _)| *name == key)
+            else {
+                return Err(anyhow::anyhow!("Unknown setting: {key}"));
+            };
+            println!("{key} = {current}");
+        }
+        _ => {
+            println!("🐺 Current settings:");
+            for (name, current) in cache.setting_entries() {
+                println!("{name} = {current}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shows the similarity between two strings under the configured and raw metrics
+/// 
+/// # Errors
+/// Returns an error if the cache cannot be loaded to read the configured algorithm
+pub fn show_similarity(a: &str, b: &str) -> Result<()> {
+    let algorithm = CommandCache::load()?.settings().similarity_algorithm;
+    let lower_a = a.to_lowercase();
+    let lower_b = b.to_lowercase();
+
+    println!("🐺 Comparing '{a}' and '{b}':");
+    println!(
+        "Score ({algorithm}): {:.3}",
+        calculate_similarity_with(a, b, algorithm)
+    );
+    println!("Levenshtein distance: {}", levenshtein_distance(a, b));
+    println!(
+        "Damerau-Levenshtein distance: {}",
+        damerau_levenshtein_distance(&lower_a, &lower_b)
+    );
+    println!("Edit similarity: {:.3}", calculate_similarity(a, b));
+    println!(
+        "Jaro-Winkler similarity: {:.3}",
+        strsim::jaro_winkler(&lower_a, &lower_b)
+    );
+    println!("Trigram similarity: {:.3}", trigram_similarity(a, b));
+    Ok(())
+}
+
+/// Representative command lines timed by [`benchmark_corrections`]
+const BENCH_COMMAND_LINES: &[&str] = &[
+    "gti status",
+    "git status",
+    "carg buld --relese",
+    "cargo test --workspace",
+    "dokcer ps -a",
+    "kubctl get pods",
+    "pyhton3 -m venv .venv",
+    "sl -la",
+    "grpe -r TODO src",
+    "npx creat-vite my-app",
+    "systemclt restart nginx",
+    "qwertyuiop",
+];
+
+/// Times cache loading and command line correction, reporting latency percentiles
+///
+/// The real command cache is used so the numbers reflect the user's own PATH and aliases.
+/// 
+/// # Errors
+/// Returns an error if the cache cannot be loaded
+pub fn benchmark_corrections(iterations: usize) -> Result<()> {
+    let load_start = Instant::now();
+    let cache = CommandCache::load()?;
+    let load_time = load_start.elapsed();
+
+    let iterations = iterations.max(1);
+    let mut timings = Vec::with_capacity(iterations * BENCH_COMMAND_LINES.len());
+    for _ in 0..iterations {
+        for command_line in BENCH_COMMAND_LINES {
+            let start = Instant::now();
+            let _ = std::hint::black_box(cache.fix_command_line(command_line));
+            timings.push(start.elapsed());
+        }
+    }
+    timings.sort_unstable();
+
+    println!("🐺 Correction latency over {} runs:", timings.len());
+    println!("Cache load: {load_time:.2?}");
+    println!("p50: {:.2?}", percentile(&timings, 50));
+    println!("p95: {:.2?}", percentile(&timings, 95));
+    println!("p99: {:.2?}", percentile(&timings, 99));
+    println!("Known commands and aliases: {}", cache.command_count());
+    Ok(())
+}
+
+/// Get the given percentile of sorted, non-empty timings
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let index = (sorted.len() * percent).div_ceil(100).saturating_sub(1);
+    sorted[index.min(sorted.len() - 1)]
+}
+
+/// Extracts the failed command from pasted shell error output and offers a correction
+///
+/// Reads the error output from stdin when no text is given.
+/// 
+/// # Errors
+/// Returns an error if stdin cannot be read or the correction flow fails
+pub fn correct_from_error(text: Option<&str>, threshold: Option<f64>) -> Result<()> {
+    let piped = text.is_none();
+    let error_output = match text {
+        Some(text) => text.to_string(),
+        None => std::io::read_to_string(std::io::stdin())?,
+    };
+
+    let Some(command) = crate::command::extract_failed_command(&error_output) else {
+        println!("Couldn't find a \"command not found\" error in that text! 🐺");
+        return Ok(());
+    };
+
+    // With the error piped in, stdin is used up and can't answer the (Y/n/c) prompt,
+    // so only show the suggestion instead of running anything
+    if piped {
+        let cache = load_cache_with_threshold(threshold)?;
+        match cache.fix_command_line(&command) {
+            Some(correction) if correction != command => {
+                println!("Awoo! 🐺 Did you mean `{correction}`?");
+            }
+            _ => println!("Command '{command}' not found! 🐺"),
+        }
+        return Ok(());
+    }
+
+    check_command_line(&command, threshold)
+}
+
+/// Prints the completed command line for shell integration
+/// 
+/// # Errors
+/// Returns an error if the cache cannot be loaded
+pub fn complete_command_line(command_line: &str) -> Result<()> {
+    let mut cache = CommandCache::load()?;
+    discover_help(&mut cache, command_line)?;
+    println!("{}", cache.generate_full_completion(command_line));
+    Ok(())
+}
+
+/// Manages the shell aliases in a TUI
+///
+/// Returns whether any alias was added, edited or deleted.
+///
+/// # Errors
+/// Returns an error if the cache cannot be loaded or the TUI fails
+pub fn manage_aliases() -> Result<bool> {
+    let mut cache = CommandCache::load()?;
+    cache.force_refresh_aliases()?;
+    crate::tui::run_alias_manager(&mut cache)
+}
+
+/// Fixes a command line in the correction preview TUI and runs the accepted line
+///
+/// Picking a different command than the one typed is recorded like an accepted
+/// correction prompt.
+///
+/// # Errors
+/// Returns an error if the cache cannot be loaded or saved, the TUI fails, or the
+/// accepted command cannot be run
+pub fn fix_interactively(command_line: &str, threshold: Option<f64>) -> Result<()> {
+    let mut cache = load_refreshed_cache(threshold)?;
+    discover_help(&mut cache, command_line)?;
+
+    let Some(accepted) = crate::tui::run_fix_mode(command_line, &cache)? else {
+        return Ok(());
+    };
+
+    let typed = command_line.trim();
+    let cmd_only = typed.split_whitespace().next().unwrap_or_default();
+    if !cmd_only.is_empty() && accepted.split_whitespace().next() != Some(cmd_only) {
+        cache.record_correction_in_line(cmd_only, &accepted, typed);
+        cache.save()?;
+    }
+
+    run_correction(&cache, &accepted)
+}
+
+/// Loads the command cache with the `--threshold` similarity threshold, if one was given
+///
+/// # Errors
+/// Returns an error if the cache cannot be loaded
+fn load_cache_with_threshold(threshold: Option<f64>) -> Result<CommandCache> {
+    let mut cache = CommandCache::load()?;
+    if let Some(threshold) = threshold {
+        cache.set_similarity_threshold(threshold);
+    }
+    Ok(cache)
+}
+
+/// Loads the command cache, rescanning `PATH` and saving it first if it's due an update
+///
+/// # Errors
+/// Returns an error if the cache cannot be loaded, updated or saved
+fn load_refreshed_cache(threshold: Option<f64>) -> Result<CommandCache> {
+    let mut cache = load_cache_with_threshold(threshold)?;
     if cache.should_update() {
         cache.update()?;
         cache.save()?;
     }
+    Ok(cache)
+}
+
+/// Discovers the subcommands of a command line's tool from its `--help` output, if the
+/// `help_discovery` setting is on, saving the cache when the tool was asked
+fn discover_help(cache: &mut CommandCache, command_line: &str) -> Result<()> {
+    if cache.discover_help(command_line) {
+        cache.save()?;
+    }
+    Ok(())
+}
+
+/// Checks a command line for potential corrections
+///
+/// This is reached when the shell can't find the line's command, so there is no tool to
+/// ask for its `--help`; subcommand discovery happens in [`fix_last_command`],
+/// [`fix_interactively`] and completion instead.
+///
+/// A running daemon's answer is used when there is one, see [`daemon_answer`]; the cache
+/// is then only loaded to record what the user made of the suggestion. When the shell
+/// expanded an alias in the line, the correction is shown as typed, see
+/// [`ExpandedLine::from_env`].
+/// 
+/// # Errors
+/// Returns an error if the command line cannot be processed or suggestions cannot be generated
+pub fn check_command_line(command: &str, threshold: Option<f64>) -> Result<()> {
+    let command = command.trim();
+    let mut cache = LazyCache::new(threshold);
+    let answer = match daemon_answer(command, threshold) {
+        Some(answer) => answer,
+        None => DaemonAnswer::for_command_line(cache.refreshed()?, command),
+    };
+    let (correction, source) = match answer {
+        DaemonAnswer::Correction(correction, source) => (correction, source),
+        DaemonAnswer::NotFound(lines) => {
+            for line in lines {
+                println!("{line}");
+            }
+            return Ok(());
+        }
+    };
     
     // Extract just the command part for display purposes
     let cmd_only = command.split_whitespace().next().unwrap_or(command);
     
-    // Try to find a correction
-    if let Some(correction) = cache.fix_command_line(command) {
-        println!("Awoo! 🐺 Did you mean `{correction}`? *wags tail* (Y/n/c)");
+    if cache.settings().auto_run_learned_only {
+        let cache = cache.get()?;
+        let learned = is_learned_correction(cache, command, &correction);
+        if run_learned_only(cache, command, cmd_only, &correction, source, learned)? {
+            return Ok(());
+        }
+    }
+
+    // Shown as typed if the shell expanded an alias in the line
+    let shown = ExpandedLine::from_env(command)
+        .map_or_else(|| correction.clone(), |line| line.typed_correction(&correction));
+    let confidence = format_confidence_bar(calculate_similarity(command, &correction));
+    println!("Awoo! 🐺 Did you mean `{shown}` ({confidence})? *wags tail* (Y/n/c)");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    
+    let cache = cache.get()?;
+    match input.trim().to_lowercase().as_str() {
+        "y" | "" => {
+            cache.record_correction_from(cmd_only, &correction, command, source);
+            cache.save()?;
+            println!("Running suggested command...");
+            run_correction(cache, &correction)?;
+        }
+        "c" => {
+            record_rejection(cache, source)?;
+            print!("What's the correct command? ");
+            std::io::stdout().flush()?;
+            let mut correct = String::new();
+            std::io::stdin().read_line(&mut correct)?;
+            learn_correction(command, correct.trim(), None)?;
+        }
+        _ => {
+            decline_suggestion(cache, cmd_only, &correction);
+            record_rejection(cache, source)?;
+            println!("Command '{cmd_only}' not found! 🐺");
+        }
+    }
+    Ok(())
+}
+
+/// Gets the lines printed for a command that has no correction, with a hint at a
+/// package or well-known command that isn't installed if there's one
+#[must_use]
+pub fn not_found_lines(settings: &Settings, command: &str) -> Vec<String> {
+    if let Some(suggestion) = settings
+        .package_suggestions
+        .then(|| crate::command::find_package_suggestion(command))
+        .flatten()
+    {
+        let mut lines = vec![format!(
+            "Command '{command}' not found! 🐺 Did you mean `{}` from the `{}` package (not installed)?",
+            suggestion.binary, suggestion.package
+        )];
+        if let Some(install_command) = suggestion.install_command {
+            lines.push(format!("Install it with: {install_command}"));
+        }
+        lines
+    } else if let Some(uninstalled) =
+        crate::command::find_uninstalled_command(command, settings.apropos_fallback)
+    {
+        vec![format!("Command '{command}' not found! 🐺 Did you mean `{uninstalled}` (not installed)?")]
+    } else {
+        vec![format!("Command '{command}' not found! 🐺")]
+    }
+}
+
+/// Gets a running daemon's answer for a command line
+///
+/// That's the reply the shell integration already got from the daemon, see
+/// [`crate::daemon::REPLY_VAR`], or else a fresh one. A `--threshold` skips the daemon,
+/// which matches with its own.
+fn daemon_answer(command: &str, threshold: Option<f64>) -> Option<DaemonAnswer> {
+    if threshold.is_some() {
+        return None;
+    }
+    crate::daemon::answer_from_env().or_else(|| crate::daemon::query_daemon(command).ok())
+}
+
+/// The command cache, loaded the first time it's needed
+struct LazyCache {
+    threshold: Option<f64>,
+    cache: Option<CommandCache>,
+}
+
+impl LazyCache {
+    fn new(threshold: Option<f64>) -> Self {
+        Self { threshold, cache: None }
+    }
+
+    /// Gets the cache, loading it first if it isn't yet
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be loaded
+    fn get(&mut self) -> Result<&mut CommandCache> {
+        let cache = match self.cache.take() {
+            Some(cache) => cache,
+            None => load_cache_with_threshold(self.threshold)?,
+        };
+        Ok(self.cache.insert(cache))
+    }
+
+    /// Gets the cache like [`Self::get`], rescanning `PATH` first if it's due an update
+    ///
+    /// # Errors
+    /// Returns an error if the cache cannot be loaded, updated or saved
+    fn refreshed(&mut self) -> Result<&mut CommandCache> {
+        let cache = match self.cache.take() {
+            Some(cache) => cache,
+            None => load_refreshed_cache(self.threshold)?,
+        };
+        Ok(self.cache.insert(cache))
+    }
+
+    /// Gets the settings, reading only those while the cache isn't loaded
+    fn settings(&self) -> Settings {
+        self.cache
+            .as_ref()
+            .map_or_else(CommandCache::load_settings, |cache| cache.settings().clone())
+    }
+}
+
+/// Prints every step the correction logic takes for a command line, as a tree
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn trace_correction(command: &str, threshold: Option<f64>) -> Result<()> {
+    let cache = load_cache_with_threshold(threshold)?;
+    print!("{}", cache.trace(command));
+    Ok(())
+}
+
+/// Prints the correction decision for a command line as one machine-readable line
+///
+/// Nothing is run or recorded; the line is the same suggestion `check_command_line`
+/// would prompt for, so shell widgets can show it as they see fit.
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded or the record cannot be formatted
+pub fn check_line(command: &str, format: LineFormat, threshold: Option<f64>) -> Result<()> {
+    let cache = load_refreshed_cache(threshold)?;
+    println!("{}", correction_record(&cache, command).to_line(format)?);
+    Ok(())
+}
+
+/// Corrects the last command run, `thefuck`-style: shows the fix and runs it on Enter
+///
+/// `command` is the last command as passed by the shell alias; when empty, the last
+/// entry of the shell's history file is used instead. A line starting with a shell
+/// alias is corrected through the alias's expansion and shown with the alias, see
+/// [`ExpandedLine`].
+///
+/// # Errors
+/// Returns an error if there is no last command, the cache cannot be loaded or saved,
+/// or the correction cannot be run
+pub fn fix_last_command(command: &str, threshold: Option<f64>) -> Result<()> {
+    let command = match command.trim() {
+        "" => last_history_command()
+            .ok_or_else(|| anyhow::anyhow!("No previous command found in your shell history"))?,
+        command => command.to_string(),
+    };
+    let mut cache = load_refreshed_cache(threshold)?;
+    let expansion = command
+        .split_whitespace()
+        .next()
+        .and_then(|alias| cache.get_alias_target(alias))
+        .and_then(|target| ExpandedLine::expand(&command, target));
+    let typed = command;
+    let command = expansion
+        .as_ref()
+        .map_or(typed.clone(), |(_, expanded)| expanded.clone());
+    discover_help(&mut cache, &command)?;
+    let record = correction_record(&cache, &command);
+    let Some(corrected) = record.corrected else {
+        println!("Nothing to correct in `{typed}`! 🐺");
+        return Ok(());
+    };
+    let shown = expansion.map_or_else(
+        || corrected.clone(),
+        |(line, _)| line.typed_correction(&corrected),
+    );
+
+    print!(
+        "{} [{}/{}] ",
+        shown.bold().bright_green(),
+        "enter".green(),
+        "ctrl+c".red()
+    );
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    if !input.trim().is_empty() {
+        println!("Not running it! 🐺");
+        return Ok(());
+    }
+
+    let cmd_only = command.split_whitespace().next().unwrap_or(&command);
+    cache.record_correction_from(cmd_only, &corrected, &command, record.source);
+    cache.save()?;
+    run_correction(&cache, &corrected)
+}
+
+/// Gets the last command from the shell history file (`$HISTFILE`, or `~/.zsh_history`)
+///
+/// Zsh's extended history format (`: <time>:<duration>;<command>`) is understood.
+fn last_history_command() -> Option<String> {
+    let path = std::env::var_os("HISTFILE")
+        .map(std::path::PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".zsh_history")))?;
+    // History files may hold bytes that aren't valid UTF-8
+    let content = String::from_utf8_lossy(&std::fs::read(path).ok()?).into_owned();
+    content
+        .lines()
+        .rev()
+        .map(|line| {
+            line.strip_prefix(": ")
+                .and_then(|extended| extended.split_once(';'))
+                .map_or(line, |(_, command)| command)
+                .trim()
+        })
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Works out the correction `check_command_line` would suggest for a command line
+///
+/// A line with no correction, or one that is already right, gets no corrected line.
+fn correction_record(cache: &CommandCache, command: &str) -> CorrectionRecord {
+    let command = command.trim();
+    let cmd_only = command.split_whitespace().next().unwrap_or(command);
+
+    let suggestion = if let Some((correction, source)) = cache.fix_command_line_with_source(command) {
+        let score = calculate_similarity(command, &correction);
+        Some((correction, source, score))
+    } else {
+        cache.get_closest_match(cmd_only).map(|similar| {
+            let source = if cache.get_alias_target(&similar).is_some() {
+                MatchSource::FuzzyAlias
+            } else {
+                MatchSource::FuzzyCommand
+            };
+            let score = calculate_similarity(cmd_only, &similar);
+            (similar, Some(source), score)
+        })
+    };
+
+    // A line that is already right has nothing to correct
+    let Some((corrected, source, score)) =
+        suggestion.filter(|(corrected, _, _)| corrected != command)
+    else {
+        return CorrectionRecord {
+            original: command.to_string(),
+            corrected: None,
+            source: None,
+            score: 0.0,
+            auto_run: false,
+        };
+    };
+    // Only a line learned as a whole runs unasked, not one with a learned command word
+    let auto_run = cache.learned_line(command).as_deref() == Some(corrected.as_str())
+        && cache.settings().dangerous_signature(&corrected).is_none();
+    CorrectionRecord {
+        original: command.to_string(),
+        corrected: Some(corrected),
+        source,
+        score,
+        auto_run,
+    }
+}
+
+/// Checks whether a correction is exactly one the user confirmed before
+///
+/// That's the correction learned for the whole line, or the line with only its command
+/// word replaced by the word's learned correction; a learned command word doesn't vouch
+/// for arguments corrected by fuzzy matching.
+fn is_learned_correction(cache: &CommandCache, typed: &str, correction: &str) -> bool {
+    let typed = typed.trim();
+    let cmd_only = typed.split_whitespace().next().unwrap_or(typed);
+    cache.learned_line(typed).as_deref() == Some(correction)
+        || cache
+            .learned_line(cmd_only)
+            .is_some_and(|learned| format!("{learned}{}", &typed[cmd_only.len()..]) == correction)
+}
+
+/// Applies the `auto_run_learned_only` policy to a suggested correction
+///
+/// Returns `false` when the setting is off, leaving the usual prompt to the caller.
+/// Otherwise a `learned` correction runs right away, while a fresh fuzzy match is
+/// only printed along with how to confirm it for next time. `source` is where the
+/// correction came from, for the unlearn hint after it runs.
+///
+/// # Errors
+/// Returns an error if the cache cannot be saved or the correction cannot be run
+fn run_learned_only(
+    cache: &mut CommandCache,
+    typed: &str,
+    cmd_only: &str,
+    correction: &str,
+    source: Option<MatchSource>,
+    learned: bool,
+) -> Result<bool> {
+    if !cache.settings().auto_run_learned_only {
+        return Ok(false);
+    }
+
+    if learned {
+        cache.record_correction_from(cmd_only, correction, typed, Some(MatchSource::Learned));
+        cache.save()?;
+        println!("Awoo! 🐺 Running your learned correction `{correction}`...");
+        run_correction(cache, correction)?;
+        print_unlearn_hint(cache, typed, cmd_only, correction, source);
+    } else {
+        let confidence = format_confidence_bar(calculate_similarity(typed, correction));
+        println!("Awoo! 🐺 Did you mean `{correction}` ({confidence})? Not running it automatically.");
+        println!(
+            "To run it next time, teach me: super_snoofer learn-correction {cmd_only} {}",
+            correction.split_whitespace().next().unwrap_or(correction)
+        );
+    }
+    Ok(true)
+}
+
+/// Holds back the command of a declined suggestion for the typo it was offered for
+///
+/// Saved along with the rejection right after. A suggestion that kept the typed
+/// command, only correcting its arguments, isn't held back.
+fn decline_suggestion(cache: &mut CommandCache, cmd_only: &str, suggestion: &str) {
+    if let Some(suggested) = suggestion.split_whitespace().next()
+        && suggested != cmd_only
+    {
+        cache.decline_suggestion(cmd_only, suggested);
+    }
+}
+
+/// Records a turned-down suggestion against the source that made it
+///
+/// # Errors
+/// Returns an error if the cache cannot be saved
+fn record_rejection(cache: &mut CommandCache, source: Option<MatchSource>) -> Result<()> {
+    if let Some(source) = source {
+        cache.record_rejection(source);
+    }
+    cache.save()
+}
+
+/// Runs an accepted correction after applying the configured rewrite rules
+///
+/// A line matching one of the `dangerous_commands` signatures only runs once the user
+/// confirms it, however confident the correction was.
+///
+/// # Errors
+/// Returns an error if the confirmation cannot be read or the rewritten command cannot be run
+fn run_correction(cache: &CommandCache, correction: &str) -> Result<()> {
+    let rewritten = cache.settings().apply_rewrite_rules(correction);
+    if rewritten != correction {
+        println!("🐺 Rewritten to `{rewritten}`");
+    }
+
+    if let Some(signature) = cache.settings().dangerous_signature(&rewritten) {
+        print!("⚠️  `{rewritten}` is a dangerous command ({signature}). Run it anyway? (y/N) ");
+        std::io::stdout().flush()?;
         let mut input = String::new();
         std::io::stdin().read_line(&mut input)?;
-        
-        match input.trim().to_lowercase().as_str() {
-            "y" | "" => {
-                println!("Running suggested command...");
-                process_full_command(&correction)?;
-            }
-            "c" => {
-                print!("What's the correct command? ");
-                std::io::stdout().flush()?;
-                let mut correct = String::new();
-                std::io::stdin().read_line(&mut correct)?;
-                learn_correction(command, correct.trim())?;
-            }
-            _ => println!("Command '{cmd_only}' not found! 🐺")
+        if !input.trim().eq_ignore_ascii_case("y") {
+            println!("Not running it! 🐺");
+            return Ok(());
         }
+    }
+
+    run_command(cache, &rewritten)
+}
+
+/// Prints how to forget a learned correction right after it was run automatically
+///
+/// Only shown when the `unlearn_hints` setting is enabled and the correction's source
+/// is [`MatchSource::Learned`].
+fn print_unlearn_hint(
+    cache: &CommandCache,
+    typed: &str,
+    cmd_only: &str,
+    correction: &str,
+    source: Option<MatchSource>,
+) {
+    if !cache.settings().unlearn_hints || source != Some(MatchSource::Learned) {
+        return;
+    }
+
+    if let Some(typo) = learned_typo(cache, typed, cmd_only, correction) {
+        println!(
+            "🐺 Ran `{correction}` (you typed `{typed}`) — wrong? run `super_snoofer unlearn {typo}`"
+        );
+    }
+}
+
+/// Gets the typo whose learned correction produced a correction: the whole typed line,
+/// or its command word when the correction starts with the word's learned correction
+///
+/// A line matched against a similar learned line has no typo of its own to name.
+fn learned_typo<'a>(
+    cache: &CommandCache,
+    typed: &'a str,
+    cmd_only: &'a str,
+    correction: &str,
+) -> Option<&'a str> {
+    if cache.get_direct_correction(typed).map(String::as_str) == Some(correction) {
+        return Some(typed);
+    }
+
+    cache
+        .get_direct_correction(cmd_only)
+        .filter(|learned| {
+            correction
+                .strip_prefix(learned.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+        .map(|_| cmd_only)
+}
+
+/// Forgets a learned correction
+/// 
+/// # Errors
+/// Returns an error if the cache cannot be loaded or saved
+pub fn unlearn_correction(typo: &str) -> Result<()> {
+    let mut cache = CommandCache::load()?;
+    if cache.unlearn_correction(typo) {
+        cache.save()?;
+        println!("Forgot the correction for '{typo}'! 🐺");
     } else {
-        // If we can't find a specific correction, try to suggest a similar command
-        if let Some(similar) = cache.get_closest_match(cmd_only, 0.4) {
-            println!("Awoo! 🐺 Did you mean `{similar}`? *wags tail* (Y/n/c)");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
-            
-            match input.trim().to_lowercase().as_str() {
-                "y" | "" => {
-                    println!("Running suggested command...");
-                    process_full_command(&similar)?;
-                }
-                "c" => {
-                    print!("What's the correct command? ");
-                    std::io::stdout().flush()?;
-                    let mut correct = String::new();
-                    std::io::stdin().read_line(&mut correct)?;
-                    learn_correction(command, correct.trim())?;
-                }
-                _ => println!("Command '{cmd_only}' not found! 🐺")
-            }
-        } else {
-            println!("Command '{cmd_only}' not found! 🐺");
+        println!("No learned correction for '{typo}'! 🐺");
+    }
+    Ok(())
+}
+
+/// Imports corrections and aliases from another tool's export, see [`crate::import`]
+///
+/// Corrections without a category of their own get `category`, if given. Aliases are
+/// added to the shell config unless they're already defined.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, the command cache cannot be
+/// loaded or saved, or an alias cannot be added to the shell config
+pub fn import_corrections(path: &Path, format: ImportFormat, category: Option<&str>) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let imported = parse_import(&content, format)
+        .with_context(|| format!("Failed to parse {} as {format}", path.display()))?;
+
+    let mut cache = CommandCache::load()?;
+    let corrections = imported.iter().filter_map(|entry| match entry {
+        Imported::Correction {
+            typo,
+            correction,
+            category: own,
+        } => Some((typo.as_str(), correction.as_str(), own.as_deref().or(category))),
+        Imported::Alias { .. } => None,
+    });
+    let learned = cache.import_corrections(corrections)?;
+
+    let mut aliases = 0;
+    for entry in &imported {
+        if let Imported::Alias { name, command } = entry
+            && cache.get_alias_target(name) != Some(command)
+        {
+            add_alias(name, Some(command))?;
+            aliases += 1;
         }
     }
+    if aliases > 0 {
+        cache.force_refresh_aliases()?;
+    }
+
+    println!("Imported {learned} corrections and {aliases} aliases from {}! 🐺", path.display());
+    Ok(())
+}
+
+/// Forgets every learned correction tagged with a category
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded or saved
+pub fn unlearn_category(category: &str) -> Result<()> {
+    let mut cache = CommandCache::load()?;
+    let forgotten = cache.unlearn_category(category);
+    if forgotten > 0 {
+        cache.save()?;
+        println!("Forgot {forgotten} corrections in '{category}'! 🐺");
+    } else {
+        println!("No learned corrections in '{category}'! 🐺");
+    }
+    Ok(())
+}
+
+/// Mutes a typo in the frequent typos and frequent corrections lists
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded or saved
+pub fn mute_typo(typo: &str) -> Result<()> {
+    let mut cache = CommandCache::load()?;
+    if cache.mute_typo(typo) {
+        cache.save()?;
+        println!("Muted '{typo}' in your frequent typos and corrections! 🐺");
+    } else {
+        println!("'{typo}' is already muted! 🐺");
+    }
     Ok(())
 }
 
 /// Processes a full command line
+///
+/// While the command runs, interrupts (Ctrl+C) are left to the child: the terminal
+/// delivers SIGINT to the whole foreground process group, so the child receives it
+/// directly while we keep waiting instead of dying and orphaning it. If the child is
+/// killed by a signal, we exit with `128 + signal` like a shell would.
+///
+/// To verify manually, run `super_snoofer full-command "sleep 30"`, press Ctrl+C and
+/// check that `echo $?` prints `130` and no `sleep` process is left behind.
 /// 
 /// # Errors
 /// Returns an error if the command cannot be processed or if there are issues with the command execution
 pub fn process_full_command(command: &str) -> Result<()> {
+    // The cache is only read for the deprecation note, so one that can't load is no error
+    run_command(&CommandCache::load().unwrap_or_default(), command)
+}
+
+/// Runs a full command line with an already loaded cache, see [`process_full_command`]
+///
+/// # Errors
+/// Returns an error if there are issues with the command execution
+pub fn run_command(cache: &CommandCache, command: &str) -> Result<()> {
+    print_deprecation_note(cache, command);
+
     // Execute the command through the shell to ensure PATH is used
     let result = if cfg!(target_os = "windows") {
         Command::new("cmd")
             .args(["/C", command])
             .status()
     } else {
-        Command::new("sh")
-            .args(["-c", command])
-            .status()
+        run_in_shell(command)
     };
     
     match result {
         Ok(status) => {
+            if terminating_signal(status).is_some() {
+                // Mirror the shell: an interrupted command's code becomes ours
+                std::process::exit(shell_exit_code(status));
+            }
             if !status.success() {
                 println!("Command failed with status: {}", status);
             }
@@ -240,4 +1194,168 @@ pub fn process_full_command(command: &str) -> Result<()> {
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Prints a gentle note when the command is deprecated in favor of a newer one
+///
+/// Only shown when the `deprecation_notes` setting is enabled; never blocks execution.
+fn print_deprecation_note(cache: &CommandCache, command_line: &str) {
+    let Some(command) = command_line.split_whitespace().next() else {
+        return;
+    };
+
+    if let Some(replacement) = cache.settings().deprecation_for(command) {
+        eprintln!("🐺 note: `{command}` is deprecated; consider `{replacement}`");
+    }
+}
+
+/// Exit code for a finished child process, following shell conventions
+///
+/// Children killed by a signal map to `128 + signal`.
+#[must_use]
+pub fn shell_exit_code(status: ExitStatus) -> i32 {
+    if let Some(signal) = terminating_signal(status) {
+        return 128 + signal;
+    }
+
+    status.code().unwrap_or(1)
+}
+
+/// Get the signal that terminated a child process, if any
+fn terminating_signal(status: ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        std::os::unix::process::ExitStatusExt::signal(&status)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// Run a command through `sh`, ignoring SIGINT in this process until it finishes
+#[cfg(unix)]
+fn run_in_shell(command: &str) -> std::io::Result<ExitStatus> {
+    use std::os::unix::process::CommandExt;
+
+    let mut child_command = Command::new("sh");
+    child_command.args(["-c", command]);
+
+    // SAFETY: `signal` is async-signal-safe, so it may run between fork and exec.
+    // The child must not inherit our ignored SIGINT disposition.
+    unsafe {
+        child_command.pre_exec(|| {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            Ok(())
+        });
+    }
+
+    // SAFETY: swapping the SIGINT disposition has no memory-safety requirements
+    let previous = unsafe { libc::signal(libc::SIGINT, libc::SIG_IGN) };
+    let result = child_command.status();
+    // SAFETY: restores the disposition saved above
+    unsafe {
+        libc::signal(libc::SIGINT, previous);
+    }
+
+    result
+}
+
+/// Run a command through `sh`
+#[cfg(not(unix))]
+fn run_in_shell(command: &str) -> std::io::Result<ExitStatus> {
+    Command::new("sh").args(["-c", command]).status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_whole_learned_lines_auto_run() {
+        let mut cache = CommandCache::new();
+        cache.learn_correction("gti", "git", None).unwrap();
+        cache.learn_correction("gti stauts", "git status", None).unwrap();
+
+        let record = correction_record(&cache, "gti status");
+        assert_eq!(record.corrected.as_deref(), Some("git status"));
+        assert!(!record.auto_run);
+
+        let record = correction_record(&cache, "gti stauts");
+        assert_eq!(record.corrected.as_deref(), Some("git status"));
+        assert!(record.auto_run);
+    }
+
+    #[test]
+    fn records_report_the_step_that_corrected_the_line() {
+        let mut cache = CommandCache::new();
+        cache.insert("git");
+        cache.insert("ls");
+        cache.learn_correction("gti stauts", "git status", None).unwrap();
+
+        let record = correction_record(&cache, "gti stauts");
+        assert_eq!(record.source, Some(MatchSource::Learned));
+        let record = correction_record(&cache, "gti log");
+        assert_eq!(record.corrected.as_deref(), Some("git log"));
+        assert_eq!(record.source, Some(MatchSource::FuzzyCommand));
+        let record = correction_record(&cache, "ls-la");
+        assert_eq!(record.corrected.as_deref(), Some("ls -la"));
+        assert_eq!(record.source, None);
+    }
+
+    #[test]
+    fn lowered_threshold_suggests_looser_closest_matches() {
+        let mut cache = CommandCache::new();
+        cache.insert("terraform");
+        // Without fuzzy sources, only the closest match fallback suggests a command
+        cache.set_setting("match_precedence", "exact-command").unwrap();
+        assert_eq!(correction_record(&cache, "tfm").corrected, None);
+
+        cache.set_similarity_threshold(0.3);
+        let record = correction_record(&cache, "tfm");
+        assert_eq!(record.corrected.as_deref(), Some("terraform"));
+        assert_eq!(record.source, Some(MatchSource::FuzzyCommand));
+    }
+
+    #[test]
+    fn only_confirmed_corrections_count_as_learned() {
+        let mut cache = CommandCache::new();
+        cache.learn_correction("gti", "git", None).unwrap();
+        cache.learn_correction("cargo biuld", "cargo build", None).unwrap();
+
+        assert!(is_learned_correction(&cache, "gti status", "git status"));
+        assert!(is_learned_correction(&cache, "cargo biuld", "cargo build"));
+        assert!(!is_learned_correction(&cache, "gti stauts", "git status"));
+        assert!(!is_learned_correction(&cache, "cargo tset", "cargo test"));
+    }
+
+    #[test]
+    fn unlearn_hints_only_name_the_typo_behind_the_correction() {
+        let mut cache = CommandCache::new();
+        cache.learn_correction("gti", "git", None).unwrap();
+        cache.learn_correction("cargo biuld", "cargo build", None).unwrap();
+
+        assert_eq!(learned_typo(&cache, "gti status", "gti", "git status"), Some("gti"));
+        assert_eq!(
+            learned_typo(&cache, "cargo biuld", "cargo", "cargo build"),
+            Some("cargo biuld")
+        );
+        // `gti` is learned, but its `git` didn't produce `gitk`
+        assert_eq!(learned_typo(&cache, "gti", "gti", "gitk"), None);
+    }
+
+    #[test]
+    fn learned_only_policy_runs_learned_and_prints_fuzzy_corrections() {
+        let mut cache = CommandCache::new();
+        let learned = Some(MatchSource::Learned);
+        assert!(!run_learned_only(&mut cache, "ture", "ture", "true", learned, true).unwrap());
+
+        cache.set_setting("auto_run_learned_only", "true").unwrap();
+        let fuzzy = Some(MatchSource::FuzzyCommand);
+        assert!(run_learned_only(&mut cache, "ture", "ture", "true", fuzzy, false).unwrap());
+        assert!(cache.get_command_history(10).is_empty());
+        assert!(run_learned_only(&mut cache, "ture", "ture", "true", learned, true).unwrap());
+        assert_eq!(cache.get_command_history(10).len(), 1);
+    }
+}
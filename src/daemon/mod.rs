@@ -0,0 +1,426 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use anyhow::Result;
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::{shell::arguments::join_words, CommandCache, MatchSource};
+#[cfg(unix)]
+use anyhow::Context;
+#[cfg(unix)]
+use log::debug;
+#[cfg(unix)]
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    time::Duration,
+};
+
+/// File name of the daemon's unix domain socket
+pub const SOCKET_FILE: &str = "super_snoofer.sock";
+
+/// Request line that asks a running daemon to shut down
+pub const STOP_REQUEST: &str = "__super_snoofer_stop__";
+
+/// Start of the reply line for a command line with nothing to correct
+///
+/// An explicit reply, so clients can tell it from a daemon that didn't answer. The lines
+/// to print instead of a suggestion follow it, each after a tab.
+pub const NO_CORRECTION_REPLY: &str = "__super_snoofer_no_correction__";
+
+/// Environment variable the shell integration passes a daemon's reply to `super_snoofer` in
+///
+/// The command-not-found handlers ask the daemon themselves and only start `super_snoofer`
+/// to prompt for the correction it answered, or when no daemon answered (an empty reply).
+pub const REPLY_VAR: &str = "SUPER_SNOOFER_DAEMON_REPLY";
+
+/// Stands in for the source of a correction that has none in a reply line
+const NO_SOURCE: &str = "-";
+
+/// How long the daemon and its clients wait on a single socket read/write
+#[cfg(unix)]
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Get the path of the daemon socket
+///
+/// Prefers the user's runtime directory and falls back to the cache or temp directory.
+#[must_use]
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(SOCKET_FILE)
+}
+
+/// Run the daemon in the foreground, answering correction queries over the socket
+///
+/// Each connection sends a single request line: the words of a command line separated
+/// by tabs, as a command-not-found handler got them, or the whole line as a single word
+/// (see [`join_words`]). It receives a single [`DaemonAnswer`] line back.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - Another daemon is already listening on the socket
+/// - The command cache cannot be loaded
+/// - The socket cannot be bound
+#[cfg(unix)]
+pub fn run_daemon() -> Result<()> {
+    let path = socket_path();
+    remove_stale_socket(&path)?;
+
+    // Load and warm the cache up front so every query is answered from memory
+    let mut cache = CommandCache::load()?;
+    if cache.should_update() {
+        cache.update_saved()?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to bind daemon socket at {}", path.display()))?;
+    serve(&listener, &mut cache);
+
+    let _ = fs::remove_file(&path);
+    Ok(())
+}
+
+/// Answer clients on the listener until one asks the daemon to stop
+#[cfg(unix)]
+fn serve(listener: &UnixListener, cache: &mut CommandCache) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("Failed to accept daemon connection: {e}");
+                continue;
+            }
+        };
+
+        match handle_client(cache, &stream) {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(e) => debug!("Error handling daemon client: {e}"),
+        }
+    }
+}
+
+/// Run the daemon in the foreground (unsupported on this platform)
+///
+/// # Errors
+///
+/// Always returns an error since unix domain sockets are unavailable
+#[cfg(not(unix))]
+pub fn run_daemon() -> Result<()> {
+    Err(anyhow::anyhow!("Daemon mode is only supported on unix platforms"))
+}
+
+/// Ask a running daemon to shut down
+///
+/// # Returns
+///
+/// `true` if a daemon was running and has been asked to stop, `false` if none was found.
+/// A stale socket left behind by a crashed daemon is removed.
+///
+/// # Errors
+///
+/// This function will return an error if a stale socket cannot be removed
+#[cfg(unix)]
+pub fn stop_daemon() -> Result<bool> {
+    let path = socket_path();
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    if send_request(&path, STOP_REQUEST).is_ok() {
+        return Ok(true);
+    }
+
+    remove_stale_socket(&path)?;
+    Ok(false)
+}
+
+/// Ask a running daemon to shut down (unsupported on this platform)
+///
+/// # Errors
+///
+/// Never returns an error; there is no daemon to stop
+#[cfg(not(unix))]
+pub fn stop_daemon() -> Result<bool> {
+    Ok(false)
+}
+
+/// The answer to a correction query, as sent in a daemon's reply line
+///
+/// Computed from the loaded cache in the same way when no daemon is running, so both
+/// paths suggest the same correction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaemonAnswer {
+    /// The corrected command line, along with the source of the correction
+    Correction(String, Option<MatchSource>),
+    /// Nothing to correct, with the lines to print instead of a suggestion
+    NotFound(Vec<String>),
+}
+
+impl DaemonAnswer {
+    /// Answer for a command line whose command wasn't found
+    ///
+    /// The line is corrected as a whole or its command word matched alone, see
+    /// [`CommandCache::suggest_correction`]; a line that is already right has nothing to
+    /// correct.
+    #[must_use]
+    pub fn for_command_line(cache: &CommandCache, command_line: &str) -> Self {
+        let command_line = command_line.trim();
+        if let Some((correction, source)) = cache
+            .suggest_correction(command_line)
+            .filter(|(correction, _)| correction != command_line)
+        {
+            return Self::Correction(correction, source);
+        }
+
+        let command = command_line.split_whitespace().next().unwrap_or(command_line);
+        Self::NotFound(crate::commands::not_found_lines(cache.settings(), command))
+    }
+}
+
+impl fmt::Display for DaemonAnswer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Correction(correction, Some(source)) => write!(f, "{source}\t{correction}"),
+            Self::Correction(correction, None) => write!(f, "{NO_SOURCE}\t{correction}"),
+            Self::NotFound(lines) => {
+                f.write_str(NO_CORRECTION_REPLY)?;
+                lines.iter().try_for_each(|line| write!(f, "\t{line}"))
+            }
+        }
+    }
+}
+
+impl FromStr for DaemonAnswer {
+    type Err = anyhow::Error;
+
+    fn from_str(reply: &str) -> Result<Self, Self::Err> {
+        if let Some(lines) = reply.strip_prefix(NO_CORRECTION_REPLY) {
+            return Ok(Self::NotFound(lines.split('\t').skip(1).map(str::to_string).collect()));
+        }
+
+        let (source, correction) = reply
+            .split_once('\t')
+            .ok_or_else(|| anyhow::anyhow!("Malformed daemon reply: {reply}"))?;
+        let source = (source != NO_SOURCE).then(|| source.parse()).transpose()?;
+        Ok(Self::Correction(correction.to_string(), source))
+    }
+}
+
+/// Get the daemon's answer the shell integration passed in [`REPLY_VAR`], if any
+#[must_use]
+pub fn answer_from_env() -> Option<DaemonAnswer> {
+    std::env::var(REPLY_VAR)
+        .ok()
+        .filter(|reply| !reply.is_empty())?
+        .parse()
+        .ok()
+}
+
+/// Query a running daemon for a correction of the given command line
+///
+/// # Errors
+///
+/// Returns an error when no daemon answers, so callers can fall back to loading the
+/// cache themselves
+#[cfg(unix)]
+pub fn query_daemon(command_line: &str) -> Result<DaemonAnswer> {
+    query_daemon_at(&socket_path(), command_line)
+}
+
+/// Query a running daemon for a correction (unsupported on this platform)
+///
+/// # Errors
+///
+/// Always returns an error since unix domain sockets are unavailable
+#[cfg(not(unix))]
+pub fn query_daemon(_command_line: &str) -> Result<DaemonAnswer> {
+    Err(anyhow::anyhow!("Daemon mode is only supported on unix platforms"))
+}
+
+/// Query the daemon listening on `path`, see [`query_daemon`]
+#[cfg(unix)]
+fn query_daemon_at(path: &Path, command_line: &str) -> Result<DaemonAnswer> {
+    send_request(path, command_line)?.parse()
+}
+
+/// Remove the socket file if no daemon is listening on it
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    if UnixStream::connect(path).is_ok() {
+        return Err(anyhow::anyhow!(
+            "A daemon is already running on {}",
+            path.display()
+        ));
+    }
+
+    debug!("Removing stale daemon socket at {}", path.display());
+    fs::remove_file(path)
+        .with_context(|| format!("Failed to remove stale socket at {}", path.display()))
+}
+
+/// Send a single request line to the daemon and read its single-line reply
+#[cfg(unix)]
+fn send_request(path: &Path, request: &str) -> Result<String> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+    stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+
+    writeln!(stream, "{request}")?;
+    stream.flush()?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim_end_matches('\n').to_string())
+}
+
+/// Answer a single client connection
+///
+/// The cache is reloaded first if another process saved it since, so corrections learned
+/// while the daemon runs are used right away.
+///
+/// Returns `true` if the client asked the daemon to stop.
+#[cfg(unix)]
+fn handle_client(cache: &mut CommandCache, stream: &UnixStream) -> Result<bool> {
+    stream.set_read_timeout(Some(SOCKET_TIMEOUT))?;
+    stream.set_write_timeout(Some(SOCKET_TIMEOUT))?;
+
+    let mut request = String::new();
+    BufReader::new(stream).read_line(&mut request)?;
+    let request = request.trim_end_matches('\n');
+
+    let mut writer = stream;
+    if request == STOP_REQUEST {
+        writeln!(writer, "ok")?;
+        return Ok(true);
+    }
+
+    if let Err(e) = cache.reload_if_changed() {
+        debug!("Failed to reload the changed cache: {e}");
+    }
+
+    let words: Vec<&str> = request.split('\t').collect();
+    let answer = DaemonAnswer::for_command_line(cache, &join_words(&words));
+    writeln!(writer, "{answer}")?;
+
+    // Refresh an expired cache once the client has its answer, so no shell waits on the
+    // scan and the next query is answered from a warm cache. It's merged into the saved
+    // cache, so corrections learned since the daemon started aren't overwritten.
+    if cache.should_update() {
+        cache.update_saved()?;
+    }
+
+    Ok(false)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Send a request to `handle_client` over a socket pair and read its reply
+    fn reply_to(cache: &mut CommandCache, request: &str) -> String {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        writeln!(client, "{request}").unwrap();
+        handle_client(cache, &server).unwrap();
+        let mut reply = String::new();
+        BufReader::new(client).read_line(&mut reply).unwrap();
+        reply.trim_end().to_string()
+    }
+
+    #[test]
+    fn replies_with_a_sentinel_when_nothing_needs_correcting() {
+        let mut cache = CommandCache::new();
+        cache.learn_correction("gti", "git", None).unwrap();
+        assert_eq!(reply_to(&mut cache, "gti status"), "learned\tgit status");
+        assert_eq!(
+            reply_to(&mut cache, "qqzx status"),
+            format!("{NO_CORRECTION_REPLY}\tCommand 'qqzx' not found! 🐺")
+        );
+    }
+
+    #[test]
+    fn words_sent_by_a_handler_are_joined_into_a_line() {
+        let mut cache = CommandCache::new();
+        cache.learn_correction("sl", "ls", None).unwrap();
+        assert_eq!(
+            reply_to(&mut cache, "sl\t-l\tmy notes.txt"),
+            "learned\tls -l 'my notes.txt'"
+        );
+    }
+
+    #[test]
+    fn answers_survive_the_reply_line() {
+        let answers = [
+            DaemonAnswer::Correction("git status".to_string(), Some(MatchSource::Learned)),
+            DaemonAnswer::Correction("ls -la".to_string(), None),
+            DaemonAnswer::NotFound(Vec::new()),
+            DaemonAnswer::NotFound(vec![
+                "Command 'htpo' not found!".to_string(),
+                "Install it with: sudo apt install htop".to_string(),
+            ]),
+        ];
+        for answer in answers {
+            assert_eq!(answer.to_string().parse::<DaemonAnswer>().unwrap(), answer);
+        }
+        assert!("git status".parse::<DaemonAnswer>().is_err());
+    }
+
+    #[test]
+    fn queries_are_answered_by_a_serving_daemon_until_it_stops() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SOCKET_FILE);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut cache = CommandCache::new();
+        cache.insert("ls");
+        cache.learn_correction("gti", "git", None).unwrap();
+        let daemon = std::thread::spawn(move || serve(&listener, &mut cache));
+
+        assert_eq!(
+            query_daemon_at(&path, "gti status").unwrap(),
+            DaemonAnswer::Correction("git status".to_string(), Some(MatchSource::Learned))
+        );
+        assert_eq!(
+            query_daemon_at(&path, "ls-la").unwrap(),
+            DaemonAnswer::Correction("ls -la".to_string(), None)
+        );
+        assert!(matches!(
+            query_daemon_at(&path, "ls -la").unwrap(),
+            DaemonAnswer::NotFound(_)
+        ));
+
+        send_request(&path, STOP_REQUEST).unwrap();
+        daemon.join().unwrap();
+        assert!(query_daemon_at(&path, "gti status").is_err());
+    }
+
+    #[test]
+    fn corrections_learned_after_starting_are_used_and_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        let mut seed = CommandCache::new();
+        seed.set_cache_path(path.clone());
+        seed.insert("git");
+        seed.save().unwrap();
+        let mut daemon_cache = CommandCache::load_from_path(&path).unwrap();
+
+        // Another process learns a correction while the daemon runs
+        let mut other = CommandCache::load_from_path(&path).unwrap();
+        other.learn_correction("gti", "git", None).unwrap();
+
+        assert_eq!(reply_to(&mut daemon_cache, "gti status"), "learned\tgit status");
+
+        // An expired snapshot is rescanned without losing it
+        daemon_cache.expire_for_test();
+        daemon_cache.update_saved().unwrap();
+        let saved = CommandCache::load_from_path(&path).unwrap();
+        assert_eq!(saved.get_direct_correction("gti").map(String::as_str), Some("git"));
+    }
+}
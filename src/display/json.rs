@@ -0,0 +1,58 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! JSON output of the history and frequency listings, for `--json`
+//!
+//! History entries are `{typo, correction, timestamp}` objects with RFC 3339 timestamps,
+//! and frequency lists are `{term, count}` objects, most frequent first.
+
+use crate::CommandHistoryEntry;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A history entry as written to JSON
+#[derive(Debug, Serialize)]
+struct HistoryRecord<'a> {
+    typo: &'a str,
+    correction: &'a str,
+    /// When the correction was made, in RFC 3339
+    timestamp: String,
+}
+
+/// A term and how often it occurred, as written to JSON
+#[derive(Debug, Serialize)]
+struct FrequencyRecord<'a> {
+    term: &'a str,
+    count: usize,
+}
+
+/// Format history entries as a JSON array of `{typo, correction, timestamp}` objects
+///
+/// # Errors
+/// Returns an error if the entries cannot be serialized
+pub fn history_json(entries: &[CommandHistoryEntry]) -> Result<String> {
+    let records: Vec<HistoryRecord<'_>> = entries
+        .iter()
+        .map(|entry| HistoryRecord {
+            typo: &entry.typo,
+            correction: &entry.correction,
+            timestamp: DateTime::<Utc>::from(entry.timestamp).to_rfc3339(),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&records)?)
+}
+
+/// Format terms and their counts as a JSON array of `{term, count}` objects
+///
+/// # Errors
+/// Returns an error if the terms cannot be serialized
+pub fn frequency_json(terms: &[(String, usize)]) -> Result<String> {
+    let records: Vec<FrequencyRecord<'_>> = terms
+        .iter()
+        .map(|(term, count)| FrequencyRecord {
+            term,
+            count: *count,
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&records)?)
+}
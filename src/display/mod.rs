@@ -1,10 +1,21 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use crate::HistoryTracker;
+use crate::{HistoryTracker, MatchSource, Verbosity};
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use colored::Colorize;
-use std::time::SystemTime;
+use serde::Serialize;
+use std::{
+    fmt,
+    io::{self, BufRead, IsTerminal, Write},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+pub mod json;
+
+/// Seconds in a day
+const SECS_PER_DAY: u64 = 86_400;
 
 /// Default number of history entries to display
 pub const HISTORY_DISPLAY_LIMIT: usize = 20;
@@ -15,13 +26,121 @@ fn format_time(system_time: SystemTime) -> String {
     datetime.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
+/// Machine-readable format of a correction record
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineFormat {
+    /// Tab-separated fields: original, corrected, source, score, safe to auto-run
+    #[default]
+    Tsv,
+    /// A single-line JSON object
+    Json,
+}
+
+impl fmt::Display for LineFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Tsv => "tsv",
+            Self::Json => "json",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for LineFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tsv" => Ok(Self::Tsv),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow::anyhow!(
+                "Unknown line format '{s}' (expected tsv or json)"
+            )),
+        }
+    }
+}
+
+/// The correction decision for a command line, for shell widgets to act on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CorrectionRecord {
+    /// The command line as typed
+    pub original: String,
+    /// The corrected command line, if there is a correction
+    pub corrected: Option<String>,
+    /// The correction source that suggested the command
+    pub source: Option<MatchSource>,
+    /// How similar the correction is to the typed line, from 0.0 to 1.0
+    pub score: f64,
+    /// Whether the correction can run without asking: the whole line was learned and
+    /// isn't dangerous
+    pub auto_run: bool,
+}
+
+impl CorrectionRecord {
+    /// Format the record as a single line
+    ///
+    /// In TSV, missing values are empty fields and tabs, newlines and backslashes in
+    /// the command lines are escaped as `\t`, `\n` and `\\`.
+    ///
+    /// # Errors
+    /// Returns an error if the record cannot be serialized to JSON
+    pub fn to_line(&self, format: LineFormat) -> Result<String> {
+        match format {
+            LineFormat::Json => Ok(serde_json::to_string(self)?),
+            LineFormat::Tsv => {
+                let escape = |field: &str| {
+                    field
+                        .replace('\\', "\\\\")
+                        .replace('\t', "\\t")
+                        .replace('\n', "\\n")
+                };
+                Ok(format!(
+                    "{}\t{}\t{}\t{:.2}\t{}",
+                    escape(&self.original),
+                    self.corrected.as_deref().map(escape).unwrap_or_default(),
+                    self.source.map(|source| source.to_string()).unwrap_or_default(),
+                    self.score,
+                    self.auto_run
+                ))
+            }
+        }
+    }
+}
+
+/// Width in characters of the confidence bar shown next to suggestions
+pub const CONFIDENCE_BAR_WIDTH: u32 = 10;
+
+/// Format a similarity score (0.0 to 1.0) as a whole percentage, e.g. `92%`
+#[must_use]
+pub fn format_confidence(score: f64) -> String {
+    format!("{:.0}%", score.clamp(0.0, 1.0) * 100.0)
+}
+
+/// Format a similarity score as a small bar followed by its percentage, e.g. `█████████░ 92%`
+#[must_use]
+pub fn format_confidence_bar(score: f64) -> String {
+    let filled = score.clamp(0.0, 1.0) * f64::from(CONFIDENCE_BAR_WIDTH);
+    let bar: String = (0..CONFIDENCE_BAR_WIDTH)
+        .map(|i| if f64::from(i) + 0.5 < filled { '█' } else { '░' })
+        .collect();
+    format!("{} {}", bar, format_confidence(score))
+}
+
 /// Display command correction history
 ///
+/// With `json`, the entries are printed as a JSON array instead, see [`json`].
+///
 /// # Errors
 /// Returns an error if the command cache cannot be loaded
-pub fn display_command_history() -> Result<()> {
+pub fn display_command_history(json: bool) -> Result<()> {
     let cache = crate::CommandCache::load()?;
 
+    if json {
+        let history = cache.get_command_history(HISTORY_DISPLAY_LIMIT);
+        println!("{}", json::history_json(&history)?);
+        return Ok(());
+    }
+
     // Check if history is enabled
     if !cache.is_history_enabled() {
         println!("🐺 Command history tracking is currently disabled.");
@@ -52,16 +171,31 @@ pub fn display_command_history() -> Result<()> {
     println!("{}", "─".repeat(80));
 
     for (i, entry) in history.iter().enumerate() {
+        let repeats = if entry.count > 1 {
+            format!(" (×{})", entry.count)
+        } else {
+            String::new()
+        };
         println!(
-            "{:<5} {:<20} {:<20} {:<30}",
+            "{:<5} {:<20} {:<20} {:<30}{}",
             (i + 1).to_string().bold(),
             entry.typo.bright_red(),
             entry.correction.bright_green(),
-            format_time(entry.timestamp).dimmed()
+            format_time(entry.timestamp).dimmed(),
+            repeats.bright_yellow()
         );
+        if !entry.full_line.is_empty() && entry.full_line != entry.typo {
+            println!("{:<5} {} {}", "", "in:".dimmed(), entry.full_line.dimmed());
+        }
     }
 
     println!("{}", "─".repeat(80));
+
+    let verbosity = cache.settings().verbosity;
+    if verbosity == Verbosity::Terse {
+        return Ok(());
+    }
+
     println!(
         "{} commands shown. Total history: {} entries.",
         history.len(),
@@ -81,17 +215,59 @@ pub fn display_command_history() -> Result<()> {
         "  {} - Clear history",
         "super_snoofer --clear-history".bright_yellow()
     );
+    if verbosity == Verbosity::Chatty {
+        println!(
+            "  {} - Turn your frequent corrections into aliases",
+            "super_snoofer suggest".bright_yellow()
+        );
+    }
+
+    Ok(())
+}
 
+/// Print lines, pausing after each screenful when writing to a terminal
+///
+/// With `paginate` off, or when stdout isn't a terminal, everything is printed at once.
+///
+/// # Errors
+/// Returns an error if writing to stdout or reading the keypress fails
+pub fn print_paged(lines: &[String], paginate: bool) -> Result<()> {
+    let page_size = crossterm::terminal::size().map_or(usize::MAX, |(_, rows)| {
+        usize::from(rows.saturating_sub(1)).max(1)
+    });
+    let paginate = paginate && io::stdout().is_terminal() && lines.len() > page_size;
+
+    let mut stdout = io::stdout().lock();
+    for (i, line) in lines.iter().enumerate() {
+        if paginate && i > 0 && i % page_size == 0 {
+            write!(stdout, "{}", "-- more: Enter to continue, q to quit --".dimmed())?;
+            stdout.flush()?;
+            let mut answer = String::new();
+            io::stdin().lock().read_line(&mut answer)?;
+            if answer.trim().eq_ignore_ascii_case("q") {
+                break;
+            }
+        }
+        writeln!(stdout, "{line}")?;
+    }
     Ok(())
 }
 
 /// Display most frequent typos
 ///
+/// At most `limit` typos are shown, a screenful at a time unless `paginate` is off. With
+/// `json`, they're printed as a JSON array instead, see [`json`].
+///
 /// # Errors
-/// Returns an error if the command cache cannot be loaded
-pub fn display_frequent_typos() -> Result<()> {
+/// Returns an error if the command cache cannot be loaded or output cannot be written
+pub fn display_frequent_typos(limit: usize, paginate: bool, json: bool) -> Result<()> {
     let cache = crate::CommandCache::load()?;
 
+    if json {
+        println!("{}", json::frequency_json(&cache.get_frequent_typos(limit))?);
+        return Ok(());
+    }
+
     // Check if history is enabled
     if !cache.is_history_enabled() {
         println!("🐺 Command history tracking is currently disabled.");
@@ -99,28 +275,40 @@ pub fn display_frequent_typos() -> Result<()> {
         return Ok(());
     }
 
-    let typos = cache.get_frequent_typos(HISTORY_DISPLAY_LIMIT);
+    let typos = cache.get_frequent_typos(limit);
 
     if typos.is_empty() {
         println!("🐺 No typo history found yet.");
         return Ok(());
     }
 
-    println!("🐺 Your most common typos:");
-    for (i, (typo, count)) in typos.iter().enumerate() {
-        println!("{}. {} ({} times)", i + 1, typo.bright_red(), count);
+    if cache.settings().verbosity != Verbosity::Terse {
+        println!("🐺 Your most common typos:");
     }
+    let lines: Vec<String> = typos
+        .iter()
+        .enumerate()
+        .map(|(i, (typo, count))| format!("{}. {} ({} times)", i + 1, typo.bright_red(), count))
+        .collect();
 
-    Ok(())
+    print_paged(&lines, paginate)
 }
 
 /// Display most frequent corrections
 ///
+/// At most `limit` corrections are shown, a screenful at a time unless `paginate` is off.
+/// With `json`, they're printed as a JSON array instead, see [`json`].
+///
 /// # Errors
-/// Returns an error if the command cache cannot be loaded
-pub fn display_frequent_corrections() -> Result<()> {
+/// Returns an error if the command cache cannot be loaded or output cannot be written
+pub fn display_frequent_corrections(limit: usize, paginate: bool, json: bool) -> Result<()> {
     let cache = crate::CommandCache::load()?;
 
+    if json {
+        println!("{}", json::frequency_json(&cache.get_frequent_corrections(limit))?);
+        return Ok(());
+    }
+
     // Check if history is enabled
     if !cache.is_history_enabled() {
         println!("🐺 Command history tracking is currently disabled.");
@@ -128,17 +316,145 @@ pub fn display_frequent_corrections() -> Result<()> {
         return Ok(());
     }
 
-    let corrections = cache.get_frequent_corrections(HISTORY_DISPLAY_LIMIT);
+    let corrections = cache.get_frequent_corrections(limit);
 
     if corrections.is_empty() {
         println!("🐺 No correction history found yet.");
         return Ok(());
     }
 
-    println!("🐺 Your most frequently used corrections:");
-    for (i, (correction, count)) in corrections.iter().enumerate() {
-        println!("{}. {} ({} times)", i + 1, correction.bright_green(), count);
+    if cache.settings().verbosity != Verbosity::Terse {
+        println!("🐺 Your most frequently used corrections:");
     }
+    let lines: Vec<String> = corrections
+        .iter()
+        .enumerate()
+        .map(|(i, (correction, count))| {
+            format!("{}. {} ({} times)", i + 1, correction.bright_green(), count)
+        })
+        .collect();
+
+    print_paged(&lines, paginate)
+}
 
+/// Display the shell aliases not used within the last `since_days`, or never used at all
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn display_unused_aliases(since_days: Option<u64>) -> Result<()> {
+    let cache = crate::CommandCache::load()?;
+
+    if !cache.is_history_enabled() {
+        println!("🐺 Command history tracking is currently disabled.");
+        println!("To enable it, run: super_snoofer --enable-history");
+        return Ok(());
+    }
+
+    let since = since_days.and_then(|days| {
+        SystemTime::now().checked_sub(Duration::from_secs(days.saturating_mul(SECS_PER_DAY)))
+    });
+    let unused = cache.unused_aliases(since);
+    let period = since_days.map_or_else(String::new, |days| format!(" in the last {days} days"));
+    if unused.is_empty() {
+        println!("🐺 Every alias has been used{period}!");
+        return Ok(());
+    }
+
+    if cache.settings().verbosity != Verbosity::Terse {
+        println!(
+            "🐺 {} of {} aliases unused{period}:",
+            unused.len(),
+            cache.shell_aliases().len()
+        );
+    }
+    for (name, target, last_used) in &unused {
+        let last_used = last_used.map_or_else(|| "never used".to_string(), format_time);
+        println!("{:<16} {target}  ({last_used})", name.bright_green());
+    }
+    Ok(())
+}
+
+/// Display the learned corrections, optionally only those tagged with a category
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn display_learned_corrections(category: Option<&str>) -> Result<()> {
+    let cache = crate::CommandCache::load()?;
+    let corrections = cache.learned_corrections(category);
+    if corrections.is_empty() {
+        match category {
+            Some(category) => println!("🐺 No learned corrections in '{category}'!"),
+            None => println!("🐺 No learned corrections yet!"),
+        }
+        return Ok(());
+    }
+
+    if cache.settings().verbosity != Verbosity::Terse {
+        let scope = category.map_or_else(String::new, |category| format!(" in '{category}'"));
+        println!("🐺 {} learned corrections{scope}:", corrections.len());
+    }
+    for (typo, correction, tag) in corrections {
+        let tag = tag.map_or_else(String::new, |tag| format!("  [{tag}]").dimmed().to_string());
+        println!("{:<16} → {}{tag}", typo.bright_red(), correction.bright_green());
+    }
     Ok(())
 }
+
+/// Display how many suggestions from each correction source were accepted and rejected
+///
+/// # Errors
+/// Returns an error if the command cache cannot be loaded
+pub fn display_source_stats() -> Result<()> {
+    let cache = crate::CommandCache::load()?;
+
+    if !cache.is_history_enabled() {
+        println!("🐺 Command history tracking is currently disabled.");
+        println!("To enable it, run: super_snoofer --enable-history");
+        return Ok(());
+    }
+
+    let stats = cache.get_source_stats();
+    if stats.is_empty() {
+        println!("🐺 No correction history found yet.");
+        return Ok(());
+    }
+
+    if cache.settings().verbosity != Verbosity::Terse {
+        println!("🐺 Corrections by source:");
+    }
+    for entry in &stats {
+        let source = entry
+            .source
+            .map_or_else(|| "unknown".to_string(), |source| source.to_string());
+        let acceptance = entry
+            .acceptance_rate()
+            .map_or_else(String::new, |rate| format!(" {}", format_confidence_bar(rate)));
+        println!(
+            "{:<14} {} accepted, {} rejected{acceptance}",
+            source.bright_green(),
+            entry.accepted,
+            entry.rejected
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_is_rounded_to_whole_percentages() {
+        assert_eq!(format_confidence(0.916), "92%");
+        assert_eq!(format_confidence(0.914), "91%");
+        assert_eq!(format_confidence(0.0), "0%");
+        assert_eq!(format_confidence(0.004), "0%");
+        assert_eq!(format_confidence(1.0), "100%");
+        assert_eq!(format_confidence(1.7), "100%");
+        assert_eq!(format_confidence(-0.3), "0%");
+
+        assert_eq!(format_confidence_bar(0.0), "░░░░░░░░░░ 0%");
+        assert_eq!(format_confidence_bar(0.92), "█████████░ 92%");
+        assert_eq!(format_confidence_bar(1.0), "██████████ 100%");
+    }
+}
@@ -0,0 +1,158 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+/// Git subcommands whose positional argument is a branch name
+pub const BRANCH_SUBCOMMANDS: &[&str] = &["checkout", "switch", "branch"];
+
+/// Flags after which the branch argument names a new branch, which must not be corrected
+const BRANCH_CREATE_FLAGS: &[&str] = &["-b", "-B", "-c", "-C", "--orphan"];
+
+/// Flags of `git branch` whose first operand is an existing branch
+const BRANCH_DELETE_FLAGS: &[&str] = &["-d", "-D", "--delete"];
+
+/// Flags of `git branch` renaming the branch given first when two operands follow
+const BRANCH_MOVE_FLAGS: &[&str] = &["-m", "-M", "--move"];
+
+/// Local branches of a repository as of its refs' last modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchCache {
+    /// Latest modification time of `HEAD` and `refs/heads` when the branches were listed
+    pub stamp: SystemTime,
+    pub branches: Vec<String>,
+}
+
+/// Find the root of the git repository containing `start`
+#[must_use]
+pub fn find_repo_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Get the git directory of a repository, following the `gitdir:` file of worktrees
+fn git_dir(root: &Path) -> PathBuf {
+    let dot_git = root.join(".git");
+    if dot_git.is_file()
+        && let Some(dir) = fs::read_to_string(&dot_git).ok().and_then(|contents| {
+            contents
+                .trim()
+                .strip_prefix("gitdir:")
+                .map(|dir| root.join(dir.trim()))
+        })
+    {
+        return dir;
+    }
+    dot_git
+}
+
+/// Get the time the repository's branches last changed
+///
+/// Checking out a branch touches `HEAD`; creating or deleting one touches `refs/heads`.
+#[must_use]
+pub fn refs_stamp(root: &Path) -> Option<SystemTime> {
+    let git_dir = git_dir(root);
+    [git_dir.join("HEAD"), git_dir.join("refs").join("heads")]
+        .iter()
+        .filter_map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .max()
+}
+
+/// List the local branches of a repository
+#[must_use]
+pub fn list_local_branches(root: &Path) -> Vec<String> {
+    Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["branch", "--format=%(refname:short)"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|branch| !branch.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Find the index of the existing-branch argument in a tokenized git command line
+///
+/// The first token is the command and the first non-flag token after it is the
+/// subcommand; the branch is the next non-flag token. There is none after `--` (paths
+/// follow) or after a flag that creates a new branch, like `-b`. `git branch` creates
+/// the branch it's given, so only the operand of `-d` and the old name of `-m old new`
+/// are existing branches.
+#[must_use]
+pub fn branch_argument_index(tokens: &[&str]) -> Option<usize> {
+    let subcommand = tokens
+        .iter()
+        .skip(1)
+        .position(|token| !token.starts_with('-'))?
+        + 1;
+    if tokens[subcommand] == "branch" {
+        return branch_operand_index(tokens, subcommand);
+    }
+
+    for (index, token) in tokens.iter().enumerate().skip(subcommand + 1) {
+        if *token == "--" || BRANCH_CREATE_FLAGS.contains(token) {
+            return None;
+        }
+        if !token.starts_with('-') {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Find the index of the existing-branch operand of a `git branch` line, see
+/// [`branch_argument_index`]
+fn branch_operand_index(tokens: &[&str], subcommand: usize) -> Option<usize> {
+    let flags = &tokens[subcommand + 1..];
+    let operands: Vec<usize> = (subcommand + 1..tokens.len())
+        .filter(|&index| !tokens[index].starts_with('-'))
+        .collect();
+    if flags.contains(&"--") {
+        return None;
+    }
+    if flags.iter().any(|flag| BRANCH_DELETE_FLAGS.contains(flag)) {
+        return operands.first().copied();
+    }
+    if flags.iter().any(|flag| BRANCH_MOVE_FLAGS.contains(flag)) && operands.len() == 2 {
+        return operands.first().copied();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(line: &str) -> Option<usize> {
+        branch_argument_index(&line.split_whitespace().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn branch_arguments_are_existing_branches_only() {
+        assert_eq!(index("git checkout mian"), Some(2));
+        assert_eq!(index("git switch -c feature"), None);
+        assert_eq!(index("git branch feature"), None);
+        assert_eq!(index("git branch -d featrue"), Some(3));
+        assert_eq!(index("git branch -m featrue renamed"), Some(3));
+        assert_eq!(index("git branch -m renamed"), None);
+    }
+}
@@ -0,0 +1,141 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Log of shell alias uses, kept next to the cache file
+//!
+//! The shell integration reports every alias it runs in the background, so recording a
+//! use must be cheap and must not race the cache being saved by the command in the
+//! foreground. Uses are appended to this small log under a file lock instead of going
+//! through a cache load and save, and the cache reads the log when it loads. Each line
+//! is `<seconds since the epoch>\t<alias>`.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Size past which the log is rewritten with only the last use of each alias
+const MAX_LOG_BYTES: u64 = 64 * 1024;
+
+/// Get the path of the alias log kept next to a cache file
+#[must_use]
+pub fn log_path(cache_path: &Path) -> PathBuf {
+    cache_path.with_extension("aliases")
+}
+
+/// Append a use of an alias at `at` to the log
+///
+/// A log grown past [`MAX_LOG_BYTES`] is first compacted to the last use of each alias.
+///
+/// # Errors
+/// Returns an error if the log cannot be opened, locked or written
+pub fn append(log: &Path, alias: &str, at: SystemTime) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(log)
+        .with_context(|| format!("Failed to open alias log at {}", log.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock alias log at {}", log.display()))?;
+
+    if file.metadata()?.len() > MAX_LOG_BYTES {
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let compacted: String = parse(&content)
+            .iter()
+            .map(|(alias, used)| format_line(alias, *used))
+            .collect();
+        file.set_len(0)?;
+        file.write_all(compacted.as_bytes())?;
+    }
+    file.write_all(format_line(alias, at).as_bytes())
+        .with_context(|| format!("Failed to write alias log at {}", log.display()))
+}
+
+/// Read when each alias in the log was last used
+///
+/// A missing or unreadable log has no uses.
+#[must_use]
+pub fn read(log: &Path) -> HashMap<String, SystemTime> {
+    let Ok(mut file) = File::open(log) else {
+        return HashMap::new();
+    };
+    let mut content = String::new();
+    if file.lock_shared().is_err() || file.read_to_string(&mut content).is_err() {
+        return HashMap::new();
+    }
+    parse(&content)
+}
+
+/// Remove the log, e.g. when the history is cleared
+///
+/// # Errors
+/// Returns an error if the log exists but cannot be removed
+pub fn clear(log: &Path) -> Result<()> {
+    match fs::remove_file(log) {
+        Err(error) if error.kind() != ErrorKind::NotFound => {
+            Err(error).with_context(|| format!("Failed to remove alias log at {}", log.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Format one use as a log line
+fn format_line(alias: &str, at: SystemTime) -> String {
+    let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    format!("{secs}\t{alias}\n")
+}
+
+/// Parse log lines into the last use of each alias, skipping malformed lines
+fn parse(content: &str) -> HashMap<String, SystemTime> {
+    let mut uses: HashMap<String, SystemTime> = HashMap::new();
+    for (secs, alias) in content
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(secs, alias)| Some((secs.parse::<u64>().ok()?, alias)))
+    {
+        let at = UNIX_EPOCH + Duration::from_secs(secs);
+        let last = uses.entry(alias.to_string()).or_insert(at);
+        *last = (*last).max(at);
+    }
+    uses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appended_uses_are_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_path(&dir.path().join("cache.json"));
+        let earlier = UNIX_EPOCH + Duration::from_secs(1_000);
+        let later = UNIX_EPOCH + Duration::from_secs(2_000);
+        append(&log, "gs", later).unwrap();
+        append(&log, "gs", earlier).unwrap();
+        append(&log, "ll", earlier).unwrap();
+
+        let uses = read(&log);
+        assert_eq!(uses.len(), 2);
+        assert_eq!(uses["gs"], later);
+        assert_eq!(uses["ll"], earlier);
+
+        clear(&log).unwrap();
+        assert!(read(&log).is_empty());
+    }
+
+    #[test]
+    fn large_logs_are_compacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = log_path(&dir.path().join("cache.json"));
+        for secs in 0..5_000 {
+            append(&log, "gs", UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+        }
+        assert!(fs::metadata(&log).unwrap().len() <= MAX_LOG_BYTES + 32);
+        assert_eq!(read(&log)["gs"], UNIX_EPOCH + Duration::from_secs(4_999));
+    }
+}
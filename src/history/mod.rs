@@ -1,20 +1,57 @@
 #![warn(clippy::all, clippy::pedantic)]
 
+use crate::settings::MatchSource;
+
+pub mod alias_log;
+
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     time::SystemTime,
 };
 
 /// Maximum number of entries in history
 pub const MAX_HISTORY_SIZE: usize = 100_000;
 
+/// Seconds in a day, the unit of the frequency half-life
+const SECS_PER_DAY: f64 = 86_400.0;
+
 /// Entry in the command history
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CommandHistoryEntry {
     pub typo: String,
     pub correction: String,
     pub timestamp: SystemTime,
+    /// Number of consecutive times this exact correction was recorded
+    #[serde(default = "default_entry_count")]
+    pub count: usize,
+    /// Full command line the correction was made in, e.g. `gti status` (empty if unknown)
+    #[serde(default)]
+    pub full_line: String,
+    /// Where the accepted suggestion came from (`None` if unknown)
+    #[serde(default)]
+    pub source: Option<MatchSource>,
+}
+
+/// How suggestions from one correction source fared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceStats {
+    /// The correction source, `None` for corrections recorded without one
+    pub source: Option<MatchSource>,
+    /// Number of accepted suggestions
+    pub accepted: usize,
+    /// Number of rejected suggestions
+    pub rejected: usize,
+}
+
+impl SourceStats {
+    /// Get the share of suggestions that were accepted, if any were made
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        let total = self.accepted + self.rejected;
+        (total > 0).then(|| self.accepted as f64 / total as f64)
+    }
 }
 
 /// Gets whether history tracking is enabled by default
@@ -23,10 +60,44 @@ pub fn default_history_enabled() -> bool {
     true
 }
 
+/// Gets whether consecutive identical corrections are collapsed by default
+#[must_use]
+pub fn default_collapse_repeats() -> bool {
+    true
+}
+
+/// Gets the repeat count of a history entry recorded before counts existed
+#[must_use]
+pub fn default_entry_count() -> usize {
+    1
+}
+
 /// Functions for tracking and analyzing command history
 pub trait HistoryTracker {
     /// Record a correction in the history
-    fn record_correction(&mut self, typo: &str, correction: &str);
+    fn record_correction(&mut self, typo: &str, correction: &str) {
+        self.record_correction_in_line(typo, correction, "");
+    }
+
+    /// Record a correction in the history along with the full command line it was made in
+    fn record_correction_in_line(&mut self, typo: &str, correction: &str, full_line: &str) {
+        self.record_correction_from(typo, correction, full_line, None);
+    }
+
+    /// Record an accepted correction along with the source that suggested it
+    fn record_correction_from(
+        &mut self,
+        typo: &str,
+        correction: &str,
+        full_line: &str,
+        source: Option<MatchSource>,
+    );
+
+    /// Record that a suggestion from `source` was turned down
+    fn record_rejection(&mut self, source: MatchSource);
+
+    /// Get accepted and rejected suggestion counts per source, most accepted first
+    fn get_source_stats(&self) -> Vec<SourceStats>;
 
     /// Get frequent typos with their counts, limited to a specified number
     fn get_frequent_typos(&self, limit: usize) -> Vec<(String, usize)>;
@@ -71,6 +142,28 @@ pub struct HistoryManager {
     /// Whether history tracking is enabled
     #[serde(default = "default_history_enabled")]
     pub history_enabled: bool,
+    /// Whether consecutive identical corrections collapse into one entry
+    ///
+    /// Not saved with the history; the cache sets it from the `collapse_repeated_history`
+    /// setting.
+    #[serde(skip, default = "default_collapse_repeats")]
+    pub collapse_repeats: bool,
+    /// Typos whose corrections are hidden from the frequency statistics
+    #[serde(default)]
+    pub muted_typos: HashSet<String>,
+    /// Number of rejected suggestions per correction source
+    #[serde(default)]
+    pub rejections_by_source: HashMap<MatchSource, usize>,
+    /// Days after which a recorded correction counts half as much when ranking by
+    /// frequency (0 ranks by plain counts)
+    ///
+    /// Not saved with the history; the cache sets it from the `frequency_half_life_days`
+    /// setting.
+    #[serde(skip)]
+    pub frequency_half_life_days: u64,
+    /// When each shell alias was last run, as reported by the shell hook
+    #[serde(default)]
+    pub alias_last_used: HashMap<String, SystemTime>,
 }
 
 impl Default for HistoryManager {
@@ -80,6 +173,11 @@ impl Default for HistoryManager {
             typo_frequency: HashMap::new(),
             correction_frequency: HashMap::new(),
             history_enabled: default_history_enabled(),
+            collapse_repeats: default_collapse_repeats(),
+            muted_typos: HashSet::new(),
+            rejections_by_source: HashMap::new(),
+            frequency_half_life_days: 0,
+            alias_last_used: HashMap::new(),
         }
     }
 }
@@ -105,14 +203,16 @@ impl HistoryManager {
         }
 
         // If no direct correction, look through our history for similar commands
+        let weights = self.recency_weights(|entry| &entry.correction);
         let mut best_match = None;
-        let mut highest_freq = 0;
+        let mut highest_weight = 0.0;
 
         for (correction, freq) in &self.correction_frequency {
             if let Some(similar) = find_similar_fn(correction) {
-                if *freq > highest_freq {
+                let weight = Self::weight_of(weights.as_ref(), correction, *freq);
+                if weight > highest_weight {
                     best_match = Some(similar);
-                    highest_freq = *freq;
+                    highest_weight = weight;
                 }
             }
         }
@@ -121,8 +221,156 @@ impl HistoryManager {
     }
 }
 
+impl HistoryManager {
+    /// Count the flags that appear in recorded corrections starting with `command`
+    ///
+    /// `command` may include subcommands (e.g. `docker run`); flags already part of it
+    /// are not counted. Results are ranked by count, most frequent first.
+    #[must_use]
+    pub fn frequent_flags_for(&self, command: &str) -> Vec<(String, usize)> {
+        let prefix: Vec<&str> = command.split_whitespace().collect();
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in &self.command_history {
+            let tokens: Vec<&str> = entry.correction.split_whitespace().collect();
+            if !tokens.starts_with(&prefix) {
+                continue;
+            }
+
+            for flag in tokens[prefix.len()..].iter().filter(|t| t.starts_with('-')) {
+                *counts.entry((*flag).to_string()).or_insert(0) += entry.count;
+            }
+        }
+
+        let mut flags: Vec<(String, usize)> = counts.into_iter().collect();
+        flags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        flags
+    }
+
+    /// Mute `typo` in the frequency statistics, including corrections recorded later
+    ///
+    /// History entries are kept; a muted typo's corrections are only left out of the
+    /// frequent typos and frequent corrections lists. Returns `false` if it was already
+    /// muted.
+    pub fn mute_typo(&mut self, typo: &str) -> bool {
+        self.muted_typos.insert(typo.to_string())
+    }
+
+    /// Check whether a typo is muted
+    #[must_use]
+    pub fn is_typo_muted(&self, typo: &str) -> bool {
+        self.muted_typos.contains(typo)
+    }
+
+    /// Take in when aliases were last used, as read from the [`alias_log`]
+    ///
+    /// Uses are ignored while history is disabled.
+    pub fn merge_alias_uses(&mut self, uses: HashMap<String, SystemTime>) {
+        if !self.history_enabled {
+            return;
+        }
+        for (alias, used) in uses {
+            let last = self.alias_last_used.entry(alias).or_insert(used);
+            *last = (*last).max(used);
+        }
+    }
+
+    /// Get when an alias was last used, either run directly or reached through a correction
+    #[must_use]
+    pub fn alias_last_used(&self, alias: &str) -> Option<SystemTime> {
+        let corrected_to = self
+            .command_history
+            .iter()
+            .filter(|entry| {
+                entry.correction == alias
+                    || entry.full_line.split_whitespace().next() == Some(alias)
+            })
+            .map(|entry| entry.timestamp)
+            .max();
+        self.alias_last_used
+            .get(alias)
+            .copied()
+            .into_iter()
+            .chain(corrected_to)
+            .max()
+    }
+
+    /// Sum the history entries' counts per key, each halved for every half-life of its age
+    ///
+    /// Returns `None` when no half-life is set, so plain counts are used instead.
+    /// Muted typos are left out.
+    fn recency_weights<'a>(
+        &'a self,
+        key: impl Fn(&'a CommandHistoryEntry) -> &'a str,
+    ) -> Option<HashMap<&'a str, f64>> {
+        if self.frequency_half_life_days == 0 {
+            return None;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let half_life = self.frequency_half_life_days as f64 * SECS_PER_DAY;
+        let now = SystemTime::now();
+        let mut weights = HashMap::new();
+        for entry in &self.command_history {
+            if self.is_typo_muted(&entry.typo) {
+                continue;
+            }
+            let age = now
+                .duration_since(entry.timestamp)
+                .unwrap_or_default()
+                .as_secs_f64();
+            #[allow(clippy::cast_precision_loss)]
+            let weight = entry.count as f64 * 0.5_f64.powf(age / half_life);
+            *weights.entry(key(entry)).or_insert(0.0) += weight;
+        }
+        Some(weights)
+    }
+
+    /// Get the ranking weight of a key: its recency weight if any, else its plain count
+    #[allow(clippy::cast_precision_loss)]
+    fn weight_of(weights: Option<&HashMap<&str, f64>>, key: &str, count: usize) -> f64 {
+        weights.map_or(count as f64, |weights| {
+            weights.get(key).copied().unwrap_or_default()
+        })
+    }
+
+    /// Sort `(key, count)` pairs most frequent first, by recency weight when a half-life is set
+    fn sort_by_frequency(
+        &self,
+        items: &mut [(String, usize)],
+        key: impl Fn(&CommandHistoryEntry) -> &str,
+    ) {
+        let weights = self.recency_weights(key);
+        items.sort_by(|a, b| {
+            Self::weight_of(weights.as_ref(), &b.0, b.1)
+                .total_cmp(&Self::weight_of(weights.as_ref(), &a.0, a.1))
+                .then_with(|| b.1.cmp(&a.1))
+        });
+    }
+
+    /// Count how often each correction was recorded for a muted typo
+    fn muted_correction_counts(&self) -> HashMap<&str, usize> {
+        let mut counts = HashMap::new();
+        for entry in &self.command_history {
+            if self.is_typo_muted(&entry.typo) {
+                *counts.entry(entry.correction.as_str()).or_insert(0) += entry.count;
+            }
+        }
+        counts
+    }
+}
+
 impl HistoryTracker for HistoryManager {
-    fn record_correction(&mut self, typo: &str, correction: &str) {
+    fn record_correction_from(
+        &mut self,
+        typo: &str,
+        correction: &str,
+        full_line: &str,
+        source: Option<MatchSource>,
+    ) {
         // Skip recording if history is disabled
         if !self.history_enabled {
             return;
@@ -135,11 +383,29 @@ impl HistoryTracker for HistoryManager {
             .entry(correction.to_string())
             .or_insert(0) += 1;
 
+        // Collapse a repeat of the most recent correction into its entry
+        if self.collapse_repeats
+            && let Some(latest) = self.command_history.front_mut()
+            && latest.typo == typo
+            && latest.correction == correction
+            && latest.source == source
+        {
+            latest.count += 1;
+            latest.timestamp = SystemTime::now();
+            if !full_line.is_empty() {
+                latest.full_line = full_line.to_string();
+            }
+            return;
+        }
+
         // Add to history
         self.command_history.push_front(CommandHistoryEntry {
             typo: typo.to_string(),
             correction: correction.to_string(),
             timestamp: SystemTime::now(),
+            count: 1,
+            full_line: full_line.to_string(),
+            source,
         });
 
         // Ensure we don't exceed the maximum history size
@@ -148,27 +414,75 @@ impl HistoryTracker for HistoryManager {
         }
     }
 
+    fn record_rejection(&mut self, source: MatchSource) {
+        if self.history_enabled {
+            *self.rejections_by_source.entry(source).or_insert(0) += 1;
+        }
+    }
+
+    fn get_source_stats(&self) -> Vec<SourceStats> {
+        let mut accepted: HashMap<Option<MatchSource>, usize> = HashMap::new();
+        for entry in &self.command_history {
+            *accepted.entry(entry.source).or_insert(0) += entry.count;
+        }
+
+        let sources: HashSet<Option<MatchSource>> = accepted
+            .keys()
+            .copied()
+            .chain(self.rejections_by_source.keys().copied().map(Some))
+            .collect();
+        let mut stats: Vec<SourceStats> = sources
+            .into_iter()
+            .map(|source| SourceStats {
+                source,
+                accepted: accepted.get(&source).copied().unwrap_or(0),
+                rejected: source
+                    .and_then(|source| self.rejections_by_source.get(&source).copied())
+                    .unwrap_or(0),
+            })
+            .collect();
+
+        // Unknown sources go last; known ones follow the default precedence on ties
+        let rank = |source: Option<MatchSource>| {
+            source.map_or(MatchSource::ALL.len(), |source| {
+                MatchSource::ALL.iter().position(|s| *s == source).unwrap_or_default()
+            })
+        };
+        stats.sort_by(|a, b| {
+            b.accepted
+                .cmp(&a.accepted)
+                .then_with(|| rank(a.source).cmp(&rank(b.source)))
+        });
+        stats
+    }
+
     fn get_frequent_typos(&self, limit: usize) -> Vec<(String, usize)> {
         let mut typos: Vec<(String, usize)> = self
             .typo_frequency
             .iter()
+            .filter(|(k, _)| !self.is_typo_muted(k))
             .map(|(k, v)| (k.clone(), *v))
             .collect();
 
-        typos.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by frequency in descending order
+        self.sort_by_frequency(&mut typos, |entry| &entry.typo);
         typos.truncate(limit); // Limit to the requested number
 
         typos
     }
 
     fn get_frequent_corrections(&self, limit: usize) -> Vec<(String, usize)> {
+        let muted = self.muted_correction_counts();
         let mut corrections: Vec<(String, usize)> = self
             .correction_frequency
             .iter()
-            .map(|(k, v)| (k.clone(), *v))
+            .map(|(k, v)| {
+                let muted_count = muted.get(k.as_str()).copied().unwrap_or(0);
+                (k.clone(), v.saturating_sub(muted_count))
+            })
+            .filter(|(_, v)| *v > 0)
             .collect();
 
-        corrections.sort_by(|a, b| b.1.cmp(&a.1)); // Sort by frequency in descending order
+        self.sort_by_frequency(&mut corrections, |entry| &entry.correction);
         corrections.truncate(limit); // Limit to the requested number
 
         corrections
@@ -186,6 +500,8 @@ impl HistoryTracker for HistoryManager {
         self.command_history.clear();
         self.typo_frequency.clear();
         self.correction_frequency.clear();
+        self.rejections_by_source.clear();
+        self.alias_last_used.clear();
     }
 
     fn is_history_enabled(&self) -> bool {
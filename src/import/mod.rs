@@ -0,0 +1,181 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Importing corrections and aliases from other correction tools
+//!
+//! Two formats are understood:
+//!
+//! - `csv`: one `typo,correction[,category]` mapping per line, as exported from
+//!   spreadsheets or other tools. Fields may be double-quoted, with `""` for a quote
+//!   inside one. Blank lines, `#` comments and a `typo,correction` header are skipped.
+//! - `aliases`: shell alias definitions as printed by `alias` in zsh or bash, i.e. the
+//!   aliases `zsh-you-should-use` reminds you of, one `[alias ]name='command'` per line.
+
+use anyhow::{Result, bail};
+use std::{fmt, str::FromStr};
+
+/// A format corrections can be imported from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// `typo,correction[,category]` lines
+    #[default]
+    Csv,
+    /// `alias name='command'` lines
+    Aliases,
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Csv => "csv",
+            Self::Aliases => "aliases",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "aliases" | "you-should-use" => Ok(Self::Aliases),
+            _ => Err(anyhow::anyhow!(
+                "Unknown import format '{s}' (expected csv or aliases)"
+            )),
+        }
+    }
+}
+
+/// A mapping read from an import file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Imported {
+    /// A typo and the command it should be corrected to
+    Correction {
+        typo: String,
+        correction: String,
+        category: Option<String>,
+    },
+    /// A shell alias and the command it expands to
+    Alias { name: String, command: String },
+}
+
+/// Parse the mappings of an import file
+///
+/// # Errors
+/// Returns an error naming the line of the first malformed mapping
+pub fn parse_import(content: &str, format: ImportFormat) -> Result<Vec<Imported>> {
+    let mut imported = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match format {
+            ImportFormat::Csv => {
+                let fields = split_csv_line(line);
+                if index == 0 && fields.first().is_some_and(|field| field == "typo") {
+                    continue;
+                }
+                let mut fields = fields.into_iter();
+                let (Some(typo), Some(correction)) = (fields.next(), fields.next()) else {
+                    bail!("Line {}: expected typo,correction[,category]", index + 1);
+                };
+                if typo.is_empty() || correction.is_empty() {
+                    bail!("Line {}: empty typo or correction", index + 1);
+                }
+                imported.push(Imported::Correction {
+                    typo,
+                    correction,
+                    category: fields.next().filter(|category| !category.is_empty()),
+                });
+            }
+            ImportFormat::Aliases => {
+                let Some((name, command)) = crate::shell::aliases::parse_alias_line(line) else {
+                    bail!("Line {}: expected alias name='command'", index + 1);
+                };
+                imported.push(Imported::Alias { name, command });
+            }
+        }
+    }
+    Ok(imported)
+}
+
+/// Split a CSV line into its trimmed fields, unquoting double-quoted ones
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn correction(typo: &str, correction: &str, category: Option<&str>) -> Imported {
+        Imported::Correction {
+            typo: typo.to_string(),
+            correction: correction.to_string(),
+            category: category.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn csv_lines_are_unquoted_and_headers_skipped() {
+        let content = "typo,correction,category\n\
+                       # comment\n\
+                       gti, git status ,git\n\
+                       \n\
+                       \"sl\",\"ls \"\"-la\"\"\",\n";
+        assert_eq!(
+            parse_import(content, ImportFormat::Csv).unwrap(),
+            [
+                correction("gti", "git status", Some("git")),
+                correction("sl", "ls \"-la\"", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_by_number() {
+        let error = parse_import("gti,git\nsl\n", ImportFormat::Csv).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: expected typo,correction[,category]");
+        let error = parse_import(",git", ImportFormat::Csv).unwrap_err();
+        assert_eq!(error.to_string(), "Line 1: empty typo or correction");
+    }
+
+    #[test]
+    fn alias_definitions_are_imported_as_aliases() {
+        assert_eq!(
+            parse_import("alias gs='git status'\nll='ls -l'\n", ImportFormat::Aliases).unwrap(),
+            [
+                Imported::Alias {
+                    name: "gs".to_string(),
+                    command: "git status".to_string(),
+                },
+                Imported::Alias {
+                    name: "ll".to_string(),
+                    command: "ls -l".to_string(),
+                },
+            ]
+        );
+        assert!(parse_import("not an alias", ImportFormat::Aliases).is_err());
+        assert_eq!("you-should-use".parse::<ImportFormat>().unwrap(), ImportFormat::Aliases);
+    }
+}
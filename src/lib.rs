@@ -3,8 +3,12 @@
 pub mod cache;
 pub mod command;
 pub mod commands;
+pub mod daemon;
 pub mod display;
+pub mod git;
 pub mod history;
+pub mod import;
+pub mod settings;
 pub mod shell;
 pub mod suggestion;
 pub mod utils;
@@ -15,7 +19,8 @@ pub mod tui;
 pub use cache::CommandCache;
 pub use command::CommandPatterns;
 pub use commands::*;  // Re-export all command functions
-pub use history::{CommandHistoryEntry, HistoryManager, HistoryTracker};
+pub use history::{CommandHistoryEntry, HistoryManager, HistoryTracker, SourceStats};
+pub use settings::{CacheFormat, MatchPrecedence, MatchSource, Settings, Verbosity};
 pub use shell::{
     add_alias,
     suggest_aliases,
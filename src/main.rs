@@ -1,22 +1,25 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 use anyhow::Result;
+use std::time::Duration;
 
 // Import modules for functionality
 use super_snoofer::{
+    CommandCache,
     commands::{self as cmd},
-    shell::{add_alias, install_shell_integration, suggest_aliases, uninstall_shell_integration},
+    daemon,
+    ollama::{is_offline_env, AiHistoryLog, ModelConfig, OFFLINE_MESSAGE},
+    shell::{
+        add_alias, arguments::join_words, install_shell_integration, suggest_aliases,
+        uninstall_shell_integration,
+    },
+    tui::run_tui_mode,
 };
-
-use crate::ollama::ModelConfig;
-use crate::tui::run_tui_mode;
 // Import ASCII art constants from ratui_lib
 use ratui_lib::{GAEROS_ASCII, KADE_ASCII};
 
 mod cli;
 use cli::{Cli, Commands};
-mod ollama;
-mod tui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -24,6 +27,7 @@ async fn main() -> Result<()> {
     
     // Create model configuration from CLI parameters
     let model_config = ModelConfig::new(cli.standard_model, cli.code_model);
+    let offline = cli.offline || is_offline_env();
 
     // Check for easter egg commands
     if cli.command_to_check.len() == 1 {
@@ -43,18 +47,23 @@ async fn main() -> Result<()> {
         install_shell_integration()?;
         println!("Shell integration fixed. Please restart your shell or run 'source ~/.zshrc'");
         println!("Launching AI prompt interface now...");
-        return run_tui_mode("", false, model_config).await;
+        return launch_tui("", false, model_config, offline).await;
+    }
+
+    // Correct the last command the way `thefuck` does
+    if cli.thefuck_style {
+        return cmd::fix_last_command(&join_words(&cli.command_to_check), cli.threshold);
     }
 
     // Handle command not found case
     if !cli.command_to_check.is_empty() {
-        let cmd = cli.command_to_check.join(" ");
-        return cmd::check_command_line(&cmd);
+        let cmd = join_words(&cli.command_to_check);
+        return cmd::check_command_line(&cmd, cli.threshold);
     }
 
     // Handle prompt mode
     if let Some(prompt) = cli.prompt.as_ref() {
-        return run_tui_mode(prompt, cli.codestral, model_config).await;
+        return launch_tui(prompt, cli.codestral, model_config, offline).await;
     }
 
     // Handle easter egg commands as regular flags (alternative method)
@@ -81,24 +90,46 @@ async fn main() -> Result<()> {
             println!("Please restart your shell or run 'source ~/.zshrc' to apply changes.");
         }
         Some(Commands::Command { command }) => {
-            cmd::check_command_line(command)?;
+            cmd::check_command_line(command, cli.threshold)?;
+        }
+        Some(Commands::ResetCache { dry_run }) => {
+            cmd::reset_cache(*dry_run)?;
+            if !dry_run {
+                println!("Command cache cleared successfully! 🐺");
+            }
         }
-        Some(Commands::ResetCache) => {
-            cmd::reset_cache()?;
-            println!("Command cache cleared successfully! 🐺");
+        Some(Commands::ResetMemory { dry_run }) => {
+            cmd::reset_memory(*dry_run)?;
+            if !dry_run {
+                println!("Command cache and learned corrections cleared successfully! 🐺");
+            }
         }
-        Some(Commands::ResetMemory) => {
-            cmd::reset_memory()?;
-            println!("Command cache and learned corrections cleared successfully! 🐺");
+        Some(Commands::SelfTest) => {
+            cmd::self_test()?;
+        }
+        Some(Commands::RefreshAliases) => {
+            cmd::refresh_aliases()?;
+        }
+        Some(Commands::RefreshCache { background }) => {
+            cmd::refresh_cache(*background)?;
         }
         Some(Commands::History) => {
-            cmd::show_history()?;
+            cmd::show_history(cli.json)?;
+        }
+        Some(Commands::HistoryFor { command }) => {
+            cmd::show_history_for(command)?;
+        }
+        Some(Commands::FrequentTypos { limit, all }) => {
+            cmd::show_frequent_typos((!all).then_some(*limit), cli.json)?;
         }
-        Some(Commands::FrequentTypos) => {
-            cmd::show_frequent_typos()?;
+        Some(Commands::FrequentCorrections { limit, all }) => {
+            cmd::show_frequent_corrections((!all).then_some(*limit), cli.json)?;
         }
-        Some(Commands::FrequentCorrections) => {
-            cmd::show_frequent_corrections()?;
+        Some(Commands::SourceStats) => {
+            cmd::show_source_stats()?;
+        }
+        Some(Commands::Mute { typo }) => {
+            cmd::mute_typo(typo)?;
         }
         Some(Commands::ClearHistory) => {
             cmd::clear_history()?;
@@ -121,19 +152,96 @@ async fn main() -> Result<()> {
             suggest_aliases()?;
         }
         Some(Commands::CheckCommandLine { command }) => {
-            cmd::check_command_line(command)?;
+            cmd::check_command_line(command, cli.threshold)?;
+        }
+        Some(Commands::CheckLine { command, format }) => {
+            cmd::check_line(command, *format, cli.threshold)?;
         }
         Some(Commands::FullCommand { command }) => {
             cmd::process_full_command(command)?;
         }
-        Some(Commands::LearnCorrection { typo, command }) => {
-            cmd::learn_correction(typo, command)?;
+        Some(Commands::Aliases) => {
+            if cmd::manage_aliases()? {
+                println!("Please restart your shell or run 'source ~/.zshrc' to apply changes.");
+            }
+        }
+        Some(Commands::UnusedAliases { since }) => {
+            cmd::show_unused_aliases(*since)?;
+        }
+        Some(Commands::RecordAliasUse { alias }) => {
+            cmd::record_alias_use(alias)?;
+        }
+        Some(Commands::Fix { command_line }) => {
+            cmd::fix_interactively(command_line.as_deref().unwrap_or_default(), cli.threshold)?;
+        }
+        Some(Commands::Complete { command_line }) => {
+            cmd::complete_command_line(command_line)?;
+        }
+        Some(Commands::LearnCorrection {
+            typo,
+            command,
+            category,
+        }) => {
+            cmd::learn_correction(typo, command, category.as_deref())?;
             println!("Correction learned successfully! 🐺");
         }
+        Some(Commands::Unlearn { typo, category }) => match (typo, category) {
+            (_, Some(category)) => cmd::unlearn_category(category)?,
+            (Some(typo), None) => cmd::unlearn_correction(typo)?,
+            (None, None) => unreachable!("clap requires a typo or a category"),
+        },
+        Some(Commands::Trace { command }) => {
+            cmd::trace_correction(command, cli.threshold)?;
+        }
+        Some(Commands::Import {
+            path,
+            format,
+            category,
+        }) => {
+            cmd::import_corrections(path, *format, category.as_deref())?;
+        }
+        Some(Commands::Corrections { category }) => {
+            cmd::show_learned_corrections(category.as_deref())?;
+        }
+        Some(Commands::AiHistory { limit }) => {
+            cmd::show_ai_history(*limit)?;
+        }
+        Some(Commands::EnableAiLog) => {
+            cmd::enable_ai_log()?;
+            println!("AI interaction logging is now enabled! 🐺");
+        }
+        Some(Commands::DisableAiLog) => {
+            cmd::disable_ai_log()?;
+            println!("AI interaction logging is now disabled! 🐺");
+        }
+        Some(Commands::Config { key, value }) => {
+            cmd::configure(key.as_deref(), value.as_deref())?;
+        }
+        Some(Commands::Similarity { a, b }) => {
+            cmd::show_similarity(a, b)?;
+        }
+        Some(Commands::FromError { text }) => {
+            cmd::correct_from_error(text.as_deref(), cli.threshold)?;
+        }
+        Some(Commands::Bench { iterations }) => {
+            cmd::benchmark_corrections(*iterations)?;
+        }
+        Some(Commands::Daemon { stop }) => {
+            if *stop {
+                if daemon::stop_daemon()? {
+                    println!("Daemon stopped! 🐺");
+                } else {
+                    println!("No running daemon found! 🐺");
+                }
+            } else {
+                println!("Starting daemon on {} 🐺", daemon::socket_path().display());
+                daemon::run_daemon()?;
+            }
+        }
         Some(Commands::Prompt { prompt, codestral, standard_model, code_model }) => {
             // Create a command-specific model config that overrides the global one
             let cmd_model_config = ModelConfig::new(standard_model.clone(), code_model.clone());
-            run_tui_mode(prompt, *codestral, cmd_model_config).await?;
+            launch_tui(prompt, *codestral, cmd_model_config, offline).await?;
         }
         None => {
             // Show help
@@ -144,3 +252,40 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Launch the AI prompt TUI unless offline mode disables AI features
+async fn launch_tui(
+    prompt: &str,
+    use_codestral: bool,
+    model_config: ModelConfig,
+    offline: bool,
+) -> Result<()> {
+    if offline {
+        println!("{OFFLINE_MESSAGE}");
+        return Ok(());
+    }
+
+    // The AI log and input highlighting are both opt-in settings stored in the cache, which
+    // is only loaded in full when highlighting needs its commands
+    let settings = CommandCache::load_settings();
+    let ai_log = settings
+        .ai_log_enabled
+        .then(AiHistoryLog::open_default)
+        .flatten();
+    let idle_timeout = Duration::from_secs(settings.tui_idle_timeout_secs);
+    let command_cache = settings
+        .input_highlighting
+        .then(|| CommandCache::load().ok())
+        .flatten();
+
+    run_tui_mode(
+        prompt,
+        use_codestral,
+        model_config,
+        ai_log,
+        command_cache,
+        settings.fold_tags,
+        idle_timeout,
+    )
+    .await
+}
@@ -6,14 +6,118 @@ use ollama_rs::{
     generation::completion::request::GenerationRequest,
     Ollama,
 };
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
 
 /// Default model for standard queries
 pub const DEFAULT_MODEL: &str = "cognitivecomputations_Dolphin3.0-R1-Mistral-24B-Q5_K_M:latest";
 /// Default code model for code-focused queries
 pub const DEFAULT_CODE_MODEL: &str = "codestral:latest";
 
+/// Environment variable that disables all AI/network features when set to `1`
+pub const OFFLINE_ENV_VAR: &str = "SUPER_SNOOFER_OFFLINE";
+
+/// Message shown when an AI feature is requested in offline mode
+pub const OFFLINE_MESSAGE: &str = "AI features disabled in offline mode 🐺";
+
+/// Check whether offline mode has been enabled through the environment
+#[must_use]
+pub fn is_offline_env() -> bool {
+    std::env::var(OFFLINE_ENV_VAR).is_ok_and(|value| value == "1")
+}
+
+/// File name of the AI interaction log inside the config directory
+pub const AI_HISTORY_FILE: &str = "ai_history.jsonl";
+
+/// A single logged AI prompt and its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiInteraction {
+    pub timestamp: SystemTime,
+    pub model: String,
+    pub prompt: String,
+    pub response: String,
+}
+
+/// Append-only JSONL log of AI interactions
+///
+/// This is separate from command-correction history and only written when the
+/// user has opted in.
+#[derive(Debug, Clone)]
+pub struct AiHistoryLog {
+    path: PathBuf,
+}
+
+impl AiHistoryLog {
+    /// Create a log backed by the given file
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Create a log at the default location (`~/.config/super_snoofer/ai_history.jsonl`)
+    #[must_use]
+    pub fn open_default() -> Option<Self> {
+        let home_dir = dirs::home_dir()?;
+        Some(Self::new(
+            home_dir
+                .join(".config")
+                .join("super_snoofer")
+                .join(AI_HISTORY_FILE),
+        ))
+    }
+
+    /// Get the path of the log file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an interaction to the log
+    ///
+    /// # Errors
+    /// Returns an error if the log directory or file cannot be written
+    pub fn append(&self, interaction: &AiInteraction) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(interaction)?)?;
+        Ok(())
+    }
+
+    /// Read the most recent interactions, newest first
+    ///
+    /// Lines that cannot be parsed are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the log file exists but cannot be read
+    pub fn recent(&self, limit: usize) -> Result<Vec<AiInteraction>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        Ok(content
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .take(limit)
+            .collect())
+    }
+}
+
 /// Configuration for Ollama models
 #[derive(Debug, Clone)]
 pub struct ModelConfig {
@@ -105,11 +209,65 @@ impl OllamaClient {
         Ok(response.response)
     }
     
-    /// Stream a response using Ollama's API by implementing our own streaming solution
-    /// 
+    /// Stream a response using Ollama, calling `on_chunk` with each piece of text as it arrives
+    ///
+    /// This is the simplest way to stream from library code: no channel or runtime plumbing
+    /// beyond awaiting the returned future is needed.
+    ///
+    /// # Errors
+    /// Returns an error if streaming fails due to Ollama API issues or network problems
+    pub async fn generate_with_callback(
+        &self,
+        prompt: &str,
+        use_code_model: bool,
+        mut on_chunk: impl FnMut(&str),
+    ) -> Result<()> {
+        let never_cancelled = CancellationToken::new();
+        self.for_each_chunk(prompt, use_code_model, &never_cancelled, |chunk| {
+            on_chunk(&chunk);
+            std::future::ready(())
+        })
+        .await
+    }
+
+    /// Stream a response using Ollama, sending each piece of text over `tx`
+    ///
+    /// Cancelling `cancel` aborts the HTTP request: the response stream is dropped,
+    /// closing the connection, and `Ok(())` is returned without waiting for more text.
+    ///
     /// # Errors
     /// Returns an error if streaming fails due to Ollama API issues or network problems
-    pub async fn stream_response(&self, prompt: &str, use_code_model: bool, tx: mpsc::Sender<String>) -> Result<()> {
+    pub async fn stream_response(
+        &self,
+        prompt: &str,
+        use_code_model: bool,
+        tx: mpsc::Sender<String>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        self.for_each_chunk(prompt, use_code_model, &cancel, |chunk| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(chunk).await;
+            }
+        })
+        .await
+    }
+
+    /// Stream a response using Ollama's API, awaiting `handle_chunk` for each piece of text
+    ///
+    /// Awaiting the handler lets channel senders apply backpressure instead of dropping text.
+    /// Stops as soon as `cancel` is cancelled, dropping the request or response stream.
+    async fn for_each_chunk<F, Fut>(
+        &self,
+        prompt: &str,
+        use_code_model: bool,
+        cancel: &CancellationToken,
+        mut handle_chunk: F,
+    ) -> Result<()>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = ()>,
+    {
         let model = self.model_config.get_model(use_code_model);
         
         // We won't use the official client's request type directly
@@ -134,11 +292,14 @@ impl OllamaClient {
         let serialized = serde_json::to_string(&json_payload)?;
         
         // Send request
-        let res = client.post(url)
+        let request = client.post(url)
             .header("Content-Type", "application/json")
             .body(serialized)
-            .send()
-            .await?;
+            .send();
+        let res = tokio::select! {
+            () = cancel.cancelled() => return Ok(()),
+            res = request => res?,
+        };
             
         if !res.status().is_success() {
             let error_text = res.text().await?;
@@ -149,7 +310,16 @@ impl OllamaClient {
         let mut stream = res.bytes_stream();
         let mut buffer = String::new();
         
-        while let Some(chunk_result) = stream.next().await {
+        loop {
+            // Returning drops the stream, which aborts the request mid-response
+            let chunk_result = tokio::select! {
+                biased;
+                () = cancel.cancelled() => return Ok(()),
+                next = stream.next() => next,
+            };
+            let Some(chunk_result) = chunk_result else {
+                break;
+            };
             let chunk = chunk_result?;
             let chunk_str = String::from_utf8_lossy(&chunk);
             buffer.push_str(&chunk_str);
@@ -160,39 +330,134 @@ impl OllamaClient {
                 let remainder = buffer[pos + 1..].to_string();
                 buffer = remainder;
                 
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
-                
-                // Parse the JSON response
-                if let Ok(response) = serde_json::from_str::<serde_json::Value>(line) {
-                    if let Some(text) = response.get("response").and_then(|v| v.as_str()) {
-                        if !text.is_empty() {
-                            let _ = tx.send(text.to_string()).await;
-                        }
-                    }
+                if let Some(text) = parse_stream_line(&line) {
+                    handle_chunk(text).await;
                 }
             }
         }
         
         // Process any remaining data in the buffer
-        if !buffer.is_empty() {
-            if let Ok(response) = serde_json::from_str::<serde_json::Value>(&buffer) {
-                if let Some(text) = response.get("response").and_then(|v| v.as_str()) {
-                    if !text.is_empty() {
-                        let _ = tx.send(text.to_string()).await;
-                    }
-                }
-            }
+        if let Some(text) = parse_stream_line(&buffer) {
+            handle_chunk(text).await;
         }
         
         Ok(())
     }
 }
 
+/// Extract the response text from one line of Ollama's streamed JSON, if it has any
+fn parse_stream_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()?
+        .get("response")
+        .and_then(|v| v.as_str())
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
 impl Default for OllamaClient {
     fn default() -> Self {
         Self::new()
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve one request on a local port with `body` as Ollama's streamed reply,
+    /// returning the port and the request the server received
+    ///
+    /// The reply's `Content-Length` counts `held_back` more bytes than are sent, keeping
+    /// the client waiting for the rest. The server finishes once the client closes the
+    /// connection.
+    async fn mock_ollama(
+        body: &'static str,
+        held_back: usize,
+    ) -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"}") {
+                let read = socket.read(&mut buf).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..read]);
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len() + held_back
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            // Wait for the client to hang up, by EOF or a reset
+            while let Ok(read) = socket.read(&mut buf).await {
+                if read == 0 {
+                    break;
+                }
+            }
+            String::from_utf8(request).unwrap()
+        });
+        (port, server)
+    }
+
+    #[tokio::test]
+    async fn callback_receives_each_streamed_chunk() {
+        let (port, server) = mock_ollama(
+            "{\"response\":\"Awoo\",\"done\":false}\n\
+             {\"response\":\"\",\"done\":false}\n\
+             {\"response\":\", world\",\"done\":true}",
+            0,
+        )
+        .await;
+        let client = OllamaClient {
+            client: Arc::new(Mutex::new(Ollama::new("http://127.0.0.1", port))),
+            model_config: ModelConfig::default(),
+        };
+
+        let mut chunks = Vec::new();
+        client
+            .generate_with_callback("hi", false, |chunk| chunks.push(chunk.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(chunks, ["Awoo", ", world"]);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api/generate"));
+        assert!(request.contains("\"stream\":true"));
+    }
+
+    #[tokio::test]
+    async fn cancelling_drops_the_http_stream() {
+        let (port, server) = mock_ollama("{\"response\":\"Awoo\",\"done\":false}\n", 1024).await;
+        let client = OllamaClient {
+            client: Arc::new(Mutex::new(Ollama::new("http://127.0.0.1", port))),
+            model_config: ModelConfig::default(),
+        };
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let cancel = CancellationToken::new();
+        let streaming = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { client.stream_response("hi", false, tx, cancel).await }
+        });
+        assert_eq!(rx.recv().await.as_deref(), Some("Awoo"));
+
+        cancel.cancel();
+        streaming.await.unwrap().unwrap();
+        // The rest of the body never comes, so only an aborted request closes the connection
+        let request = tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("the request was not aborted")
+            .unwrap();
+        assert!(request.starts_with("POST /api/generate"));
+    }
+}
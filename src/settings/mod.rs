@@ -0,0 +1,800 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::utils::SimilarityAlgorithm;
+use anyhow::Result;
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// User-configurable behavior persisted alongside the command cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)] // Independent opt-in toggles, not a state machine
+pub struct Settings {
+    /// Whether AI prompts and responses are logged to disk (opt-in)
+    pub ai_log_enabled: bool,
+
+    /// Algorithm used to score fuzzy command matches
+    pub similarity_algorithm: SimilarityAlgorithm,
+
+    /// Algorithm used to match whole command lines against learned full-line corrections
+    pub line_similarity_algorithm: SimilarityAlgorithm,
+
+    /// Whether `apropos` is consulted for commands that are not installed (opt-in)
+    pub apropos_fallback: bool,
+
+    /// Whether unknown commands are matched against popular packages with an install hint (opt-in)
+    pub package_suggestions: bool,
+
+    /// Order in which aliases, commands and learned corrections are tried
+    pub match_precedence: MatchPrecedence,
+
+    /// Whether the TUI input highlights the first word as a known or misspelled command (opt-in)
+    pub input_highlighting: bool,
+
+    /// Commands shorter than this many characters are only corrected exactly, never fuzzily
+    pub min_correction_length: usize,
+
+    /// Whether completions append the flags the user habitually passes to a command (opt-in)
+    pub append_frequent_flags: bool,
+
+    /// How much the mascot narrates around suggestions and history output
+    pub verbosity: Verbosity,
+
+    /// Whether running a deprecated command prints a note about its modern replacement (opt-in)
+    pub deprecation_notes: bool,
+
+    /// Deprecated commands mapped to their modern replacements
+    pub deprecations: HashMap<String, String>,
+
+    /// Whether running a learned correction prints how to unlearn it
+    pub unlearn_hints: bool,
+
+    /// Maximum number of candidate corrections offered for a single command
+    pub max_suggestions: usize,
+
+    /// `<tag>...</tag>` sections of AI responses the TUI can collapse, besides code blocks
+    pub fold_tags: Vec<String>,
+
+    /// Seconds without input or streaming after which the AI prompt exits (0 to never exit)
+    pub tui_idle_timeout_secs: u64,
+
+    /// Whether a bare subcommand like `commit` is corrected to `git commit` (opt-in)
+    pub bare_subcommands: bool,
+
+    /// Parent commands whose distinctive subcommands imply them when typed bare
+    pub bare_subcommand_parents: Vec<String>,
+
+    /// Whether branch names in `git checkout`/`switch`/`branch` are corrected against the local branches (opt-in)
+    pub git_branch_correction: bool,
+
+    /// Whether only learned corrections run without asking, while fuzzy matches are just printed (opt-in)
+    pub auto_run_learned_only: bool,
+
+    /// Whether a shell keyword typed where a command belongs (`fi foo.txt`) is corrected to a command (opt-in)
+    pub keyword_correction: bool,
+
+    /// Whether a known command glued to its first argument (`cd..`, `ls-la`) is split in two
+    pub glued_argument_splitting: bool,
+
+    /// Whether misspelled directories in `~/` path arguments are corrected against the real ones (opt-in)
+    pub path_correction: bool,
+
+    /// Whether a misspelled `cd` target is corrected against the directories in the current directory and `$CDPATH` (opt-in)
+    pub cd_correction: bool,
+
+    /// Whether arguments naming existing paths are kept as typed, with only case typos in their file names corrected (opt-in)
+    pub argument_case_correction: bool,
+
+    /// Whether tools without a built-in pattern are run with `--help` to discover their subcommands (opt-in)
+    pub help_discovery: bool,
+
+    /// Whether the names that symlinks in the PATH resolve to are commands too, e.g. `busybox` for its applets
+    pub symlink_target_commands: bool,
+
+    /// Most patterns kept for commands without a built-in pattern, least recently used dropped first (0 for no limit)
+    pub max_learned_patterns: usize,
+
+    /// Whether misspelled English words among the arguments are corrected against a built-in word list (opt-in)
+    pub dictionary_correction: bool,
+
+    /// Whether commands documented by installed man pages are offered as corrections too (opt-in, slow to scan)
+    pub man_page_commands: bool,
+
+    /// Format the command cache is stored in; switching migrates the file on next save
+    pub cache_format: CacheFormat,
+
+    /// Rules rewriting a corrected command line before it runs, applied in order
+    pub rewrite_rules: Vec<RewriteRule>,
+
+    /// Seconds a declined suggestion is held back for the same typo (0 never holds it back)
+    pub decline_cooldown_secs: u64,
+
+    /// Whether consecutive identical corrections collapse into one history entry with a count
+    pub collapse_repeated_history: bool,
+
+    /// Days after which a recorded correction counts half as much when ranking by frequency (0 ranks by plain counts)
+    pub frequency_half_life_days: u64,
+
+    /// Command signatures (e.g. `git push --force`) that always ask before a correction runs
+    pub dangerous_commands: Vec<String>,
+}
+
+/// A regex rewrite applied to corrected command lines before they run
+///
+/// The replacement may refer to capture groups, e.g. `^ls\b(.*)` -> `exa$1`. The
+/// pattern is compiled once, when the rule is loaded or set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SavedRewriteRule", into = "SavedRewriteRule")]
+pub struct RewriteRule {
+    pub pattern: String,
+    pub replacement: String,
+    /// The compiled pattern, `None` if it doesn't compile
+    regex: Option<Regex>,
+}
+
+/// A rewrite rule as it's saved, without its compiled pattern
+#[derive(Serialize, Deserialize)]
+struct SavedRewriteRule {
+    pattern: String,
+    replacement: String,
+}
+
+impl From<SavedRewriteRule> for RewriteRule {
+    fn from(saved: SavedRewriteRule) -> Self {
+        Self {
+            regex: Regex::new(&saved.pattern).ok(),
+            pattern: saved.pattern,
+            replacement: saved.replacement,
+        }
+    }
+}
+
+impl From<RewriteRule> for SavedRewriteRule {
+    fn from(rule: RewriteRule) -> Self {
+        Self {
+            pattern: rule.pattern,
+            replacement: rule.replacement,
+        }
+    }
+}
+
+/// Common deprecated commands and their modern replacements
+pub const DEFAULT_DEPRECATIONS: &[(&str, &str)] = &[
+    ("ifconfig", "ip addr"),
+    ("netstat", "ss"),
+    ("route", "ip route"),
+    ("arp", "ip neigh"),
+    ("iwconfig", "iw"),
+    ("brctl", "ip link"),
+    ("nslookup", "dig"),
+    ("egrep", "grep -E"),
+    ("fgrep", "grep -F"),
+    ("apt-get", "apt"),
+    ("service", "systemctl"),
+    ("docker-compose", "docker compose"),
+];
+
+/// Command signatures that always ask for confirmation before a correction runs
+///
+/// A signature's words must lead the command line and its flags appear anywhere in it.
+pub const DEFAULT_DANGEROUS_COMMANDS: &[&str] = &[
+    "git push --force",
+    "git push -f",
+    "git reset --hard",
+    "git clean -f",
+    "git clean -fd",
+    "git clean -fdx",
+    "git branch -D",
+];
+
+/// Check whether a flag is among a command's arguments
+///
+/// A short flag like `-f`, or a cluster like `-fd`, also matches when each of its letters
+/// is in some short flag cluster of the arguments, e.g. `-f` in `-fu`. Arguments after
+/// `--` aren't flags.
+fn has_flag(arguments: &[&str], flag: &str) -> bool {
+    let flags = arguments
+        .iter()
+        .take_while(|argument| **argument != "--");
+    if flags.clone().any(|argument| *argument == flag) {
+        return true;
+    }
+    let Some(letters) = flag.strip_prefix('-').filter(|letters| !letters.starts_with('-')) else {
+        return false;
+    };
+    let clustered: String = flags
+        .filter_map(|argument| argument.strip_prefix('-'))
+        .filter(|letters| !letters.starts_with('-'))
+        .collect();
+    !letters.is_empty() && letters.chars().all(|letter| clustered.contains(letter))
+}
+
+/// Tags of AI response sections that can be collapsed: reasoning and tool-call traces
+pub const DEFAULT_FOLD_TAGS: &[&str] = &["think", "tool_call"];
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ai_log_enabled: false,
+            similarity_algorithm: SimilarityAlgorithm::default(),
+            line_similarity_algorithm: SimilarityAlgorithm::Trigram,
+            apropos_fallback: false,
+            package_suggestions: false,
+            match_precedence: MatchPrecedence::default(),
+            input_highlighting: false,
+            min_correction_length: 2,
+            append_frequent_flags: false,
+            verbosity: Verbosity::default(),
+            deprecation_notes: false,
+            deprecations: DEFAULT_DEPRECATIONS
+                .iter()
+                .map(|(old, new)| ((*old).to_string(), (*new).to_string()))
+                .collect(),
+            unlearn_hints: true,
+            max_suggestions: 3,
+            fold_tags: DEFAULT_FOLD_TAGS.iter().map(ToString::to_string).collect(),
+            tui_idle_timeout_secs: 0,
+            bare_subcommands: false,
+            bare_subcommand_parents: vec!["git".to_string(), "cargo".to_string()],
+            git_branch_correction: false,
+            auto_run_learned_only: false,
+            keyword_correction: false,
+            glued_argument_splitting: true,
+            path_correction: false,
+            cd_correction: false,
+            argument_case_correction: false,
+            help_discovery: false,
+            symlink_target_commands: true,
+            max_learned_patterns: 1000,
+            dictionary_correction: false,
+            man_page_commands: false,
+            cache_format: CacheFormat::default(),
+            rewrite_rules: Vec::new(),
+            decline_cooldown_secs: 0,
+            collapse_repeated_history: true,
+            frequency_half_life_days: 0,
+            dangerous_commands: DEFAULT_DANGEROUS_COMMANDS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl Settings {
+    /// Update a setting from its name and a string value
+    ///
+    /// Deprecations are set per command with `deprecations.<command>` and rewrite rules
+    /// per pattern with `rewrite_rules.<pattern>`; an empty value removes the entry.
+    ///
+    /// # Errors
+    /// Returns an error if the setting is unknown, the value cannot be parsed or a
+    /// rewrite rule's pattern is not a valid regex
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        if let Some(command) = key.strip_prefix("deprecations.") {
+            if value.is_empty() {
+                self.deprecations.remove(command);
+            } else {
+                self.deprecations.insert(command.to_string(), value.to_string());
+            }
+            return Ok(());
+        }
+
+        if let Some(pattern) = key.strip_prefix("rewrite_rules.") {
+            self.set_rewrite_rule(pattern, value)?;
+            return Ok(());
+        }
+
+        match key {
+            "ai_log_enabled" => self.ai_log_enabled = parse_bool(value)?,
+            "similarity_algorithm" => self.similarity_algorithm = value.parse()?,
+            "line_similarity_algorithm" => self.line_similarity_algorithm = value.parse()?,
+            "apropos_fallback" => self.apropos_fallback = parse_bool(value)?,
+            "package_suggestions" => self.package_suggestions = parse_bool(value)?,
+            "match_precedence" => self.match_precedence = value.parse()?,
+            "input_highlighting" => self.input_highlighting = parse_bool(value)?,
+            "append_frequent_flags" => self.append_frequent_flags = parse_bool(value)?,
+            "verbosity" => self.verbosity = value.parse()?,
+            "deprecation_notes" => self.deprecation_notes = parse_bool(value)?,
+            "unlearn_hints" => self.unlearn_hints = parse_bool(value)?,
+            "min_correction_length" => {
+                self.min_correction_length = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Expected a whole number, got '{value}'"))?;
+            }
+            "tui_idle_timeout_secs" => {
+                self.tui_idle_timeout_secs = value.parse().map_err(|_| {
+                    anyhow::anyhow!("Expected a whole number of seconds, got '{value}'")
+                })?;
+            }
+            "fold_tags" => {
+                self.fold_tags = value
+                    .split(',')
+                    .map(|tag| tag.trim().trim_start_matches('<').trim_end_matches('>'))
+                    .filter(|tag| !tag.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+            }
+            "bare_subcommands" => self.bare_subcommands = parse_bool(value)?,
+            "git_branch_correction" => self.git_branch_correction = parse_bool(value)?,
+            "auto_run_learned_only" => self.auto_run_learned_only = parse_bool(value)?,
+            "keyword_correction" => self.keyword_correction = parse_bool(value)?,
+            "glued_argument_splitting" => self.glued_argument_splitting = parse_bool(value)?,
+            "path_correction" => self.path_correction = parse_bool(value)?,
+            "cd_correction" => self.cd_correction = parse_bool(value)?,
+            "argument_case_correction" => self.argument_case_correction = parse_bool(value)?,
+            "help_discovery" => self.help_discovery = parse_bool(value)?,
+            "symlink_target_commands" => self.symlink_target_commands = parse_bool(value)?,
+            "max_learned_patterns" => {
+                self.max_learned_patterns = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Expected a whole number, got '{value}'"))?;
+            }
+            "dictionary_correction" => self.dictionary_correction = parse_bool(value)?,
+            "man_page_commands" => self.man_page_commands = parse_bool(value)?,
+            "cache_format" => self.cache_format = value.parse()?,
+            "dangerous_commands" => {
+                self.dangerous_commands = value
+                    .split(',')
+                    .map(|signature| signature.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .filter(|signature| !signature.is_empty())
+                    .collect();
+            }
+            "bare_subcommand_parents" => {
+                self.bare_subcommand_parents = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|parent| !parent.is_empty())
+                    .map(ToString::to_string)
+                    .collect();
+            }
+            "decline_cooldown_secs" => {
+                self.decline_cooldown_secs = value.parse().map_err(|_| {
+                    anyhow::anyhow!("Expected a whole number of seconds, got '{value}'")
+                })?;
+            }
+            "collapse_repeated_history" => self.collapse_repeated_history = parse_bool(value)?,
+            "frequency_half_life_days" => {
+                self.frequency_half_life_days = value.parse().map_err(|_| {
+                    anyhow::anyhow!("Expected a whole number of days, got '{value}'")
+                })?;
+            }
+            "max_suggestions" => {
+                self.max_suggestions =
+                    value.parse().ok().filter(|max| *max > 0).ok_or_else(|| {
+                        anyhow::anyhow!("Expected a positive whole number, got '{value}'")
+                    })?;
+            }
+            _ => return Err(anyhow::anyhow!("Unknown setting: {key}")),
+        }
+        Ok(())
+    }
+
+    /// List all settings with their current values
+    #[must_use]
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("ai_log_enabled", self.ai_log_enabled.to_string()),
+            ("similarity_algorithm", self.similarity_algorithm.to_string()),
+            (
+                "line_similarity_algorithm",
+                self.line_similarity_algorithm.to_string(),
+            ),
+            ("apropos_fallback", self.apropos_fallback.to_string()),
+            ("package_suggestions", self.package_suggestions.to_string()),
+            ("match_precedence", self.match_precedence.to_string()),
+            ("input_highlighting", self.input_highlighting.to_string()),
+            ("min_correction_length", self.min_correction_length.to_string()),
+            ("append_frequent_flags", self.append_frequent_flags.to_string()),
+            ("verbosity", self.verbosity.to_string()),
+            ("deprecation_notes", self.deprecation_notes.to_string()),
+            ("deprecations", self.deprecations_summary()),
+            ("unlearn_hints", self.unlearn_hints.to_string()),
+            ("max_suggestions", self.max_suggestions.to_string()),
+            ("fold_tags", self.fold_tags.join(",")),
+            (
+                "tui_idle_timeout_secs",
+                self.tui_idle_timeout_secs.to_string(),
+            ),
+            ("bare_subcommands", self.bare_subcommands.to_string()),
+            (
+                "bare_subcommand_parents",
+                self.bare_subcommand_parents.join(","),
+            ),
+            (
+                "git_branch_correction",
+                self.git_branch_correction.to_string(),
+            ),
+            (
+                "auto_run_learned_only",
+                self.auto_run_learned_only.to_string(),
+            ),
+            ("keyword_correction", self.keyword_correction.to_string()),
+            (
+                "glued_argument_splitting",
+                self.glued_argument_splitting.to_string(),
+            ),
+            ("path_correction", self.path_correction.to_string()),
+            ("cd_correction", self.cd_correction.to_string()),
+            (
+                "argument_case_correction",
+                self.argument_case_correction.to_string(),
+            ),
+            ("help_discovery", self.help_discovery.to_string()),
+            (
+                "symlink_target_commands",
+                self.symlink_target_commands.to_string(),
+            ),
+            (
+                "max_learned_patterns",
+                self.max_learned_patterns.to_string(),
+            ),
+            (
+                "dictionary_correction",
+                self.dictionary_correction.to_string(),
+            ),
+            ("man_page_commands", self.man_page_commands.to_string()),
+            ("cache_format", self.cache_format.to_string()),
+            ("rewrite_rules", self.rewrite_rules_summary()),
+            (
+                "decline_cooldown_secs",
+                self.decline_cooldown_secs.to_string(),
+            ),
+            (
+                "collapse_repeated_history",
+                self.collapse_repeated_history.to_string(),
+            ),
+            (
+                "frequency_half_life_days",
+                self.frequency_half_life_days.to_string(),
+            ),
+            ("dangerous_commands", self.dangerous_commands.join(",")),
+        ]
+    }
+
+    /// Add, replace or (with an empty replacement) remove the rewrite rule for a pattern
+    ///
+    /// A replaced rule keeps its position, a new one is applied after the existing ones.
+    fn set_rewrite_rule(&mut self, pattern: &str, replacement: &str) -> Result<()> {
+        if replacement.is_empty() {
+            self.rewrite_rules.retain(|rule| rule.pattern != pattern);
+            return Ok(());
+        }
+
+        let regex = Regex::new(pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid rewrite pattern '{pattern}': {e}"))?;
+        match self
+            .rewrite_rules
+            .iter_mut()
+            .find(|rule| rule.pattern == pattern)
+        {
+            Some(rule) => rule.replacement = replacement.to_string(),
+            None => self.rewrite_rules.push(RewriteRule {
+                pattern: pattern.to_string(),
+                replacement: replacement.to_string(),
+                regex: Some(regex),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Apply the rewrite rules, in order, to a corrected command line
+    ///
+    /// Each rule replaces every match of its pattern; a line no rule matches is returned
+    /// unchanged. Rules whose pattern no longer compiles are skipped.
+    #[must_use]
+    pub fn apply_rewrite_rules(&self, line: &str) -> String {
+        self.rewrite_rules
+            .iter()
+            .fold(line.to_string(), |line, rule| {
+                let Some(regex) = &rule.regex else {
+                    return line;
+                };
+                match regex.try_replacen(&line, 0, rule.replacement.as_str()) {
+                    Ok(rewritten) => rewritten.into_owned(),
+                    Err(_) => line,
+                }
+            })
+    }
+
+    /// Get the dangerous command signature a command line matches, if any
+    ///
+    /// The signature's leading words must lead the line and each of its flags must
+    /// appear among the line's arguments, e.g. `git push --force` matches `git push
+    /// origin main --force` but not `git push --force-with-lease`. Wrappers like `sudo`
+    /// and `NAME=value` assignments before the command are skipped, and a short flag
+    /// may be part of a cluster, so `git push -f` matches `sudo git push -fu origin`.
+    #[must_use]
+    pub fn dangerous_signature(&self, line: &str) -> Option<&str> {
+        let tokens: Vec<&str> = crate::command::strip_wrappers(line)
+            .split_whitespace()
+            .collect();
+        self.dangerous_commands
+            .iter()
+            .find(|signature| {
+                let (flags, words): (Vec<&str>, Vec<&str>) = signature
+                    .split_whitespace()
+                    .partition(|token| token.starts_with('-'));
+                !words.is_empty()
+                    && tokens.starts_with(&words)
+                    && flags
+                        .iter()
+                        .all(|flag| has_flag(&tokens[words.len()..], flag))
+            })
+            .map(String::as_str)
+    }
+
+    /// Summarize the rewrite rules as an ordered `pattern→replacement` list
+    fn rewrite_rules_summary(&self) -> String {
+        self.rewrite_rules
+            .iter()
+            .map(|rule| format!("{}→{}", rule.pattern, rule.replacement))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Get the modern replacement for a deprecated command, if notes are enabled
+    #[must_use]
+    pub fn deprecation_for(&self, command: &str) -> Option<&str> {
+        if !self.deprecation_notes {
+            return None;
+        }
+        self.deprecations.get(command).map(String::as_str)
+    }
+
+    /// Summarize the deprecations as a sorted `old→new` list
+    fn deprecations_summary(&self) -> String {
+        let mut pairs: Vec<String> = self
+            .deprecations
+            .iter()
+            .map(|(old, new)| format!("{old}→{new}"))
+            .collect();
+        pairs.sort();
+        pairs.join(", ")
+    }
+}
+
+/// How much friendly narration accompanies suggestions and history output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verbosity {
+    /// A single actionable line per suggestion
+    Terse,
+    /// The usual friendly output
+    #[default]
+    Normal,
+    /// Extra tips alongside the usual output
+    Chatty,
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Terse => "terse",
+            Self::Normal => "normal",
+            Self::Chatty => "chatty",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Verbosity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "terse" => Ok(Self::Terse),
+            "normal" => Ok(Self::Normal),
+            "chatty" => Ok(Self::Chatty),
+            _ => Err(anyhow::anyhow!(
+                "Unknown verbosity '{s}' (expected terse, normal or chatty)"
+            )),
+        }
+    }
+}
+
+/// On-disk format of the command cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CacheFormat {
+    /// Human-readable JSON, easy to inspect and edit
+    #[default]
+    Json,
+    /// Compact CBOR, smaller and faster to parse for large histories
+    Binary,
+}
+
+impl CacheFormat {
+    /// Get the file extension used for caches in this format
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Binary => "cbor",
+        }
+    }
+
+    /// Get the format of a cache file from its extension, defaulting to JSON
+    #[must_use]
+    pub fn from_path(path: &std::path::Path) -> Self {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == Self::Binary.extension())
+        {
+            Self::Binary
+        } else {
+            Self::Json
+        }
+    }
+}
+
+impl fmt::Display for CacheFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Json => "json",
+            Self::Binary => "binary",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for CacheFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "binary" | "cbor" => Ok(Self::Binary),
+            _ => Err(anyhow::anyhow!(
+                "Unknown cache format '{s}' (expected json or binary)"
+            )),
+        }
+    }
+}
+
+/// A source of corrections consulted when resolving a typed command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchSource {
+    /// The typed name is a shell alias
+    ExactAlias,
+    /// The typed name is a command in PATH
+    ExactCommand,
+    /// The typed name has a learned correction
+    Learned,
+    /// The typed name is close to a shell alias
+    FuzzyAlias,
+    /// The typed name is close to a command in PATH
+    FuzzyCommand,
+}
+
+impl MatchSource {
+    /// All sources in the default precedence order
+    pub const ALL: [Self; 5] = [
+        Self::ExactAlias,
+        Self::ExactCommand,
+        Self::Learned,
+        Self::FuzzyAlias,
+        Self::FuzzyCommand,
+    ];
+}
+
+impl fmt::Display for MatchSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::ExactAlias => "exact-alias",
+            Self::ExactCommand => "exact-command",
+            Self::Learned => "learned",
+            Self::FuzzyAlias => "fuzzy-alias",
+            Self::FuzzyCommand => "fuzzy-command",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for MatchSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|source| source.to_string() == s.trim().to_lowercase())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown match source '{s}' (expected exact-alias, exact-command, learned, fuzzy-alias or fuzzy-command)"
+                )
+            })
+    }
+}
+
+/// Order in which correction sources are consulted; the first source with a match wins
+///
+/// The default is exact alias > exact command > learned correction > fuzzy alias >
+/// fuzzy command. Sources left out of a custom ordering are never consulted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchPrecedence(pub Vec<MatchSource>);
+
+impl Default for MatchPrecedence {
+    fn default() -> Self {
+        Self(MatchSource::ALL.to_vec())
+    }
+}
+
+impl fmt::Display for MatchPrecedence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl FromStr for MatchPrecedence {
+    type Err = anyhow::Error;
+
+    /// Parse a comma-separated list such as `learned,exact-alias,exact-command`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sources = Vec::new();
+        for name in s.split(',').filter(|name| !name.trim().is_empty()) {
+            let source: MatchSource = name.parse()?;
+            if sources.contains(&source) {
+                return Err(anyhow::anyhow!("Match source '{source}' is listed twice"));
+            }
+            sources.push(source);
+        }
+
+        if sources.is_empty() {
+            return Err(anyhow::anyhow!("Match precedence needs at least one source"));
+        }
+        Ok(Self(sources))
+    }
+}
+
+/// Parse a boolean setting value, accepting common spellings
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(anyhow::anyhow!("Expected a boolean value, got '{value}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dangerous_signatures_see_through_wrappers_and_flag_clusters() {
+        let settings = Settings::default();
+        assert_eq!(
+            settings.dangerous_signature("sudo git push -fu origin main"),
+            Some("git push -f")
+        );
+        assert_eq!(
+            settings.dangerous_signature("GIT_TRACE=1 git clean -xdf"),
+            Some("git clean -f")
+        );
+        assert_eq!(
+            settings.dangerous_signature("git push --force-with-lease"),
+            None
+        );
+        assert_eq!(settings.dangerous_signature("git push -u origin main"), None);
+        assert_eq!(settings.dangerous_signature("git push -- -f"), None);
+    }
+
+    #[test]
+    fn rewrite_rules_rewrite_matching_lines_only() {
+        let mut settings = Settings::default();
+        settings.set(r"rewrite_rules.^ls\b", "exa").unwrap();
+        assert!(settings.set("rewrite_rules.(", "x").is_err());
+        assert_eq!(settings.apply_rewrite_rules("ls -la"), "exa -la");
+        assert_eq!(settings.apply_rewrite_rules("lsblk"), "lsblk");
+        assert_eq!(settings.apply_rewrite_rules("git ls-files"), "git ls-files");
+
+        let saved = serde_json::to_string(&settings).unwrap();
+        let loaded: Settings = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.apply_rewrite_rules("ls"), "exa");
+
+        settings.set(r"rewrite_rules.^ls\b", "").unwrap();
+        assert_eq!(settings.apply_rewrite_rules("ls -la"), "ls -la");
+    }
+}
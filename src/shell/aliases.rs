@@ -38,10 +38,10 @@ pub fn suggest_aliases() -> Result<()> {
     }
 
     for (command, count) in &corrections {
-        let alias = if command.len() <= 3 {
+        let alias = if command.chars().count() <= 3 {
             command.to_string()
         } else {
-            command[0..2].to_string()
+            command.chars().take(2).collect()
         };
 
         println!("\nYou've used '{command}' {count} times! Let's create an alias for that.");
@@ -69,29 +69,71 @@ pub fn suggest_aliases() -> Result<()> {
 /// Returns an error if the shell configuration files cannot be read or if aliases cannot be parsed
 pub fn parse_shell_aliases() -> Result<HashMap<String, String>> {
     let mut aliases = HashMap::new();
-    
-    // Get home directory
+
+    for file_path in alias_config_files()? {
+        if file_path.exists() {
+            if let Ok(()) = parse_aliases_from_file(&file_path, &mut aliases) {
+                // Successfully parsed aliases from this file
+            }
+        }
+    }
+
+    Ok(aliases)
+}
+
+/// Get the shell config files aliases are read from: `.zshrc` and related files
+///
+/// # Errors
+/// Returns an error if the home directory cannot be found
+fn alias_config_files() -> Result<Vec<PathBuf>> {
     let home_dir = dirs::home_dir()
         .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
 
-    // Parse .zshrc and related files
-    let zsh_files = vec![
+    Ok(vec![
         home_dir.join(".zshrc"),
         home_dir.join("toolkit/zsh/core_shell.zsh"),
         home_dir.join("toolkit/zsh/docker.zsh"),
         home_dir.join("toolkit/zsh/git.zsh"),
         home_dir.join("toolkit/zsh/personal.zsh"),
-    ];
+    ])
+}
 
-    for file_path in zsh_files {
-        if file_path.exists() {
-            if let Ok(()) = parse_aliases_from_file(&file_path, &mut aliases) {
-                // Successfully parsed aliases from this file
+/// Remove every definition of an alias from the shell config files
+///
+/// Returns the number of `alias` lines removed.
+///
+/// # Errors
+/// Returns an error if a config file defining the alias cannot be read or written
+pub fn remove_alias(name: &str) -> Result<usize> {
+    let mut removed = 0;
+
+    for file_path in alias_config_files()? {
+        if !file_path.exists() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&file_path)?;
+        let kept: Vec<&str> = content
+            .lines()
+            .filter(|line| {
+                let line = line.trim();
+                !(line.starts_with("alias ")
+                    && parse_alias_line(line).is_some_and(|(alias, _)| alias == name))
+            })
+            .collect();
+
+        let removed_here = content.lines().count() - kept.len();
+        if removed_here > 0 {
+            let mut updated = kept.join("\n");
+            if content.ends_with('\n') {
+                updated.push('\n');
             }
+            fs::write(&file_path, updated)?;
+            removed += removed_here;
         }
     }
 
-    Ok(aliases)
+    Ok(removed)
 }
 
 /// Detect shell config file and generate alias line
@@ -142,7 +184,10 @@ fn parse_aliases_from_file(file_path: &PathBuf, aliases: &mut HashMap<String, St
     Ok(())
 }
 
-fn parse_alias_line(line: &str) -> Option<(String, String)> {
+/// Parse an alias definition into its name and command, e.g. `alias gs='git status'`
+///
+/// The `alias ` prefix is optional, as in the output of zsh's `alias`.
+pub(crate) fn parse_alias_line(line: &str) -> Option<(String, String)> {
     let line = line.trim_start_matches("alias ").trim();
     if let Some((name, command)) = line.split_once('=') {
         let name = name.trim();
@@ -0,0 +1,180 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Rebuilding a command line from the words a shell's command-not-found handler passes
+//!
+//! Every integration script calls `super_snoofer -- <command> <arguments>...` with the
+//! command and each argument as an argument of its own, as the shell split them: zsh and
+//! bash pass `"$cmd" "$@"` and fish passes `$argv`. The shell has already removed its
+//! quotes by then, so `grpe "hello world" notes.txt` arrives as the three words `grpe`,
+//! `hello world` and `notes.txt`. Joining them with spaces would run `grep hello world
+//! notes.txt`; instead each word that needs it is quoted again, so the shell splits the
+//! corrected line back into the same words.
+//!
+//! A single word is taken as a whole command line, for callers that pass the line as
+//! typed, like the `fuck` alias passing `"$(fc -ln -1)"`.
+//!
+//! Going the other way, [`split_words`] finds where each word of such a line is, so
+//! corrections can replace a word in place and leave quoted words and the spacing
+//! between words as typed.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Check whether a character can appear unquoted in a shell word without changing it
+fn is_plain(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '=' | ':' | ',' | '+' | '@' | '%')
+}
+
+/// Quote a word for the shell if it has characters the shell would treat specially
+///
+/// Words are single-quoted, with a single quote in them written as `'\''`, e.g.
+/// `it's here` -> `'it'\''s here'`. An empty word becomes `''`.
+#[must_use]
+pub fn quote_word(word: &str) -> Cow<'_, str> {
+    if !word.is_empty() && word.chars().all(is_plain) {
+        return Cow::Borrowed(word);
+    }
+    Cow::Owned(format!("'{}'", word.replace('\'', r"'\''")))
+}
+
+/// Rebuild the command line a shell passed as separate words
+///
+/// A single word is returned as it is, since it's a whole command line.
+#[must_use]
+pub fn join_words<S: AsRef<str>>(words: &[S]) -> String {
+    match words {
+        [line] => line.as_ref().to_string(),
+        _ => words
+            .iter()
+            .map(|word| quote_word(word.as_ref()))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// A word of a command line, as the shell would split it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word<'a> {
+    /// The word as typed, quotes included
+    pub text: &'a str,
+    /// Where the word is in the line
+    pub range: Range<usize>,
+    /// Whether any part of the word is quoted or escaped
+    pub quoted: bool,
+}
+
+/// Split a command line into its words, keeping quoted whitespace inside its word
+///
+/// e.g. `git commit -m "fix typo"` has the four words `git`, `commit`, `-m` and
+/// `"fix typo"`, the last one quoted. An unterminated quote runs to the end of the line.
+#[must_use]
+pub fn split_words(line: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        if start.is_none() {
+            if c.is_whitespace() {
+                continue;
+            }
+            start = Some(index);
+            quoted = false;
+        }
+        if escaped {
+            escaped = false;
+        } else if let Some(open) = quote {
+            if c == open {
+                quote = None;
+            } else if c == '\\' && open == '"' {
+                escaped = true;
+            }
+        } else if matches!(c, '\'' | '"') {
+            quote = Some(c);
+            quoted = true;
+        } else if c == '\\' {
+            escaped = true;
+            quoted = true;
+        } else if c.is_whitespace()
+            && let Some(word_start) = start.take()
+        {
+            words.push(Word {
+                text: &line[word_start..index],
+                range: word_start..index,
+                quoted,
+            });
+        }
+    }
+    if let Some(word_start) = start {
+        words.push(Word {
+            text: &line[word_start..],
+            range: word_start..line.len(),
+            quoted,
+        });
+    }
+    words
+}
+
+/// Replace words of a command line, leaving the rest of it as typed
+///
+/// `replacements` are the ranges of the replaced words (see [`split_words`]) with their
+/// new text, in any order.
+#[must_use]
+pub fn replace_words(
+    line: &str,
+    replacements: impl IntoIterator<Item = (Range<usize>, String)>,
+) -> String {
+    let mut replacements: Vec<(Range<usize>, String)> = replacements.into_iter().collect();
+    replacements.sort_by_key(|(range, _)| range.start);
+    let mut replaced = String::with_capacity(line.len());
+    let mut end = 0;
+    for (range, text) in replacements {
+        replaced.push_str(&line[end..range.start]);
+        replaced.push_str(&text);
+        end = range.end;
+    }
+    replaced.push_str(&line[end..]);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_words_keeps_quoted_whitespace() {
+        let words = split_words(r#"git commit  -m "fix typo" it\'s"#);
+        let texts: Vec<&str> = words.iter().map(|word| word.text).collect();
+        assert_eq!(texts, ["git", "commit", "-m", r#""fix typo""#, r"it\'s"]);
+        assert_eq!(words[2].range, 12..14);
+        assert!(!words[2].quoted);
+        assert!(words[3].quoted && words[4].quoted);
+    }
+
+    #[test]
+    fn quote_word_quotes_only_special_words() {
+        assert_eq!(quote_word("notes.txt"), "notes.txt");
+        assert_eq!(quote_word("hello world"), "'hello world'");
+        assert_eq!(quote_word("it's here"), r"'it'\''s here'");
+        assert_eq!(quote_word("$HOME"), "'$HOME'");
+        assert_eq!(quote_word(""), "''");
+    }
+
+    #[test]
+    fn join_words_round_trips_through_split_words() {
+        let words = ["grep", "hello world", "it's", "", "notes.txt"];
+        let line = join_words(&words);
+        assert_eq!(line, r"grep 'hello world' 'it'\''s' '' notes.txt");
+        assert_eq!(split_words(&line).len(), words.len());
+        assert_eq!(join_words(&["git  status 'a b'"]), "git  status 'a b'");
+    }
+
+    #[test]
+    fn replace_words_keeps_the_rest_of_the_line() {
+        assert_eq!(
+            replace_words("a  b 'c d'", [(3..4, "x".to_string())]),
+            "a  x 'c d'"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Reconciling a command line as typed with its alias-expanded form
+//!
+//! By the time a mistyped command reaches the command-not-found handler, the shell has
+//! already expanded its aliases: typing `g stauts` with `alias g=gti` hands over
+//! `gti stauts`. Correcting the typed form instead could "fix" the alias itself
+//! (`g` -> `go`), contradicting the shell. So the protocol is:
+//!
+//! 1. The zsh integration's `preexec` hook exports the line as typed in
+//!    [`TYPED_LINE_VAR`] and as expanded in [`EXPANDED_LINE_VAR`] when the two differ,
+//!    and unsets both otherwise.
+//! 2. `super_snoofer` corrects the line it received (the expanded one) as usual, and
+//!    that correction is what runs.
+//! 3. When the received line is the exported expanded line, the correction is shown in
+//!    the typed form, keeping the alias. A correction of the alias's own expansion
+//!    (`git status` above) can't be written with the alias, so it's shown expanded.
+//!
+//! The `fuck` alias corrects the previous line as it's in the shell history, which is
+//! the typed form, so it expands a leading alias from the cache itself (see
+//! [`ExpandedLine::expand`]) and then follows steps 2 and 3.
+
+/// Environment variable the shell integration sets to the command line as typed
+pub const TYPED_LINE_VAR: &str = "SUPER_SNOOFER_TYPED_LINE";
+
+/// Environment variable the shell integration sets to the command line with its aliases
+/// expanded
+pub const EXPANDED_LINE_VAR: &str = "SUPER_SNOOFER_EXPANDED_LINE";
+
+/// A command line whose leading alias the shell expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedLine {
+    /// The alias as typed, e.g. `g`
+    alias: String,
+    /// The words the alias expanded to, e.g. `git`
+    expansion: Vec<String>,
+}
+
+impl ExpandedLine {
+    /// Pair a typed line with its expanded form
+    ///
+    /// Returns `None` unless the typed line's first word expanded into words that the
+    /// rest of both lines follows unchanged, e.g. `g stauts` and `git stauts`.
+    fn new(typed: &str, expanded: &str) -> Option<Self> {
+        let typed: Vec<&str> = typed.split_whitespace().collect();
+        let expanded: Vec<&str> = expanded.split_whitespace().collect();
+        let (alias, tail) = typed.split_first()?;
+        if typed == expanded || expanded.len() <= tail.len() || !expanded.ends_with(tail) {
+            return None;
+        }
+
+        let expansion = &expanded[..expanded.len() - tail.len()];
+        Some(Self {
+            alias: (*alias).to_string(),
+            expansion: expansion.iter().map(|word| (*word).to_string()).collect(),
+        })
+    }
+
+    /// Get the expansion the shell integration exported for the line `super_snoofer`
+    /// received, see [`Self::from_lines`]
+    #[must_use]
+    pub fn from_env(received: &str) -> Option<Self> {
+        let typed = std::env::var(TYPED_LINE_VAR).ok()?;
+        let expanded = std::env::var(EXPANDED_LINE_VAR).ok()?;
+        Self::from_lines(received, &typed, &expanded)
+    }
+
+    /// Pair the typed and expanded lines exported for a received line
+    ///
+    /// Lines left over from another command, e.g. one run inside a function, are
+    /// ignored: the received line has to be the expanded line.
+    #[must_use]
+    pub fn from_lines(received: &str, typed: &str, expanded: &str) -> Option<Self> {
+        if !expanded.split_whitespace().eq(received.split_whitespace()) {
+            return None;
+        }
+        Self::new(typed, expanded)
+    }
+
+    /// Expand the leading alias of a typed line, given the alias's expansion
+    ///
+    /// Returns the pairing and the expanded line, e.g. `g stauts` with `git` gives
+    /// `git stauts`, or `None` when the expansion changes nothing.
+    #[must_use]
+    pub fn expand(typed: &str, expansion: &str) -> Option<(Self, String)> {
+        let typed = typed.trim();
+        let rest = typed
+            .split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest.trim_start());
+        let expanded = if rest.is_empty() {
+            expansion.trim().to_string()
+        } else {
+            format!("{} {rest}", expansion.trim())
+        };
+        Some((Self::new(typed, &expanded)?, expanded))
+    }
+
+    /// Write a correction of the expanded line in the form it was typed
+    ///
+    /// e.g. `git status` -> `g status` for `alias g=git`. A correction that changed the
+    /// expansion itself is returned unchanged.
+    #[must_use]
+    pub fn typed_correction(&self, corrected: &str) -> String {
+        let words: Vec<&str> = corrected.split_whitespace().collect();
+        let expands = words.len() >= self.expansion.len()
+            && words
+                .iter()
+                .zip(&self.expansion)
+                .all(|(word, expanded)| word == expanded);
+        if !expands {
+            return corrected.to_string();
+        }
+
+        std::iter::once(self.alias.as_str())
+            .chain(words[self.expansion.len()..].iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrections_are_shown_with_the_alias() {
+        let (line, expanded) = ExpandedLine::expand("g stauts", "git").unwrap();
+        assert_eq!(expanded, "git stauts");
+        assert_eq!(line.typed_correction("git status"), "g status");
+    }
+
+    #[test]
+    fn multi_word_expansions_are_kept_whole() {
+        let (line, expanded) = ExpandedLine::expand("gl --oneline", "git log").unwrap();
+        assert_eq!(expanded, "git log --oneline");
+        assert_eq!(line.typed_correction("git log --oneline"), "gl --oneline");
+        // A correction of the expansion itself can't be written with the alias
+        assert_eq!(
+            line.typed_correction("git show --oneline"),
+            "git show --oneline"
+        );
+    }
+
+    #[test]
+    fn exported_lines_are_only_used_for_the_line_they_expand() {
+        let line = ExpandedLine::from_lines("gti  stauts", "g stauts", "gti stauts").unwrap();
+        assert_eq!(line.typed_correction("gti status"), "g status");
+        // The correction of the alias's own expansion is shown expanded
+        assert_eq!(line.typed_correction("git status"), "git status");
+
+        assert!(ExpandedLine::from_lines("gti log", "g stauts", "gti stauts").is_none());
+    }
+
+    #[test]
+    fn lines_without_an_expansion_are_not_paired() {
+        assert!(ExpandedLine::new("git status", "git status").is_none());
+        assert!(ExpandedLine::expand("ls", "ls").is_none());
+    }
+}
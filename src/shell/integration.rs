@@ -3,8 +3,16 @@
 use anyhow::Result;
 use std::{fs, io::Write};
 
+use crate::{
+    daemon::{socket_path, NO_CORRECTION_REPLY, REPLY_VAR},
+    shell::arguments::quote_word,
+};
+
 /// Installs shell integration for Super Snoofer
 ///
+/// The zsh integration is always installed; the fish integration is installed too when
+/// `~/.config/fish/config.fish` exists.
+///
 /// # Errors
 /// Returns an error if the shell integration installation fails due to file system operations or permission issues
 pub fn install_shell_integration() -> Result<()> {
@@ -23,9 +31,45 @@ pub fn install_shell_integration() -> Result<()> {
     // Add source directive to shell config files if not already present
     add_source_directive(&zshrc_path, &integration_path)?;
 
+    let fish_config_path = fish_config_path(&home_dir);
+    if fish_config_path.exists() {
+        let fish_integration_path = config_dir.join("shell_integration.fish");
+        write_fish_integration_script(&fish_integration_path)?;
+        add_source_directive(&fish_config_path, &fish_integration_path)?;
+    }
+
     println!("Super Snoofer shell integration installed successfully.");
     println!("Please restart your shell or run 'source ~/.zshrc' to activate it.");
+    if fish_config_path.exists() {
+        println!("In fish, run 'source ~/.config/fish/config.fish' instead.");
+    }
+
+    Ok(())
+}
+
+/// Get the path of fish's configuration file
+fn fish_config_path(home_dir: &std::path::Path) -> std::path::PathBuf {
+    home_dir.join(".config").join("fish").join("config.fish")
+}
 
+/// Writes the fish integration script to the specified path
+///
+/// Fish has no `preexec` hook to catch `]` and `]]`, so they're abbreviations that
+/// expand to the prompt command, with the cursor left inside its quotes for the prompt.
+///
+/// # Errors
+/// Returns an error if writing to the file fails
+fn write_fish_integration_script(integration_path: &std::path::Path) -> Result<()> {
+    let handler = get_shell_integration("fish")?;
+    let script = format!(
+        r#"# Super Snoofer Integration for fish
+{handler}
+# AI prompt shortcuts: `]` for the standard model, `]]` for Codestral (fish 3.6+)
+abbr --add ']' --set-cursor 'super_snoofer --prompt "%"'
+abbr --add ']]' --set-cursor 'super_snoofer --codestral --prompt "%"'
+"#
+    );
+    fs::write(integration_path, script)?;
     Ok(())
 }
 
@@ -34,19 +78,31 @@ pub fn install_shell_integration() -> Result<()> {
 /// # Errors
 /// Returns an error if writing to the file fails
 fn write_integration_script(integration_path: &std::path::Path) -> Result<()> {
-    let script = r###"# Super Snoofer Integration - Fixed Version v2
+    let script = r#"# Super Snoofer Integration - Fixed Version v2
 # Flag to prevent double execution
 typeset -g __super_snoofer_executing=0
 
 function __super_snoofer_check_command_line() {
     # Get the raw command line as passed to preexec
     local raw_cmd="$1"
+    # Hand the typed and alias-expanded forms to super_snoofer when they differ
+    if [[ "$1" != "$3" ]]; then
+        typeset -gx SUPER_SNOOFER_TYPED_LINE="$1" SUPER_SNOOFER_EXPANDED_LINE="$3"
+    else
+        unset SUPER_SNOOFER_TYPED_LINE SUPER_SNOOFER_EXPANDED_LINE
+    fi
     # Skip if we're already executing a super_snoofer command
     if (( __super_snoofer_executing )); then
         __super_snoofer_executing=0
         return 0
     fi
     
+    # Note alias invocations so unused aliases can be reported
+    local first_word="${${(z)raw_cmd}[1]}"
+    if [[ -n "$first_word" ]] && (( ${+aliases[$first_word]} )); then
+        command super_snoofer record-alias-use "$first_word" &>/dev/null &!
+    fi
+    
     # Check for pipes, redirects, and other special shell syntax that should NOT be intercepted
     if [[ "$raw_cmd" == *"|"* || "$raw_cmd" == *">"* || "$raw_cmd" == *"<"* || 
           "$raw_cmd" == *"&"* || "$raw_cmd" == *";"* || "$raw_cmd" == *"&&"* || 
@@ -96,72 +152,6 @@ function __super_snoofer_check_command_line() {
     return 0
 }
 
-# Function to get suggestions from super_snoofer for any command
-function __super_snoofer_get_suggestion() {
-    local cmd="$1"
-    local result
-    
-    # Skip certain command patterns that might cause issues
-    if [[ "$cmd" =~ "failed with status" || "$cmd" =~ "exit status" || "$cmd" =~ "Command failed" ]]; then
-        echo "$cmd"
-        return
-    fi
-    
-    # Skip system commands and common utilities that don't need suggestions
-    if [[ "$cmd" =~ ^alias || "$cmd" =~ ^which || "$cmd" =~ ^echo || "$cmd" =~ ^compgen || \
-          "$cmd" =~ ^nvim || "$cmd" =~ ^vim || "$cmd" =~ ^cd || "$cmd" =~ ^ls || "$cmd" =~ ^git || \
-          "$cmd" =~ ^python || "$cmd" =~ ^python3 || "$cmd" =~ ^pip || "$cmd" =~ ^pip3 ]]; then
-        echo "$cmd"
-        return
-    fi
-    
-    # Special handling for cargo subcommands
-    if [[ "$cmd" =~ ^cargo[[:space:]]+ ]]; then
-        local subcmd=$(echo "$cmd" | cut -d' ' -f2)
-        local cargo_args=$(echo "$cmd" | cut -d' ' -f3-)
-        
-        # Common cargo command corrections
-        case "$subcmd" in
-            "urn") 
-                echo "cargo run $cargo_args"
-                return
-                ;;
-            "biuld") 
-                echo "cargo build $cargo_args"
-                return
-                ;;
-            "cehck") 
-                echo "cargo check $cargo_args"
-                return
-                ;;
-            "tset") 
-                echo "cargo test $cargo_args"
-                return
-                ;;
-            "isntall") 
-                echo "cargo install $cargo_args"
-                return
-                ;;
-            # No correction for valid cargo commands
-            "run"|"build"|"test"|"check"|"update"|"clean"|"doc"|"publish"|"install")
-                echo "$cmd"
-                return
-                ;;
-        esac
-    fi
-    
-    # Call super_snoofer in quiet mode to check if this is a known typo
-    result=$(super_snoofer full-command "$cmd" 2>/dev/null)
-    
-    # If super_snoofer returned a valid suggestion, use it
-    if [[ $? -eq 0 && -n "$result" && "$result" != "$cmd" && "$result" != *"failed with status"* && "$result" != *"exit status"* ]]; then
-        echo "$result"
-    else
-        # No suggestion found or invalid suggestion
-        echo "$cmd"
-    fi
-}
-
 # Define shell functions for ] and ]] to avoid "command not found" errors
 function ]() {
     __super_snoofer_executing=1
@@ -171,6 +161,11 @@ function ]() {
 # Need to use aliases instead of functions for ]] due to syntax limitations
 alias ']]'='__super_snoofer_executing=1; command super_snoofer --prompt "" --codestral'
 
+# `thefuck`-style correction of the previous command, unless `fuck` is already taken
+if ! (( ${+aliases[fuck]} || ${+functions[fuck]} )); then
+    alias fuck='__super_snoofer_executing=1; command super_snoofer --thefuck-style -- "$(fc -ln -1)"'
+fi
+
 # Hook into the pre-exec function in ZSH
 autoload -Uz add-zsh-hook
 add-zsh-hook preexec __super_snoofer_check_command_line
@@ -202,18 +197,14 @@ command_not_found_handler() {
         return 0
     fi
     
-    # For all other commands, use super_snoofer to help
+    # For all other commands, use super_snoofer to help, asking the daemon first
     __super_snoofer_executing=1
-    if [ $# -eq 0 ]; then
-        command super_snoofer -- "$cmd"
-    else
-        command super_snoofer -- "$cmd" "$@"
-    fi
+    __super_snoofer_not_found "$cmd" "$@"
     return $?
 }
-"###;
+"#;
 
-    fs::write(integration_path, script)?;
+    fs::write(integration_path, format!("{script}{}", not_found_function("zsh")))?;
 
     Ok(())
 }
@@ -223,28 +214,28 @@ command_not_found_handler() {
 /// # Errors
 /// Returns an error if reading from or writing to the shell configuration file fails
 fn add_source_directive(
-    zshrc_path: &std::path::Path,
+    config_path: &std::path::Path,
     integration_path: &std::path::Path,
 ) -> Result<()> {
     let integration_path_str = integration_path.to_string_lossy();
     let source_line = format!("source {integration_path_str}");
 
-    let mut add_to_zshrc = true;
+    let mut add_to_config = true;
 
-    // Check if the source directive already exists in .zshrc
-    if zshrc_path.exists() {
-        let zshrc_content = fs::read_to_string(zshrc_path)?;
-        if zshrc_content.contains(&source_line) || zshrc_content.contains(&*integration_path_str) {
-            add_to_zshrc = false;
+    // Check if the source directive already exists in the config file
+    if config_path.exists() {
+        let config_content = fs::read_to_string(config_path)?;
+        if config_content.contains(&source_line) || config_content.contains(&*integration_path_str) {
+            add_to_config = false;
         }
     }
 
-    // Add the source directive to .zshrc if needed
-    if add_to_zshrc {
+    // Add the source directive to the config file if needed
+    if add_to_config {
         let mut file = std::fs::OpenOptions::new()
             .append(true)
             .create(true)
-            .open(zshrc_path)?;
+            .open(config_path)?;
 
         writeln!(file, "\n# Super Snoofer shell integration")?;
         writeln!(file, "{source_line}")?;
@@ -270,19 +261,17 @@ pub fn uninstall_shell_integration() -> Result<()> {
     }
 
     // Remove the source line from .zshrc
-    let content = fs::read_to_string(&zshrc_path)?;
-    let integration_path_str = integration_path.to_string_lossy();
-    let new_content = content
-        .lines()
-        .filter(|line| {
-            !line.contains("Source Super Snoofer integration")
-                && !line.contains(&*integration_path_str)
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+    remove_source_directive(&zshrc_path, &integration_path)?;
 
-    // Write the updated content back
-    fs::write(&zshrc_path, new_content)?;
+    // Remove the fish integration the same way, if it was installed
+    let fish_integration_path = config_dir.join("shell_integration.fish");
+    if fish_integration_path.exists() {
+        fs::remove_file(&fish_integration_path)?;
+    }
+    let fish_config_path = fish_config_path(&home_dir);
+    if fish_config_path.exists() {
+        remove_source_directive(&fish_config_path, &fish_integration_path)?;
+    }
 
     // Try to remove config directory if empty
     if config_dir.exists() {
@@ -296,50 +285,143 @@ pub fn uninstall_shell_integration() -> Result<()> {
     Ok(())
 }
 
+/// Removes the source directive for an integration script from a shell configuration file
+///
+/// # Errors
+/// Returns an error if reading from or writing to the shell configuration file fails
+fn remove_source_directive(
+    config_path: &std::path::Path,
+    integration_path: &std::path::Path,
+) -> Result<()> {
+    let content = fs::read_to_string(config_path)?;
+    let integration_path_str = integration_path.to_string_lossy();
+    let new_content = content
+        .lines()
+        .filter(|line| {
+            !line.contains("Source Super Snoofer integration")
+                && line.trim() != "# Super Snoofer shell integration"
+                && !line.contains(&*integration_path_str)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Write the updated content back
+    fs::write(config_path, new_content)?;
+    Ok(())
+}
+
+/// Get the command-not-found handler of a shell, along with the function it calls
+///
+/// # Errors
+/// Returns an error if the shell is not supported
 pub fn get_shell_integration(shell: &str) -> Result<String> {
-    match shell {
+    let handler = match shell {
         "zsh" => {
-            let script = format!(
-                r#"
+            r#"
 # Super Snoofer command-not-found handler
-command_not_found_handler() {{
+command_not_found_handler() {
     local cmd="$1"
     shift
     if [ -n "$cmd" ]; then
-        if [ $# -eq 0 ]; then
-            command super_snoofer -- "$cmd"
-        else
-            command super_snoofer -- "$cmd" "$@"
-        fi
+        __super_snoofer_not_found "$cmd" "$@"
         return $?
     fi
     return 127
-}}
+}
 "#
-            );
-            Ok(script)
         }
         "bash" => {
-            let script = format!(
-                r#"
+            r#"
 # Super Snoofer command-not-found handler
-command_not_found_handle() {{
+command_not_found_handle() {
     local cmd="$1"
     shift
     if [ -n "$cmd" ]; then
-        if [ $# -eq 0 ]; then
-            command super_snoofer -- "$cmd"
-        else
-            command super_snoofer -- "$cmd" "$@"
-        fi
+        __super_snoofer_not_found "$cmd" "$@"
         return $?
     fi
     return 127
-}}
+}
 "#
-            );
-            Ok(script)
         }
-        _ => Err(anyhow::anyhow!("Unsupported shell: {}", shell)),
+        "fish" => {
+            r"
+# Super Snoofer command-not-found handler
+function fish_command_not_found
+    __super_snoofer_not_found $argv
+end
+"
+        }
+        _ => return Err(anyhow::anyhow!("Unsupported shell: {}", shell)),
+    };
+    Ok(format!("{}{handler}", not_found_function(shell)))
+}
+
+/// Get the function a shell's command-not-found handler calls with the command's words
+///
+/// It asks a running daemon over its socket itself, with zsh's socket module or with
+/// `nc -U` in bash and fish, and prints the daemon's answer when there's nothing to
+/// correct. `super_snoofer` only starts to prompt for a correction, taking the daemon's
+/// reply in [`REPLY_VAR`], or when no daemon answers. The words are sent separated by
+/// tabs, see [`crate::daemon::run_daemon`]; fish's `$argv` keeps each word whole, see
+/// [`crate::shell::arguments`].
+fn not_found_function(shell: &str) -> String {
+    let socket = socket_path();
+    let socket = quote_word(&socket.to_string_lossy()).into_owned();
+    match shell {
+        "zsh" => format!(
+            r#"
+# Ask a running daemon first; super_snoofer only starts to prompt for a correction
+__super_snoofer_not_found() {{
+    local answer
+    if [[ -S {socket} ]] && zmodload zsh/net/socket 2>/dev/null && zsocket {socket} 2>/dev/null; then
+        local fd=$REPLY
+        print -r -u $fd -- "${{(pj:\t:)@}}"
+        read -r -u $fd -t 2 answer
+        exec {{fd}}>&-
+    fi
+    if [[ "$answer" == {NO_CORRECTION_REPLY}* ]]; then
+        print -rl -- "${{(@)${{(@ps:\t:)answer}}[2,-1]}}"
+        return 127
+    fi
+    {REPLY_VAR}="$answer" command super_snoofer -- "$@"
+}}
+"#
+        ),
+        "bash" => format!(
+            r#"
+# Ask a running daemon first; super_snoofer only starts to prompt for a correction
+__super_snoofer_not_found() {{
+    local answer=""
+    if [[ -S {socket} ]] && command -v nc >/dev/null 2>&1; then
+        answer=$(IFS=$'\t'; printf '%s\n' "$*" | nc -U -w 2 {socket} 2>/dev/null)
+    fi
+    if [[ "$answer" == {NO_CORRECTION_REPLY}* ]]; then
+        local IFS=$'\t' lines
+        read -ra lines <<< "$answer"
+        printf '%s\n' "${{lines[@]:1}}"
+        return 127
+    fi
+    {REPLY_VAR}="$answer" command super_snoofer -- "$@"
+}}
+"#
+        ),
+        _ => format!(
+            r#"
+# Ask a running daemon first; super_snoofer only starts to prompt for a correction
+function __super_snoofer_not_found
+    set -l answer
+    if test -S {socket}; and type -q nc
+        set answer (string join \t -- $argv | nc -U -w 2 {socket} 2>/dev/null)
+    end
+    if string match -q -- '{NO_CORRECTION_REPLY}*' "$answer"
+        set -l lines (string split \t -- "$answer")
+        printf '%s\n' $lines[2..-1]
+        return 127
+    end
+    {REPLY_VAR}="$answer" command super_snoofer -- $argv
+end
+"#
+        ),
     }
 }
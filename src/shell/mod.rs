@@ -1,8 +1,10 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 pub mod aliases;
+pub mod arguments;
+pub mod expansion;
 pub mod integration;
 
 // Re-export the public interface
 pub use integration::{install_shell_integration, uninstall_shell_integration};
-pub use aliases::{add_alias, suggest_aliases};
+pub use aliases::{add_alias, remove_alias, suggest_aliases};
@@ -0,0 +1,128 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use crate::{utils::calculate_similarity_with, CommandCache};
+use std::{cell::RefCell, collections::HashMap};
+
+/// A correction offered for the current input
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The corrected command line
+    pub correction: String,
+    /// Similarity between the typed and corrected command name (0.0 to 1.0)
+    pub score: f64,
+}
+
+/// Corrects input as it is typed, reusing work between keystrokes
+///
+/// Unlike the one-shot [`CommandCache::fix_command_line`], the corrector remembers how
+/// each command name resolved, so typing arguments after a command never searches the
+/// command set again, and repeating an earlier input returns the previous suggestion.
+pub struct IncrementalCorrector<'a> {
+    cache: &'a CommandCache,
+    resolved_commands: RefCell<HashMap<String, Option<String>>>,
+    last_input: String,
+    last_suggestion: Option<Suggestion>,
+}
+
+impl<'a> IncrementalCorrector<'a> {
+    /// Create a corrector over the given command cache
+    #[must_use]
+    pub fn new(cache: &'a CommandCache) -> Self {
+        Self {
+            cache,
+            resolved_commands: RefCell::new(HashMap::new()),
+            last_input: String::new(),
+            last_suggestion: None,
+        }
+    }
+
+    /// Get the best correction for the input typed so far
+    ///
+    /// Returns `None` when the input is empty or already correct.
+    pub fn update(&mut self, partial_input: &str) -> Option<Suggestion> {
+        if partial_input == self.last_input {
+            return self.last_suggestion.clone();
+        }
+
+        self.last_input = partial_input.to_string();
+        self.last_suggestion = self.correct(partial_input.trim());
+        self.last_suggestion.clone()
+    }
+
+    /// Forget the remembered command resolutions, e.g. after the cache was updated
+    pub fn reset(&mut self) {
+        self.resolved_commands.get_mut().clear();
+        self.last_input.clear();
+        self.last_suggestion = None;
+    }
+
+    /// Correct a trimmed command line using the remembered command resolutions
+    fn correct(&self, input: &str) -> Option<Suggestion> {
+        let command = input.split_whitespace().next()?;
+        let corrected_command = self.resolve(command)?;
+
+        let correction = if input == command {
+            corrected_command.clone()
+        } else {
+            crate::command::fix_command_line(
+                input,
+                |cmd| self.resolve(cmd),
+                self.cache.command_patterns(),
+            )?
+        };
+
+        if correction == input {
+            return None;
+        }
+
+        Some(Suggestion {
+            score: calculate_similarity_with(
+                command,
+                &corrected_command,
+                self.cache.settings().similarity_algorithm,
+            ),
+            correction,
+        })
+    }
+
+    /// Resolve a command name through the cache, remembering the result
+    fn resolve(&self, command: &str) -> Option<String> {
+        if let Some(resolved) = self.resolved_commands.borrow().get(command) {
+            return resolved.clone();
+        }
+
+        let resolved = self.cache.find_similar(command);
+        self.resolved_commands
+            .borrow_mut()
+            .insert(command.to_string(), resolved.clone());
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reused_corrector_matches_a_fresh_one_on_every_keystroke() {
+        let mut cache = CommandCache::new();
+        for command in ["git", "cargo"] {
+            cache.insert(command);
+        }
+        let mut corrector = IncrementalCorrector::new(&cache);
+
+        let typed = "gti stauts --short";
+        for end in (1..=typed.len()).chain([typed.len(), 3, typed.len()]) {
+            let partial = &typed[..end];
+            assert_eq!(
+                corrector.update(partial),
+                IncrementalCorrector::new(&cache).update(partial),
+                "{partial:?}"
+            );
+        }
+        assert_eq!(
+            corrector.update(typed).map(|suggestion| suggestion.correction).as_deref(),
+            Some("git status --short")
+        );
+    }
+}
@@ -1,9 +1,14 @@
 #![warn(clippy::all, clippy::pedantic)]
 
+pub mod incremental;
+
+pub use incremental::{IncrementalCorrector, Suggestion};
+
 use crate::shell::aliases::{add_to_shell_config, detect_shell_config};
-use crate::HistoryTracker;
+use crate::{HistoryTracker, Verbosity};
 use anyhow::Result;
 use colored::Colorize;
+use std::fmt::Write as _;
 use std::io::Write;
 use std::path::Path;
 
@@ -37,22 +42,17 @@ pub fn suggest_alias_command() -> Result<()> {
     let (command, frequency) = &corrections[0];
 
     // Generate an alias suggestion
-    let alias_name = if command.len() <= 3 {
+    let alias_name = if command.chars().count() <= 3 {
         command.to_string()
     } else {
-        command[0..2].to_string()
+        command.chars().take(2).collect()
     };
 
-    // Generate a personalized tip
-    println!("🐺 *friendly growl* I noticed you use '{}' frequently! ({}x)", command.bright_cyan(), frequency);
-    println!(
-        "\nSuggested alias: {} → {}",
-        alias_name.bright_green(),
-        command.bright_blue()
+    // Generate a personalized tip and ask if user wants to add the alias
+    print!(
+        "{}",
+        format_alias_suggestion(&alias_name, command, *frequency, cache.settings().verbosity)
     );
-
-    // Ask if user wants to add the alias
-    print!("\nWould you like to add this alias? (y/N) ");
     std::io::stdout().flush()?;
 
     let mut response = String::new();
@@ -72,6 +72,43 @@ pub fn suggest_alias_command() -> Result<()> {
     Ok(())
 }
 
+/// Format an alias suggestion and its (y/N) prompt for the given verbosity
+///
+/// Terse output is a single actionable line; the returned text ends with the prompt
+/// and no trailing newline so the answer is typed on the same line.
+#[must_use]
+pub fn format_alias_suggestion(
+    alias_name: &str,
+    command: &str,
+    frequency: usize,
+    verbosity: Verbosity,
+) -> String {
+    if verbosity == Verbosity::Terse {
+        return format!(
+            "Add alias {} → {}? (y/N) ",
+            alias_name.bright_green(),
+            command.bright_blue()
+        );
+    }
+
+    let mut text = format!(
+        "🐺 *friendly growl* I noticed you use '{}' frequently! ({}x)\n",
+        command.bright_cyan(),
+        frequency
+    );
+    let _ = writeln!(
+        text,
+        "\nSuggested alias: {} → {}",
+        alias_name.bright_green(),
+        command.bright_blue()
+    );
+    if verbosity == Verbosity::Chatty {
+        text.push_str("Aliases live in your shell config, so they work even without me! 🐾\n");
+    }
+    text.push_str("\nWould you like to add this alias? (y/N) ");
+    text
+}
+
 /// Get command suggestions for a possibly misspelled command
 ///
 /// # Arguments
@@ -81,7 +118,8 @@ pub fn suggest_alias_command() -> Result<()> {
 ///
 /// # Returns
 ///
-/// A vector of suggested commands that are similar to the input command
+/// A vector of suggested commands that are similar to the input command, holding at
+/// most the configured `max_suggestions`
 #[must_use]
 pub fn get_command_suggestions(command: &str, cache: &crate::CommandCache) -> Vec<String> {
     let mut suggestions = Vec::new();
@@ -92,11 +130,82 @@ pub fn get_command_suggestions(command: &str, cache: &crate::CommandCache) -> Ve
     }
 
     // Then look for aliases and similar commands
-    if let Some(correction) = cache.get_closest_match(command, crate::cache::SIMILARITY_THRESHOLD) {
+    if let Some(correction) = cache.get_closest_match(command) {
         if !suggestions.contains(&correction) {
             suggestions.push(correction);
         }
     }
 
+    suggestions.truncate(cache.settings().max_suggestions);
     suggestions
 }
+
+/// Rank correction candidates for a possibly misspelled command
+///
+/// A learned correction always ranks first; the remaining candidates are ordered
+/// by [`crate::utils::combined_score`] so frequently accepted corrections win
+/// over equally similar but rarely used ones.
+///
+/// # Arguments
+///
+/// * `command` - The potentially misspelled command
+/// * `cache` - The command cache to search through
+///
+/// # Returns
+///
+/// A vector of `(candidate, score)` pairs, best first, holding at most the configured
+/// `max_suggestions`
+#[must_use]
+pub fn rank_corrections(command: &str, cache: &crate::CommandCache) -> Vec<(String, f64)> {
+    let mut ranked = Vec::new();
+
+    if let Some(correction) = cache.get_direct_correction(command) {
+        ranked.push((correction.clone(), 1.0));
+    }
+
+    for (candidate, score) in cache.get_closest_matches(command) {
+        if !ranked.iter().any(|(existing, _)| *existing == candidate) {
+            ranked.push((candidate, score));
+        }
+    }
+
+    ranked.truncate(cache.settings().max_suggestions);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terse_alias_suggestions_are_a_single_line() {
+        colored::control::set_override(false);
+        let terse = format_alias_suggestion("gs", "git status", 12, Verbosity::Terse);
+        assert_eq!(terse, "Add alias gs → git status? (y/N) ");
+        assert!(!terse.contains('\n'));
+
+        let normal = format_alias_suggestion("gs", "git status", 12, Verbosity::Normal);
+        assert!(normal.lines().count() > 1);
+        assert!(normal.contains("(12x)"));
+    }
+
+    #[test]
+    fn suggestions_are_capped_at_max_suggestions() {
+        let mut cache = crate::CommandCache::new();
+        for command in ["cargo", "cargoo", "cargo2", "carg0"] {
+            cache.insert(command);
+        }
+        cache.learn_correction("crago", "cargo2", None).unwrap();
+
+        assert_eq!(rank_corrections("crago", &cache).len(), 3);
+        assert_eq!(get_command_suggestions("crago", &cache).len(), 2);
+
+        cache.settings_mut().max_suggestions = 1;
+        let ranked = rank_corrections("crago", &cache);
+        assert_eq!(ranked, vec![("cargo2".to_string(), 1.0)]);
+        assert_eq!(get_command_suggestions("crago", &cache), vec!["cargo2".to_string()]);
+
+        cache.settings_mut().max_suggestions = 4;
+        assert_eq!(rank_corrections("crago", &cache).len(), 4);
+    }
+}
@@ -0,0 +1,371 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use std::io;
+
+use crate::shell::{add_alias, remove_alias};
+use crate::CommandCache;
+
+/// A shell alias as listed in the alias manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasEntry {
+    pub name: String,
+    pub target: String,
+    /// Whether the alias hides a command of the same name in the PATH
+    pub shadows_command: bool,
+}
+
+/// Which field of the alias form has focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasField {
+    Name,
+    Target,
+}
+
+/// What the alias manager is doing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasMode {
+    /// Browsing the alias list
+    Browse,
+    /// Filling in the form for a new alias, or for `original` when editing one
+    Edit {
+        original: Option<String>,
+        name: String,
+        target: String,
+        field: AliasField,
+    },
+    /// Waiting for the deletion of an alias to be confirmed
+    ConfirmDelete(String),
+}
+
+/// State of the alias manager
+#[derive(Debug, Clone)]
+pub struct AliasState {
+    pub entries: Vec<AliasEntry>,
+    pub selected: usize,
+    pub mode: AliasMode,
+    /// Outcome of the last change, shown above the help line
+    pub status: String,
+    /// Whether any alias was added, edited or deleted
+    pub changed: bool,
+}
+
+impl AliasState {
+    /// Create a state listing the cached aliases
+    #[must_use]
+    pub fn new(cache: &CommandCache) -> Self {
+        Self {
+            entries: alias_entries(cache),
+            selected: 0,
+            mode: AliasMode::Browse,
+            status: String::new(),
+            changed: false,
+        }
+    }
+
+    fn selected_entry(&self) -> Option<&AliasEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Re-list the aliases after the config files changed, keeping `name` selected
+    fn reload(&mut self, cache: &CommandCache, name: Option<&str>) {
+        self.entries = alias_entries(cache);
+        self.selected = name
+            .and_then(|name| self.entries.iter().position(|entry| entry.name == name))
+            .unwrap_or(self.selected)
+            .min(self.entries.len().saturating_sub(1));
+    }
+}
+
+/// List the cached aliases sorted by name, flagging those that shadow PATH commands
+fn alias_entries(cache: &CommandCache) -> Vec<AliasEntry> {
+    let mut entries: Vec<AliasEntry> = cache
+        .shell_aliases()
+        .iter()
+        .map(|(name, target)| AliasEntry {
+            name: name.clone(),
+            target: target.clone(),
+            shadows_command: cache.is_path_command(name),
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Run the alias manager
+///
+/// Aliases can be added (`a`), edited (`e`/Enter) and deleted (`d`/Delete). Changes are
+/// written to the shell config right away and the cached aliases are re-parsed.
+/// Returns whether anything changed, so the caller can ask for the shell to be reloaded.
+///
+/// # Errors
+/// Returns an error if the terminal cannot be set up or an event cannot be read
+pub fn run_alias_manager(cache: &mut CommandCache) -> Result<bool> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run_alias_loop(&mut terminal, cache);
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn run_alias_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    cache: &mut CommandCache,
+) -> Result<bool> {
+    let mut state = AliasState::new(cache);
+
+    loop {
+        terminal.draw(|frame| draw_alias_ui(frame, &state))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(state.changed);
+        }
+
+        match state.mode.clone() {
+            AliasMode::Browse => {
+                if !handle_browse_key(&mut state, key) {
+                    return Ok(state.changed);
+                }
+            }
+            AliasMode::Edit { .. } => handle_edit_key(&mut state, key, cache),
+            AliasMode::ConfirmDelete(name) => {
+                if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                    let outcome = remove_alias(&name).map(|_| ());
+                    apply_change(&mut state, cache, outcome, &format!("Deleted alias {name}"), None);
+                } else {
+                    state.status = "Deletion cancelled".to_string();
+                }
+                state.mode = AliasMode::Browse;
+            }
+        }
+    }
+}
+
+/// Handle a key while browsing, returning `false` to quit
+fn handle_browse_key(state: &mut AliasState, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => return false,
+        KeyCode::Down | KeyCode::Char('j') => {
+            state.selected = (state.selected + 1).min(state.entries.len().saturating_sub(1));
+        }
+        KeyCode::Up | KeyCode::Char('k') => state.selected = state.selected.saturating_sub(1),
+        KeyCode::Char('a') => {
+            state.mode = AliasMode::Edit {
+                original: None,
+                name: String::new(),
+                target: String::new(),
+                field: AliasField::Name,
+            };
+        }
+        KeyCode::Char('e') | KeyCode::Enter => {
+            if let Some(entry) = state.selected_entry() {
+                state.mode = AliasMode::Edit {
+                    original: Some(entry.name.clone()),
+                    name: entry.name.clone(),
+                    target: entry.target.clone(),
+                    field: AliasField::Target,
+                };
+            }
+        }
+        KeyCode::Char('d') | KeyCode::Delete => {
+            if let Some(entry) = state.selected_entry() {
+                state.mode = AliasMode::ConfirmDelete(entry.name.clone());
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+/// Handle a key in the alias form, saving the alias on Enter
+fn handle_edit_key(state: &mut AliasState, key: KeyEvent, cache: &mut CommandCache) {
+    let AliasMode::Edit {
+        original,
+        name,
+        target,
+        field,
+    } = &mut state.mode
+    else {
+        return;
+    };
+
+    match key.code {
+        KeyCode::Esc => state.mode = AliasMode::Browse,
+        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+            *field = match field {
+                AliasField::Name => AliasField::Target,
+                AliasField::Target => AliasField::Name,
+            };
+        }
+        KeyCode::Backspace => {
+            match field {
+                AliasField::Name => name.pop(),
+                AliasField::Target => target.pop(),
+            };
+        }
+        // Alias names can't contain whitespace
+        KeyCode::Char(c) if *field == AliasField::Name && !c.is_whitespace() => name.push(c),
+        KeyCode::Char(c) if *field == AliasField::Target => target.push(c),
+        KeyCode::Enter => {
+            let (original, name, target) = (original.clone(), name.trim().to_string(), target.trim().to_string());
+            if name.is_empty() || target.is_empty() {
+                state.status = "An alias needs both a name and a command".to_string();
+                return;
+            }
+
+            let outcome = save_alias(original.as_deref(), &name, &target);
+            let message = match original {
+                Some(original) if original != name => format!("Renamed alias {original} to {name}"),
+                Some(_) => format!("Updated alias {name}"),
+                None => format!("Added alias {name}"),
+            };
+            apply_change(state, cache, outcome, &message, Some(&name));
+            state.mode = AliasMode::Browse;
+        }
+        _ => {}
+    }
+}
+
+/// Write an added or edited alias, replacing the old definitions of both names
+fn save_alias(original: Option<&str>, name: &str, target: &str) -> Result<()> {
+    if let Some(original) = original {
+        remove_alias(original)?;
+    }
+    remove_alias(name)?;
+    add_alias(name, Some(target))
+}
+
+/// Re-parse the aliases after a change and report how it went
+fn apply_change(
+    state: &mut AliasState,
+    cache: &mut CommandCache,
+    outcome: Result<()>,
+    message: &str,
+    select: Option<&str>,
+) {
+    state.status = match outcome.and_then(|()| cache.force_refresh_aliases()) {
+        Ok(_) => {
+            state.changed = true;
+            message.to_string()
+        }
+        Err(e) => format!("Error: {e}"),
+    };
+    state.reload(cache, select);
+}
+
+/// Draw the alias list, the form while editing and the help line
+pub fn draw_alias_ui(f: &mut Frame, state: &AliasState) {
+    let form_height = if matches!(state.mode, AliasMode::Edit { .. }) { 4 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),              // Aliases
+            Constraint::Length(form_height), // Form
+            Constraint::Length(1),           // Status
+            Constraint::Length(1),           // Help
+        ])
+        .margin(1)
+        .split(f.area());
+
+    let shadowing = state.entries.iter().filter(|entry| entry.shadows_command).count();
+    let items: Vec<ListItem> = state
+        .entries
+        .iter()
+        .map(|entry| {
+            let mut spans = vec![
+                Span::styled(entry.name.clone(), Style::default().fg(Color::Green)),
+                Span::raw(" → "),
+                Span::raw(entry.target.clone()),
+            ];
+            if entry.shadows_command {
+                spans.push(Span::styled(
+                    "  (shadows a command)",
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let title = format!(
+        "Aliases ({}, {shadowing} shadowing commands)",
+        state.entries.len()
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut list_state =
+        ListState::default().with_selected((!state.entries.is_empty()).then_some(state.selected));
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    if let AliasMode::Edit {
+        original,
+        name,
+        target,
+        field,
+    } = &state.mode
+    {
+        let focused = |this: AliasField| {
+            if *field == this {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            }
+        };
+        let form = Paragraph::new(vec![
+            Line::from(vec![
+                Span::styled("Name:    ", focused(AliasField::Name)),
+                Span::raw(name.clone()),
+            ]),
+            Line::from(vec![
+                Span::styled("Command: ", focused(AliasField::Target)),
+                Span::raw(target.clone()),
+            ]),
+        ])
+        .block(Block::default().borders(Borders::ALL).title(
+            original
+                .as_ref()
+                .map_or_else(|| "New alias".to_string(), |original| format!("Edit {original}")),
+        ));
+        f.render_widget(form, chunks[1]);
+    }
+
+    let status = match &state.mode {
+        AliasMode::ConfirmDelete(name) => format!("Delete alias {name}? (y/N)"),
+        _ => state.status.clone(),
+    };
+    f.render_widget(Paragraph::new(status), chunks[2]);
+
+    let help = match state.mode {
+        AliasMode::Browse => "a: Add | e/Enter: Edit | d: Delete | q/Esc: Quit",
+        AliasMode::Edit { .. } => "Tab: Switch field | Enter: Save | Esc: Cancel",
+        AliasMode::ConfirmDelete(_) => "y: Delete | any other key: Keep",
+    };
+    f.render_widget(Paragraph::new(help), chunks[3]);
+}
@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -12,14 +12,20 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
     Frame,
     Terminal,
-    style::{Style, Modifier},
+    style::{Color, Style, Modifier},
 };
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::io::{self, stdout};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
-use crate::ollama::OllamaClient;
+use crate::ollama::{AiHistoryLog, AiInteraction, OllamaClient};
 use crate::ollama::ModelConfig as Config;
+use crate::CommandCache;
+use super::folding::{find_sections, fold_text, FoldedText};
 use super::UiMessage;
 
 /// Different states of the model processing
@@ -51,9 +57,10 @@ pub struct UiState {
     pub code_model: String,
     pub scroll: u16,
     pub scroll_max: u16,
-    pub show_thinking_sections: bool,
-    pub thinking_visible: bool,
-    pub thinking_sections_visible: bool,
+    pub sections_expanded: bool,    // Whether collapsible sections are expanded unless toggled
+    pub toggled_sections: HashSet<usize>, // Sections flipped from the default by F4
+    pub fold_tags: Vec<String>,     // `<tag>` sections that can be collapsed, besides code blocks
+    pub idle_timeout: Duration,     // Exit after this long without input or streaming (zero: never)
     pub last_response: Option<String>,
     pub input_height: u16,          // Height of the input box
     pub selection_mode: bool,       // Whether we're in selection mode
@@ -67,6 +74,40 @@ pub struct UiState {
     pub saved_input: String,
     pub text_copied: bool,          // Whether text was just copied
     pub text_copied_timer: u16,     // Timer for showing the copy notification
+    pub mouse_capture: bool,        // Whether we capture the mouse (off: terminal-native selection)
+    pub model_override: Option<String>, // Model used for the next submission only
+    pub model_prompt: Option<String>,   // Model name being typed in the mini-prompt, if open
+    pub input_highlight: Option<CommandHighlight>, // Color of the input's command, if highlighting
+    folded: RefCell<Option<(FoldKey, Arc<FoldedText>)>>, // Last folded response and what it was folded from
+}
+
+/// What a response was folded from: the response and which of its sections are collapsed
+#[derive(Clone)]
+struct FoldKey {
+    response_text: String,
+    fold_tags: Vec<String>,
+    sections_expanded: bool,
+    toggled_sections: HashSet<usize>,
+}
+
+impl FoldKey {
+    /// Take the key of the response as it is now
+    fn of(state: &UiState) -> Self {
+        Self {
+            response_text: state.response_text.clone(),
+            fold_tags: state.fold_tags.clone(),
+            sections_expanded: state.sections_expanded,
+            toggled_sections: state.toggled_sections.clone(),
+        }
+    }
+
+    /// Check whether the response is still folded the same way
+    fn matches(&self, state: &UiState) -> bool {
+        self.response_text == state.response_text
+            && self.fold_tags == state.fold_tags
+            && self.sections_expanded == state.sections_expanded
+            && self.toggled_sections == state.toggled_sections
+    }
 }
 
 impl Default for UiState {
@@ -84,9 +125,13 @@ impl Default for UiState {
             code_model: String::from("codestral"),
             scroll: 0,
             scroll_max: 0,
-            show_thinking_sections: true,
-            thinking_visible: true,
-            thinking_sections_visible: true,
+            sections_expanded: true,
+            toggled_sections: HashSet::new(),
+            fold_tags: crate::settings::DEFAULT_FOLD_TAGS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            idle_timeout: Duration::ZERO,
             last_response: None,
             input_height: 4,          // Default to 4 (2 content lines + 2 border lines)
             selection_mode: false,    // Not in selection mode by default
@@ -100,7 +145,35 @@ impl Default for UiState {
             saved_input: String::new(),
             text_copied: false,
             text_copied_timer: 0,
+            mouse_capture: true,      // Mouse is captured for scrolling and selection by default
+            model_override: None,
+            model_prompt: None,
+            input_highlight: None,
+            folded: RefCell::new(None),
+        }
+    }
+}
+
+impl UiState {
+    /// Get the response as displayed, with collapsed sections folded into placeholders
+    ///
+    /// The folded response is reused until the response or its folding changes, so it's
+    /// not worked out again for every frame.
+    #[must_use]
+    pub fn folded_response(&self) -> Arc<FoldedText> {
+        let mut folded = self.folded.borrow_mut();
+        if let Some((key, text)) = folded.as_ref()
+            && key.matches(self)
+        {
+            return Arc::clone(text);
         }
+
+        let sections = find_sections(&self.response_text, &self.fold_tags);
+        let text = Arc::new(fold_text(&self.response_text, &sections, |index| {
+            self.sections_expanded == self.toggled_sections.contains(&index)
+        }));
+        *folded = Some((FoldKey::of(self), Arc::clone(&text)));
+        text
     }
 }
 
@@ -109,9 +182,11 @@ pub struct TuiApp {
     pub ollama: OllamaClient,
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     config: Arc<Config>,
-    cancel_flag: Arc<Mutex<bool>>,
-    cancel_requested: Arc<Mutex<bool>>,
+    /// Cancels the HTTP request of the response being streamed
+    stream_cancel: CancellationToken,
     tx: mpsc::Sender<UiMessage>,
+    ai_log: Option<AiHistoryLog>,
+    command_cache: Option<Arc<CommandCache>>,
 }
 
 impl TuiApp {
@@ -146,8 +221,6 @@ impl TuiApp {
             code_model: code_model_name,
         });
 
-        let cancel_flag = Arc::new(Mutex::new(false));
-        let cancel_requested = Arc::new(Mutex::new(false));
         let (tx, _rx) = mpsc::channel(10);
 
         Ok(Self {
@@ -155,9 +228,10 @@ impl TuiApp {
             ollama,
             terminal,
             config,
-            cancel_flag,
-            cancel_requested,
+            stream_cancel: CancellationToken::new(),
             tx,
+            ai_log: None,
+            command_cache: None,
         })
     }
 
@@ -258,6 +332,8 @@ impl TuiApp {
         self.state.saved_input = prompt.clone();
         self.state.input.clear();
         self.state.cursor_position = 0;
+        // Sections of the previous response don't carry over to the new one
+        self.state.toggled_sections.clear();
         
         // Set streaming flag and update model state
         self.state.is_streaming = true;
@@ -266,59 +342,39 @@ impl TuiApp {
         // Start streaming response
         let standard_model = self.config.standard_model.clone();
         let _code_model = self.config.code_model.clone();
-        let cancel_flag = Arc::clone(&self.cancel_flag);
-        let cancel_requested = Arc::clone(&self.cancel_requested);
         let tx = self.tx.clone();
-        let ollama_client = self.ollama.clone();
+        // Each submission gets its own token so cancelling can't leak into the next one
+        self.stream_cancel = CancellationToken::new();
+        let stream_cancel = self.stream_cancel.clone();
+        let mut ollama_client = self.ollama.clone();
+        // A one-shot model override applies to this submission only
+        let model_override = self.state.model_override.take();
+        let ai_log = self.ai_log.clone();
+        let logged_prompt = prompt.clone();
         
         tokio::spawn(async move {
-            // Reset the cancel flag at the start of streaming
-            if let Ok(mut cancel_flag) = cancel_flag.lock() {
-                *cancel_flag = false;
-            }
-            
-            // Clear cancel_requested at the start of streaming
-            if let Ok(mut cancel_req) = cancel_requested.lock() {
-                *cancel_req = false;
-            }
-            
-            let use_code_model = standard_model == "codestral";
+            let use_code_model = if let Some(model_override) = model_override {
+                ollama_client.model_config.standard_model = model_override;
+                false
+            } else {
+                standard_model == "codestral"
+            };
+            let model = ollama_client.model_config.get_model(use_code_model).to_string();
             
             // Create a channel for streaming text updates
             let (stream_tx, mut stream_rx) = mpsc::channel::<String>(100);
             
             // Spawn a task to stream the response
+            let request_cancel = stream_cancel.clone();
             let stream_handle = tokio::spawn(async move {
-                if let Err(e) = ollama_client.stream_response(&prompt, use_code_model, stream_tx).await {
-                    return Err::<(), anyhow::Error>(e);
-                }
-                Ok(())
+                ollama_client
+                    .stream_response(&prompt, use_code_model, stream_tx, request_cancel)
+                    .await
             });
             
             // Process the streaming updates
-            let mut full_response = String::new();
-            let mut is_cancelled = false;
-            
-            while let Some(text) = stream_rx.recv().await {
-                // Check if cancel was requested
-                is_cancelled = if let Ok(flag) = cancel_flag.lock() {
-                    *flag
-                } else {
-                    false
-                };
-                
-                if is_cancelled {
-                    break;
-                }
-                
-                // Append the new text to the full response
-                full_response.push_str(&text);
-                
-                // Send the updated response to the UI
-                if let Err(e) = tx.send(UiMessage::ResponseUpdate(full_response.clone())).await {
-                    eprintln!("Failed to send response update: {}", e);
-                }
-            }
+            let (mut full_response, is_cancelled) =
+                collect_stream(&mut stream_rx, &stream_cancel, &tx).await;
             
             // Check if the streaming was cancelled
             if is_cancelled {
@@ -330,7 +386,18 @@ impl TuiApp {
                 // Wait for the streaming to complete
                 match stream_handle.await {
                     Ok(Ok(())) => {
-                        // Streaming completed successfully
+                        // Streaming completed successfully, record it if the user opted in
+                        if let Some(ai_log) = &ai_log {
+                            let interaction = AiInteraction {
+                                timestamp: std::time::SystemTime::now(),
+                                model,
+                                prompt: logged_prompt,
+                                response: full_response,
+                            };
+                            if let Err(e) = ai_log.append(&interaction) {
+                                eprintln!("Failed to log AI interaction: {e}");
+                            }
+                        }
                     },
                     Ok(Err(e)) => {
                         // Streaming had an error
@@ -356,6 +423,32 @@ impl TuiApp {
         Ok(())
     }
 
+    /// Set the log that completed prompts and responses are recorded to
+    pub fn set_ai_log(&mut self, ai_log: Option<AiHistoryLog>) {
+        self.ai_log = ai_log;
+    }
+
+    /// Set the command cache used to highlight the first word of the input
+    pub fn set_command_cache(&mut self, command_cache: Option<CommandCache>) {
+        self.command_cache = command_cache.map(Arc::new);
+    }
+
+    /// Get the command cache used for input highlighting, if enabled
+    #[must_use]
+    pub fn command_cache(&self) -> Option<Arc<CommandCache>> {
+        self.command_cache.clone()
+    }
+
+    /// Bring the input highlighting up to date with the input, if highlighting is enabled
+    pub fn refresh_input_highlight(&mut self) {
+        if let Some(cache) = &self.command_cache {
+            self.state
+                .input_highlight
+                .get_or_insert_default()
+                .update(&self.state.input, cache);
+        }
+    }
+
     /// Scrolls the response text up
     pub fn scroll_up(&mut self) {
         if self.state.scroll > 0 {
@@ -390,7 +483,8 @@ impl TuiApp {
 
     /// Calculate the scroll max based on content and view size
     pub fn update_scroll_max(&mut self, view_height: u16) {
-        let line_count = self.state.response_text.lines().count() as u16;
+        let line_count =
+            u16::try_from(self.state.folded_response().text.lines().count()).unwrap_or(u16::MAX);
         
         // We can only scroll if there are more lines than can fit in the view
         // The maximum scroll position is the number of lines that don't fit
@@ -406,15 +500,50 @@ impl TuiApp {
         }
     }
 
-    /// Toggle visibility of thinking sections
-    pub fn toggle_thinking_sections(&mut self) {
-        self.state.show_thinking_sections = !self.state.show_thinking_sections;
+    /// Collapse or expand all sections at once
+    pub fn toggle_all_sections(&mut self) {
+        self.state.sections_expanded = !self.state.sections_expanded;
+        self.state.toggled_sections.clear();
         // Reset scroll when toggling to avoid confusion
         self.state.scroll = 0;
     }
 
+    /// Collapse or expand the section at the top of the response view, or the next one below it
+    pub fn toggle_section_at_scroll(&mut self) {
+        let Some(index) = self
+            .state
+            .folded_response()
+            .line_sections
+            .iter()
+            .skip(usize::from(self.state.scroll))
+            .find_map(|section| *section)
+        else {
+            return;
+        };
+
+        if !self.state.toggled_sections.remove(&index) {
+            self.state.toggled_sections.insert(index);
+        }
+
+        // Keep the toggled section in view when collapsing it shortens the text above
+        if let Some(line) = self
+            .state
+            .folded_response()
+            .line_sections
+            .iter()
+            .position(|section| *section == Some(index))
+        {
+            self.state.scroll = self.state.scroll.min(u16::try_from(line).unwrap_or(u16::MAX));
+        }
+    }
+
     /// Toggle selection mode
     pub fn toggle_selection_mode(&mut self) {
+        // In-app selection needs mouse events; without capture the terminal selects natively
+        if !self.state.mouse_capture && !self.state.selection_mode {
+            return;
+        }
+
         self.state.selection_mode = !self.state.selection_mode;
         
         // Reset selection if we're exiting selection mode
@@ -425,6 +554,58 @@ impl TuiApp {
         }
     }
 
+    /// Open the mini-prompt for choosing a model for the next submission only
+    pub fn open_model_prompt(&mut self) {
+        self.state.model_prompt = Some(self.state.model_override.clone().unwrap_or_default());
+    }
+
+    /// Handle a key press while the model mini-prompt is open
+    ///
+    /// Enter sets the one-shot model (an empty name clears it) and Esc closes the
+    /// mini-prompt unchanged. Returns `false` if the mini-prompt is closed, so the key
+    /// should be handled normally.
+    pub fn handle_model_prompt_key(&mut self, key: KeyEvent) -> bool {
+        let Some(model_prompt) = self.state.model_prompt.as_mut() else {
+            return false;
+        };
+
+        match key.code {
+            KeyCode::Enter => {
+                let model = model_prompt.trim().to_string();
+                self.state.model_override = (!model.is_empty()).then_some(model);
+                self.state.model_prompt = None;
+            }
+            KeyCode::Esc => self.state.model_prompt = None,
+            KeyCode::Backspace => {
+                model_prompt.pop();
+            }
+            KeyCode::Char(c) => model_prompt.push(c),
+            _ => {}
+        }
+        true
+    }
+
+    /// Toggle mouse capture so the terminal's own text selection can be used
+    ///
+    /// Turning capture off also leaves selection mode, since in-app selection relies on
+    /// mouse events.
+    ///
+    /// # Errors
+    /// Returns an error if the terminal rejects the mouse capture change
+    pub fn toggle_mouse_capture(&mut self) -> Result<()> {
+        if self.state.mouse_capture {
+            execute!(self.terminal.backend_mut(), DisableMouseCapture)?;
+            if self.state.selection_mode {
+                self.toggle_selection_mode();
+            }
+        } else {
+            execute!(self.terminal.backend_mut(), EnableMouseCapture)?;
+        }
+
+        self.state.mouse_capture = !self.state.mouse_capture;
+        Ok(())
+    }
+
     /// Begin selection at the given position
     pub fn begin_selection(&mut self, row: u16, col: u16) {
         // Adjust for the borders of the response area
@@ -459,8 +640,9 @@ impl TuiApp {
             return;
         }
         
-        // Get the lines of text
-        let lines: Vec<&str> = self.state.response_text.lines().collect();
+        // Get the lines of text as displayed
+        let folded = self.state.folded_response();
+        let lines: Vec<&str> = folded.text.lines().collect();
         
         // Get selection coordinates (taking into account scrolling)
         let (start_row, start_col) = self.state.selection_start;
@@ -541,18 +723,15 @@ impl TuiApp {
         Ok(())
     }
 
+    /// Abort the HTTP request of the response being streamed, if any
+    pub fn cancel_stream(&self) {
+        self.stream_cancel.cancel();
+    }
+
     /// Request cancellation of the current operation
     pub fn request_cancel(&mut self) {
-        // Set the cancel flag
-        if let Ok(mut flag) = self.cancel_flag.lock() {
-            *flag = true;
-        }
-        
-        // Set the cancel requested flag
-        if let Ok(mut flag) = self.cancel_requested.lock() {
-            *flag = true;
-        }
-        
+        self.cancel_stream();
+
         // Update UI state to show cancellation
         self.state.cancel_requested = true;
         
@@ -770,9 +949,10 @@ impl TuiApp {
             ollama,
             terminal,
             config,
-            cancel_flag: Arc::new(Mutex::new(false)),
-            cancel_requested: Arc::new(Mutex::new(false)),
+            stream_cancel: CancellationToken::new(),
             tx,
+            ai_log: None,
+            command_cache: None,
         })
     }
 
@@ -802,16 +982,6 @@ impl TuiApp {
         Ok(())
     }
 
-    /// Get a mutable reference to the cancel_requested Mutex
-    pub fn get_cancel_requested(&self) -> &Arc<Mutex<bool>> {
-        &self.cancel_requested
-    }
-    
-    /// Get a mutable reference to the cancel_flag Mutex
-    pub fn get_cancel_flag(&self) -> &Arc<Mutex<bool>> {
-        &self.cancel_flag
-    }
-    
     /// Fix async locking of Mutex
     fn lock_mutex<T>(mutex: &Mutex<T>) -> Result<std::sync::MutexGuard<'_, T>> {
         mutex.lock().map_err(|e| anyhow::anyhow!("Failed to lock mutex: {}", e))
@@ -832,6 +1002,35 @@ impl TuiApp {
     }
 }
 
+/// Gather the text of a streamed response, sending the response so far to the UI as it grows
+///
+/// Stops as soon as `cancel` is cancelled, even while no text arrives. Returns the
+/// response and whether it was cancelled.
+async fn collect_stream(
+    stream_rx: &mut mpsc::Receiver<String>,
+    cancel: &CancellationToken,
+    tx: &mpsc::Sender<UiMessage>,
+) -> (String, bool) {
+    let mut full_response = String::new();
+    loop {
+        let received = tokio::select! {
+            biased;
+            () = cancel.cancelled() => None,
+            text = stream_rx.recv() => text,
+        };
+        match received {
+            Some(_) if cancel.is_cancelled() => return (full_response, true),
+            Some(text) => full_response.push_str(&text),
+            None => return (full_response, cancel.is_cancelled()),
+        }
+
+        // Send the updated response to the UI
+        if let Err(e) = tx.send(UiMessage::ResponseUpdate(full_response.clone())).await {
+            eprintln!("Failed to send response update: {e}");
+        }
+    }
+}
+
 impl Drop for TuiApp {
     fn drop(&mut self) {
         // Restore terminal state
@@ -846,6 +1045,9 @@ impl Drop for TuiApp {
 }
 
 /// Draw the UI with the current state
+///
+/// With input highlighting on, the first word of the input is colored by whether it is a
+/// known command (green), a likely typo (yellow) or unknown (red).
 pub fn draw_ui(f: &mut Frame, app: &UiState) {
     // Create a flexbox-like layout with dynamic input height
     let input_height = if app.input_height > 0 {
@@ -880,8 +1082,11 @@ pub fn draw_ui(f: &mut Frame, app: &UiState) {
     };
     
     // Input box with instructions
-    let input_text = &app.input;
-    let input = Paragraph::new(input_text.to_string())
+    let input_text = match &app.input_highlight {
+        Some(highlight) => highlight_command_input(&app.input, highlight),
+        None => ratatui::text::Text::from(app.input.clone()),
+    };
+    let input = Paragraph::new(input_text)
         .block(Block::default()
             .borders(Borders::ALL)
             .title(format!("Input (type your response){}", selection_mode_indicator)))
@@ -947,7 +1152,23 @@ pub fn draw_ui(f: &mut Frame, app: &UiState) {
         ""
     };
     
-    let status = Paragraph::new(format!("{}{}", status_text, status_suffix))
+    let model_indicator = if let Some(model_prompt) = &app.model_prompt {
+        format!(" | Model for next prompt: {model_prompt}_ (Enter: set, Esc: cancel)")
+    } else if let Some(model_override) = &app.model_override {
+        format!(" | Next prompt uses: {model_override}")
+    } else {
+        String::new()
+    };
+    
+    let mouse_indicator = if app.mouse_capture {
+        " | Mouse: app (F3)"
+    } else {
+        " | Mouse: terminal (F3)"
+    };
+    
+    let status = Paragraph::new(format!(
+        "{status_text}{status_suffix}{model_indicator}{mouse_indicator}"
+    ))
         .block(Block::default()
         .borders(Borders::ALL)
             .title(format!("Status {status_icon}")));
@@ -977,46 +1198,10 @@ pub fn draw_ui(f: &mut Frame, app: &UiState) {
         ""
     };
     
-    // Process the response text to handle thinking sections and selection highlighting
-    let mut processed_text = if app.show_thinking_sections {
-        app.response_text.clone()
-    } else {
-        // Hide thinking sections by replacing them with a placeholder
-        let mut processed_text = String::new();
-        let mut in_thinking_section = false;
-        let mut has_thinking_sections = false;
-        
-        for line in app.response_text.lines() {
-            if line.contains("<think>") {
-                in_thinking_section = true;
-                has_thinking_sections = true;
-                processed_text.push_str("📝 [Thinking section - press F1 to expand] 📝\n");
-                continue;
-            }
-            
-            if line.contains("</think>") {
-                in_thinking_section = false;
-                continue;
-            }
-            
-            if !in_thinking_section {
-                processed_text.push_str(line);
-                processed_text.push('\n');
-            }
-        }
-        
-        // Remove trailing newline if present
-        if processed_text.ends_with('\n') {
-            processed_text.pop();
-        }
-        
-        if !has_thinking_sections {
-            // If no thinking sections were found, just use the original text
-            app.response_text.clone()
-        } else {
-            processed_text
-        }
-    };
+    // Fold collapsed sections into placeholders before selection highlighting
+    let folded = app.folded_response();
+    let processed_text = folded.text.clone();
+    let content_length = u16::try_from(processed_text.lines().count()).unwrap_or(u16::MAX);
 
     let display_text = if app.selection_mode {
         // In selection mode, create a styled text span for rendering
@@ -1036,7 +1221,8 @@ pub fn draw_ui(f: &mut Frame, app: &UiState) {
     }
     
     if !app.response_text.is_empty() {
-        help_items.push("F1: Toggle thinking");
+        help_items.push("F1: Fold all");
+        help_items.push("F4: Fold section");
         help_items.push("Ctrl+S: Selection mode");
     }
     
@@ -1080,7 +1266,6 @@ pub fn draw_ui(f: &mut Frame, app: &UiState) {
 
     // Render scrollbar if needed
     if app.scroll_max > 0 {
-        let content_length = app.response_text.lines().count() as u16;
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
             .begin_symbol(Some("↑"))
             .end_symbol(Some("↓"));
@@ -1092,6 +1277,70 @@ pub fn draw_ui(f: &mut Frame, app: &UiState) {
     }
 }
 
+/// How the first word of an input resolves against the command cache
+///
+/// Fuzzy matching the word is too slow to repeat every frame, so its color is kept
+/// until the word changes.
+#[derive(Debug, Clone, Default)]
+pub struct CommandHighlight {
+    /// The word the color was worked out for
+    command: String,
+    color: Option<Color>,
+}
+
+impl CommandHighlight {
+    /// Work out the color of the input's first word, unless it's the word already colored
+    ///
+    /// Known commands are green, likely typos yellow and unknown words red.
+    pub fn update(&mut self, input: &str, cache: &CommandCache) {
+        let Some(command) = first_command_word(input) else {
+            return;
+        };
+        if self.color.is_some() && command == self.command {
+            return;
+        }
+        self.color = Some(match cache.find_similar(command) {
+            Some(found) if found == command => Color::Green,
+            Some(_) => Color::Yellow,
+            None => Color::Red,
+        });
+        self.command = command.to_string();
+    }
+}
+
+/// Get the first word of an input's first line
+fn first_command_word(input: &str) -> Option<&str> {
+    input.split('\n').next()?.split_whitespace().next()
+}
+
+/// Color the first word of the input as [`CommandHighlight`] worked out for it
+pub(super) fn highlight_command_input(
+    input: &str,
+    highlight: &CommandHighlight,
+) -> ratatui::text::Text<'static> {
+    let mut lines: Vec<ratatui::text::Line<'static>> = input
+        .split('\n')
+        .map(|line| ratatui::text::Line::from(line.to_string()))
+        .collect();
+
+    let first_line = input.split('\n').next().unwrap_or_default();
+    let leading = first_line.len() - first_line.trim_start().len();
+    let (Some(command), Some(color)) = (first_command_word(input), highlight.color) else {
+        return ratatui::text::Text::from(lines);
+    };
+    if command != highlight.command {
+        return ratatui::text::Text::from(lines);
+    }
+
+    let rest_start = leading + command.len();
+    lines[0] = ratatui::text::Line::from(vec![
+        ratatui::text::Span::raw(first_line[..leading].to_string()),
+        ratatui::text::Span::styled(command.to_string(), Style::default().fg(color)),
+        ratatui::text::Span::raw(first_line[rest_start..].to_string()),
+    ]);
+    ratatui::text::Text::from(lines)
+}
+
 /// Create styled text spans for the response text with selection highlighting
 fn create_styled_text(text: &str, app: &UiState) -> ratatui::text::Text<'static> {
     let lines: Vec<&str> = text.lines().collect();
@@ -1187,4 +1436,39 @@ fn create_styled_text(text: &str, app: &UiState) -> ratatui::text::Text<'static>
     }
     
     ratatui::text::Text::from(styled_lines)
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn streams_are_collected_until_they_end() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(8);
+        let (tx, mut rx) = mpsc::channel(8);
+        stream_tx.send("Awoo".to_string()).await.unwrap();
+        stream_tx.send("!".to_string()).await.unwrap();
+        drop(stream_tx);
+
+        let collected = collect_stream(&mut stream_rx, &CancellationToken::new(), &tx).await;
+        assert_eq!(collected, ("Awoo!".to_string(), false));
+        assert!(matches!(rx.recv().await, Some(UiMessage::ResponseUpdate(text)) if text == "Awoo"));
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_a_stream_that_sends_nothing() {
+        let (stream_tx, mut stream_rx) = mpsc::channel(8);
+        let (tx, _rx) = mpsc::channel(8);
+        stream_tx.send("partial".to_string()).await.unwrap();
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+
+        // The sender stays open, so only the cancellation can end the stream
+        let collected = collect_stream(&mut stream_rx, &cancel, &tx).await;
+        assert_eq!(collected, ("partial".to_string(), true));
+        drop(stream_tx);
+    }
+}
@@ -0,0 +1,295 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use std::io;
+
+use super::app::{highlight_command_input, CommandHighlight};
+use crate::display::format_confidence_bar;
+use crate::suggestion::rank_corrections;
+use crate::CommandCache;
+
+/// A ranked replacement for the typed command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixCandidate {
+    /// The command line with its first word replaced by the candidate
+    pub line: String,
+    /// Score of the candidate command, between 0.0 and 1.0
+    pub score: f64,
+}
+
+/// State of the correction preview: the typed line and what it would become
+#[derive(Debug, Clone, Default)]
+pub struct FixState {
+    /// The command line as typed
+    pub input: String,
+    /// Cursor position in `input`, in characters
+    pub cursor_position: usize,
+    /// What `fix_command_line` turns the input into, if anything
+    pub preview: Option<String>,
+    /// Ranked candidates for the first word of the input
+    pub candidates: Vec<FixCandidate>,
+    /// Highlighted candidate; `None` accepts the preview
+    pub selected: Option<usize>,
+    /// Color of the input's command
+    pub highlight: CommandHighlight,
+}
+
+impl FixState {
+    /// Create a state prefilled with `input` and its corrections
+    #[must_use]
+    pub fn new(input: &str, cache: &CommandCache) -> Self {
+        let mut state = Self {
+            input: input.to_string(),
+            cursor_position: input.chars().count(),
+            ..Self::default()
+        };
+        state.refresh(cache);
+        state
+    }
+
+    /// Recompute the preview and candidates after the input changed
+    pub fn refresh(&mut self, cache: &CommandCache) {
+        self.preview = cache
+            .fix_command_line(&self.input)
+            .filter(|fixed| *fixed != self.input);
+        self.candidates = rank_candidates(&self.input, cache);
+        self.selected = None;
+        self.highlight.update(&self.input, cache);
+    }
+
+    /// The command line Enter would accept: the highlighted candidate, else the
+    /// preview, else the input as typed
+    #[must_use]
+    pub fn accepted(&self) -> &str {
+        self.selected
+            .and_then(|index| self.candidates.get(index))
+            .map(|candidate| candidate.line.as_str())
+            .or(self.preview.as_deref())
+            .unwrap_or(&self.input)
+    }
+
+    fn byte_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor_position)
+            .map_or(self.input.len(), |(index, _)| index)
+    }
+
+    fn select_next(&mut self) {
+        if self.candidates.is_empty() {
+            return;
+        }
+        self.selected = Some(match self.selected {
+            Some(index) => (index + 1).min(self.candidates.len() - 1),
+            None => 0,
+        });
+    }
+
+    fn select_previous(&mut self) {
+        self.selected = match self.selected {
+            Some(0) | None => None,
+            Some(index) => Some(index - 1),
+        };
+    }
+
+    /// Apply an editing key, returning whether the input changed
+    fn edit(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c) => {
+                let index = self.byte_index();
+                self.input.insert(index, c);
+                self.cursor_position += 1;
+                true
+            }
+            KeyCode::Backspace if self.cursor_position > 0 => {
+                self.cursor_position -= 1;
+                let index = self.byte_index();
+                self.input.remove(index);
+                true
+            }
+            KeyCode::Delete if self.cursor_position < self.input.chars().count() => {
+                let index = self.byte_index();
+                self.input.remove(index);
+                true
+            }
+            KeyCode::Left => {
+                self.cursor_position = self.cursor_position.saturating_sub(1);
+                false
+            }
+            KeyCode::Right => {
+                self.cursor_position = (self.cursor_position + 1).min(self.input.chars().count());
+                false
+            }
+            KeyCode::Home => {
+                self.cursor_position = 0;
+                false
+            }
+            KeyCode::End => {
+                self.cursor_position = self.input.chars().count();
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Rank replacements for the first word of `command_line`, keeping its arguments
+fn rank_candidates(command_line: &str, cache: &CommandCache) -> Vec<FixCandidate> {
+    let trimmed = command_line.trim_start();
+    let Some(command) = trimmed.split_whitespace().next() else {
+        return Vec::new();
+    };
+    let rest = &trimmed[command.len()..];
+
+    rank_corrections(command, cache)
+        .into_iter()
+        .filter(|(candidate, _)| candidate != command)
+        .map(|(candidate, score)| FixCandidate {
+            line: format!("{candidate}{rest}"),
+            score,
+        })
+        .collect()
+}
+
+/// Run the interactive correction preview
+///
+/// The corrected form and the ranked candidates update on every keystroke. Up/Down
+/// highlight a candidate, Enter accepts it (or the preview when none is highlighted),
+/// and Esc or Ctrl+C quits without choosing anything.
+///
+/// # Errors
+/// Returns an error if the terminal cannot be set up or an event cannot be read
+pub fn run_fix_mode(initial: &str, cache: &CommandCache) -> Result<Option<String>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run_fix_loop(&mut terminal, FixState::new(initial, cache), cache);
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn run_fix_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mut state: FixState,
+    cache: &CommandCache,
+) -> Result<Option<String>> {
+    loop {
+        terminal.draw(|frame| draw_fix_ui(frame, &state))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Enter => {
+                let accepted = state.accepted().trim();
+                return Ok((!accepted.is_empty()).then(|| accepted.to_string()));
+            }
+            KeyCode::Down | KeyCode::Tab => state.select_next(),
+            KeyCode::Up | KeyCode::BackTab => state.select_previous(),
+            _ => {
+                if state.edit(key) {
+                    state.refresh(cache);
+                }
+            }
+        }
+    }
+}
+
+/// Draw the input, the live preview and the candidate list
+pub fn draw_fix_ui(f: &mut Frame, state: &FixState) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Input
+            Constraint::Length(3), // Preview
+            Constraint::Min(3),    // Candidates
+            Constraint::Length(1), // Help
+        ])
+        .margin(1)
+        .split(f.area());
+
+    let input = Paragraph::new(highlight_command_input(&state.input, &state.highlight))
+        .block(Block::default().borders(Borders::ALL).title("Command"));
+    f.render_widget(input, chunks[0]);
+
+    let preview = match &state.preview {
+        Some(fixed) => Line::from(Span::styled(
+            fixed.clone(),
+            Style::default().fg(Color::Green),
+        )),
+        None if state.input.trim().is_empty() => Line::from(""),
+        None => Line::from(Span::styled(
+            "No correction needed (or none found)",
+            Style::default().fg(Color::DarkGray),
+        )),
+    };
+    let preview_style = if state.selected.is_none() {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    let preview = Paragraph::new(preview).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(preview_style)
+            .title("Corrected"),
+    );
+    f.render_widget(preview, chunks[1]);
+
+    let items: Vec<ListItem> = state
+        .candidates
+        .iter()
+        .map(|candidate| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{} ", format_confidence_bar(candidate.score))),
+                Span::raw(candidate.line.clone()),
+            ]))
+        })
+        .collect();
+    let candidates = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Candidates"))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+    let mut list_state = ListState::default().with_selected(state.selected);
+    f.render_stateful_widget(candidates, chunks[2], &mut list_state);
+
+    let help = Paragraph::new("Enter: Accept and run | Up/Down: Pick candidate | Esc: Quit");
+    f.render_widget(help, chunks[3]);
+
+    let cursor_column = u16::try_from(state.cursor_position).unwrap_or(u16::MAX);
+    f.set_cursor_position((
+        chunks[0].x.saturating_add(cursor_column).saturating_add(1),
+        chunks[0].y + 1,
+    ));
+}
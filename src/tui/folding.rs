@@ -0,0 +1,147 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+/// What delimits a collapsible section of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionKind {
+    /// A `<tag>...</tag>` block such as `<think>`
+    Tag(String),
+    /// A fenced code block, with its info string (e.g. `rust`)
+    CodeBlock(String),
+}
+
+/// A collapsible section of a response, as line indices including its delimiters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Section {
+    /// Get the line shown in place of the section while it is collapsed
+    fn placeholder(&self) -> String {
+        let label = match &self.kind {
+            SectionKind::Tag(tag) if tag == "think" => "Thinking section".to_string(),
+            SectionKind::Tag(tag) => format!("<{tag}> section"),
+            SectionKind::CodeBlock(info) if info.is_empty() => "Code block".to_string(),
+            SectionKind::CodeBlock(info) => format!("{info} code block"),
+        };
+        let lines = self.end - self.start + 1;
+        format!("📝 [{label}, {lines} lines - press F4 to expand] 📝")
+    }
+
+    /// Check whether a line closes this section
+    fn is_closed_by(&self, line: &str) -> bool {
+        match &self.kind {
+            SectionKind::Tag(tag) => line.contains(&format!("</{tag}>")),
+            SectionKind::CodeBlock(_) => line.trim_start().starts_with("```"),
+        }
+    }
+}
+
+/// A response with its collapsed sections replaced by placeholders
+#[derive(Debug, Clone, Default)]
+pub struct FoldedText {
+    pub text: String,
+    /// Index of the section each displayed line belongs to, if any
+    pub line_sections: Vec<Option<usize>>,
+}
+
+/// Find the collapsible sections of a response
+///
+/// Sections are `<tag>...</tag>` blocks for each of `tags` and fenced code blocks.
+/// They don't nest: delimiters inside an open section are treated as its content.
+/// A section that isn't closed yet, e.g. while streaming, runs to the end of the text.
+#[must_use]
+pub fn find_sections(text: &str, tags: &[String]) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut open: Option<Section> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        if let Some(mut section) = open.take() {
+            section.end = index;
+            if section.is_closed_by(line) {
+                sections.push(section);
+            } else {
+                open = Some(section);
+            }
+            continue;
+        }
+
+        let kind = if let Some(info) = line.trim_start().strip_prefix("```") {
+            SectionKind::CodeBlock(info.trim().to_string())
+        } else if let Some(tag) = tags.iter().find(|tag| line.contains(&format!("<{tag}>"))) {
+            SectionKind::Tag(tag.clone())
+        } else {
+            continue;
+        };
+
+        let section = Section {
+            kind,
+            start: index,
+            end: index,
+        };
+        // A tag may open and close on the same line
+        let closes_on_same_line = match &section.kind {
+            SectionKind::Tag(tag) => line.contains(&format!("</{tag}>")),
+            SectionKind::CodeBlock(_) => false,
+        };
+        if closes_on_same_line {
+            sections.push(section);
+        } else {
+            open = Some(section);
+        }
+    }
+
+    sections.extend(open);
+    sections
+}
+
+/// Replace the collapsed sections of a response with a one-line placeholder each
+///
+/// `is_collapsed` is called with the index of each section in `sections`.
+#[must_use]
+pub fn fold_text(
+    text: &str,
+    sections: &[Section],
+    is_collapsed: impl Fn(usize) -> bool,
+) -> FoldedText {
+    let mut folded = FoldedText::default();
+    let mut next_section = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        while sections
+            .get(next_section)
+            .is_some_and(|section| section.end < index)
+        {
+            next_section += 1;
+        }
+
+        let section = sections
+            .get(next_section)
+            .filter(|section| section.start <= index)
+            .map(|_| next_section);
+
+        match section {
+            Some(section_index) if is_collapsed(section_index) => {
+                if index == sections[section_index].start {
+                    folded.text.push_str(&sections[section_index].placeholder());
+                    folded.text.push('\n');
+                    folded.line_sections.push(section);
+                }
+            }
+            _ => {
+                folded.text.push_str(line);
+                folded.text.push('\n');
+                folded.line_sections.push(section);
+            }
+        }
+    }
+
+    // Remove trailing newline if present
+    if folded.text.ends_with('\n') {
+        folded.text.pop();
+    }
+
+    folded
+}
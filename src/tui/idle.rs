@@ -0,0 +1,63 @@
+#![warn(clippy::all, clippy::pedantic)]
+
+//! Idle timeout for the AI prompt, for kiosk-style uses that should exit when left alone
+
+use std::time::{Duration, Instant};
+
+/// Tracks how long the UI has gone without input or streaming activity
+///
+/// The current time is passed in rather than read, so the timer works with any clock.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleTimer {
+    /// How long the UI may stay idle, `None` for no limit
+    timeout: Option<Duration>,
+    /// When the last activity happened
+    last_activity: Instant,
+}
+
+impl IdleTimer {
+    /// Start a timer at `now`; a zero `timeout` never expires
+    #[must_use]
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self {
+            timeout: (!timeout.is_zero()).then_some(timeout),
+            last_activity: now,
+        }
+    }
+
+    /// Restart the timer because something happened at `now`
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Check whether the UI has been idle for the whole timeout at `now`
+    #[must_use]
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.timeout
+            .is_some_and(|timeout| now.saturating_duration_since(self.last_activity) >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer_expires_after_the_timeout_without_activity() {
+        let start = Instant::now();
+        let mut idle = IdleTimer::new(Duration::from_mins(1), start);
+        assert!(!idle.is_expired(start + Duration::from_secs(59)));
+        assert!(idle.is_expired(start + Duration::from_mins(1)));
+
+        idle.record_activity(start + Duration::from_secs(30));
+        assert!(!idle.is_expired(start + Duration::from_mins(1)));
+        assert!(idle.is_expired(start + Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn zero_timeout_never_expires() {
+        let start = Instant::now();
+        let idle = IdleTimer::new(Duration::ZERO, start);
+        assert!(!idle.is_expired(start + Duration::from_hours(24)));
+    }
+}
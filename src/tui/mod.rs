@@ -1,20 +1,28 @@
 #![warn(clippy::all, clippy::pedantic)]
 
-use crate::ollama::{ModelConfig, OllamaClient};
+use crate::ollama::{is_offline_env, AiHistoryLog, ModelConfig, OllamaClient, OFFLINE_MESSAGE};
+use crate::CommandCache;
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseEventKind, MouseButton, EnableMouseCapture, DisableMouseCapture, KeyModifiers};
 use crossterm::terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::execute;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use std::io;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use tokio::sync::mpsc;
 
+pub mod aliases;
 mod app;
+pub mod fix;
+pub mod folding;
+pub mod idle;
 
 pub use app::{TuiApp, draw_ui, ModelState};
+pub use aliases::run_alias_manager;
+pub use fix::run_fix_mode;
+pub use idle::IdleTimer;
 
 /// Messages sent between the UI and background tasks
 pub enum UiMessage {
@@ -38,10 +46,30 @@ pub fn get_openai_client() -> OllamaClient {
 }
 
 /// Run the terminal user interface mode
+///
+/// When `ai_log` is provided, each completed prompt/response pair is appended to it.
+/// When `command_cache` is provided, the first word of the input is highlighted as a
+/// known or misspelled command while typing. `fold_tags` are the `<tag>` sections of
+/// responses that can be collapsed along with fenced code blocks. The TUI exits once
+/// `idle_timeout` passes without input or streaming, unless it's zero.
 /// 
 /// # Errors
 /// Returns an error if the TUI cannot be initialized or if there's an error during execution
-pub async fn run_tui_mode(prompt: &str, use_codestral: bool, model_config: ModelConfig) -> Result<()> {
+pub async fn run_tui_mode(
+    prompt: &str,
+    use_codestral: bool,
+    model_config: ModelConfig,
+    ai_log: Option<AiHistoryLog>,
+    command_cache: Option<CommandCache>,
+    fold_tags: Vec<String>,
+    idle_timeout: Duration,
+) -> Result<()> {
+    // Skip terminal and network setup entirely when AI features are disabled
+    if is_offline_env() {
+        println!("{OFFLINE_MESSAGE}");
+        return Ok(());
+    }
+
     // Initialize terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -69,6 +97,10 @@ pub async fn run_tui_mode(prompt: &str, use_codestral: bool, model_config: Model
         code_model.to_string(),
         tx,
     )?;
+    app.set_ai_log(ai_log);
+    app.set_command_cache(command_cache);
+    app.state.fold_tags = fold_tags;
+    app.state.idle_timeout = idle_timeout;
     
     // Prefill the prompt if provided
     if !prompt.is_empty() {
@@ -102,10 +134,19 @@ pub async fn run_ui(mut app: TuiApp) -> io::Result<()> {
     )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
+    let mut idle = IdleTimer::new(app.state.idle_timeout, Instant::now());
 
     // Main event loop
     loop {
+        if app.state.is_streaming {
+            idle.record_activity(Instant::now());
+        }
+        if idle.is_expired(Instant::now()) {
+            break;
+        }
+
         // Draw UI
+        app.refresh_input_highlight();
         terminal.draw(|_f| {
             let state = app.state.clone();
             if let Err(e) = app.draw(|frame| draw_ui(frame, &state)) {
@@ -115,24 +156,18 @@ pub async fn run_ui(mut app: TuiApp) -> io::Result<()> {
 
         // Handle input events with timeout to allow for streaming updates
         if event::poll(Duration::from_millis(100))? {
+            idle.record_activity(Instant::now());
             if let Event::Key(key) = event::read()? {
                 match key.code {
+                    // The model mini-prompt takes all keys while it is open
+                    _ if app.handle_model_prompt_key(key) => {},
                     KeyCode::Esc => {
                         if app.state.selection_mode {
                             // Exit selection mode if active
                             app.toggle_selection_mode();
                         } else if app.state.is_streaming {
-                            // Cancel the current response if streaming
-                            let cancel_requested = app.get_cancel_requested();
-                            if let Ok(mut guard) = cancel_requested.lock() {
-                                *guard = true;
-                            }
-                            
-                            let cancel_flag = app.get_cancel_flag();
-                            if let Ok(mut guard) = cancel_flag.lock() {
-                                *guard = true;
-                            }
-                            
+                            // Cancel the current response if streaming, aborting its request
+                            app.cancel_stream();
                             app.state.cancel_requested = true;
                             app.state.model_state = ModelState::Complete;
                         } else {
@@ -183,8 +218,22 @@ pub async fn run_ui(mut app: TuiApp) -> io::Result<()> {
                         }
                     },
                     KeyCode::F(1) => {
-                        // F1 toggles thinking sections
-                        app.toggle_thinking_sections();
+                        // F1 collapses or expands all sections
+                        app.toggle_all_sections();
+                    },
+                    KeyCode::F(4) => {
+                        // F4 collapses or expands the section at the scroll position
+                        app.toggle_section_at_scroll();
+                    },
+                    KeyCode::F(2) => {
+                        // F2 picks a model for the next prompt only
+                        app.open_model_prompt();
+                    },
+                    KeyCode::F(3) => {
+                        // F3 toggles mouse capture for terminal-native selection
+                        if let Err(e) = app.toggle_mouse_capture() {
+                            eprintln!("Failed to toggle mouse capture: {e}");
+                        }
                     },
                     KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                         // Ctrl+S toggles selection mode
@@ -341,7 +390,7 @@ pub async fn run_ui(mut app: TuiApp) -> io::Result<()> {
                 );
                 
                 // Count the number of lines in the response text
-                let response_line_count = app.state.response_text.lines().count();
+                let response_line_count = app.state.folded_response().text.lines().count();
                 
                 // Set the maximum scroll value
                 if response_line_count > response_view_height as usize {
@@ -368,10 +417,19 @@ pub async fn run_ui(mut app: TuiApp) -> io::Result<()> {
 /// Run the terminal UI application with message handling
 pub async fn run_app(mut app: TuiApp, rx: mpsc::Receiver<UiMessage>) -> Result<()> {
     let mut message_rx = rx;
+    let mut idle = IdleTimer::new(app.state.idle_timeout, Instant::now());
     
     // Main event loop
     loop {
+        if app.state.is_streaming {
+            idle.record_activity(Instant::now());
+        }
+        if idle.is_expired(Instant::now()) {
+            break;
+        }
+
         // Draw UI
+        app.refresh_input_highlight();
         let state = app.state.clone();
         app.draw(|frame| draw_ui(frame, &state))?;
 
@@ -411,14 +469,18 @@ pub async fn run_app(mut app: TuiApp, rx: mpsc::Receiver<UiMessage>) -> Result<(
                     },
                 }
                 event_received = true;
+                idle.record_activity(Instant::now());
             }
         }
         
         // Then, poll for user input events
         if event::poll(Duration::from_millis(if event_received { 0 } else { 50 }))? {
+            idle.record_activity(Instant::now());
             match event::read()? {
                 Event::Key(key) if key.kind == KeyEventKind::Press => {
                     match key.code {
+                        // The model mini-prompt takes all keys while it is open
+                        _ if app.handle_model_prompt_key(key) => {},
                         KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                             // Select all text (Ctrl+A)
                             if !app.state.is_streaming {
@@ -458,8 +520,20 @@ pub async fn run_app(mut app: TuiApp, rx: mpsc::Receiver<UiMessage>) -> Result<(
                             app.toggle_selection_mode();
                         },
                         KeyCode::F(1) => {
-                            // F1 toggles thinking sections
-                            app.toggle_thinking_sections();
+                            // F1 collapses or expands all sections
+                            app.toggle_all_sections();
+                        },
+                        KeyCode::F(4) => {
+                            // F4 collapses or expands the section at the scroll position
+                            app.toggle_section_at_scroll();
+                        },
+                        KeyCode::F(2) => {
+                            // F2 picks a model for the next prompt only
+                            app.open_model_prompt();
+                        },
+                        KeyCode::F(3) => {
+                            // F3 toggles mouse capture for terminal-native selection
+                            app.toggle_mouse_capture()?;
                         },
                         KeyCode::Char(c) => {
                             // General character handler
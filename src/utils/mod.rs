@@ -1,15 +1,74 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 use log::debug;
-use std::{collections::HashSet, env, fs, path::Path};
-use strsim::normalized_levenshtein;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, env, fmt, fs, path::Path, str::FromStr};
+use strsim::jaro_winkler;
 use walkdir::WalkDir;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+/// Algorithm used to score the similarity between a typo and a candidate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SimilarityAlgorithm {
+    /// Normalized Levenshtein distance with special handling for short strings
+    Levenshtein,
+    /// Normalized Damerau-Levenshtein distance, counting transpositions as one edit,
+    /// with special handling for short strings
+    #[default]
+    DamerauLevenshtein,
+    /// Jaro-Winkler similarity, favoring matching prefixes
+    JaroWinkler,
+    /// Jaccard similarity of character trigrams, fast on long lines and robust to
+    /// reordered words
+    Trigram,
+}
+
+impl fmt::Display for SimilarityAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Levenshtein => "levenshtein",
+            Self::DamerauLevenshtein => "damerau-levenshtein",
+            Self::JaroWinkler => "jaro-winkler",
+            Self::Trigram => "trigram",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for SimilarityAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "levenshtein" => Ok(Self::Levenshtein),
+            "damerau-levenshtein" | "damerau" => Ok(Self::DamerauLevenshtein),
+            "jaro-winkler" | "jaro" => Ok(Self::JaroWinkler),
+            "trigram" | "ngram" => Ok(Self::Trigram),
+            _ => Err(anyhow::anyhow!(
+                "Unknown similarity algorithm '{s}' (expected levenshtein, damerau-levenshtein, jaro-winkler or trigram)"
+            )),
+        }
+    }
+}
+
 /// Find the closest matching string in the given list
 pub fn find_closest_match<'a, S>(query: &str, options: &'a [S], threshold: f64) -> Option<&'a S>
+where
+    S: AsRef<str>,
+{
+    find_closest_match_with(query, options, threshold, SimilarityAlgorithm::default())
+}
+
+/// Find the closest matching string in the given list using a specific algorithm
+pub fn find_closest_match_with<'a, S>(
+    query: &str,
+    options: &'a [S],
+    threshold: f64,
+    algorithm: SimilarityAlgorithm,
+) -> Option<&'a S>
 where
     S: AsRef<str>,
 {
@@ -35,10 +94,16 @@ where
     for option in options {
         // Calculate similarity using our specialized function
         let option_str = option.as_ref();
-        let score = calculate_similarity(&query_lower, option_str);
+        let score = calculate_similarity_with(&query_lower, option_str, algorithm);
+        if score < threshold {
+            continue;
+        }
 
-        if score > best_score && score >= threshold {
-            best_score = score;
+        // Rank by similarity down-weighted for length mismatches, so a short close
+        // match beats a longer candidate that merely shares a prefix
+        let ranked_score = score * length_penalty(&query_lower, option_str);
+        if ranked_score > best_score {
+            best_score = ranked_score;
             best_match = Some(option);
         }
     }
@@ -94,19 +159,99 @@ pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     matrix[s1_len][s2_len]
 }
 
+/// Collapse runs of three or more identical characters to two
+///
+/// Commands rarely contain triple letters, so longer runs are almost always keyboard
+/// stutter (e.g. `giiit`, `statuuus`); legitimate double letters are kept.
+#[must_use]
+pub fn collapse_repeated_chars(s: &str) -> String {
+    let mut collapsed = String::with_capacity(s.len());
+    let mut previous = None;
+    let mut run = 0;
+
+    for c in s.chars() {
+        if Some(c) == previous {
+            run += 1;
+        } else {
+            previous = Some(c);
+            run = 1;
+        }
+
+        if run <= 2 {
+            collapsed.push(c);
+        }
+    }
+
+    collapsed
+}
+
+/// Lowercase a string, unify word separators and collapse stutter before similarity scoring
+///
+/// Dashes, underscores and spaces all become dashes, since tools disagree on the separator
+/// (`docker-compose` vs `docker_compose`) and mixing them up is a predictable typo.
+fn normalize_for_similarity(s: &str) -> String {
+    let unified: String = s
+        .to_lowercase()
+        .chars()
+        .map(|c| if matches!(c, '_' | ' ') { '-' } else { c })
+        .collect();
+    collapse_repeated_chars(&unified)
+}
+
+/// Count the edits turning one string into another, where an edit inserts, deletes or
+/// substitutes a character or swaps two adjacent ones
+///
+/// Unlike plain Levenshtein distance, a transposition like `sttaus` -> `status` is a
+/// single edit rather than two.
+#[must_use]
+pub fn damerau_levenshtein_distance(a: &str, b: &str) -> usize {
+    strsim::damerau_levenshtein(a, b)
+}
+
 /// Calculate similarity between two strings
+///
+/// Matching is case-insensitive, treats `-`, `_` and spaces as the same separator and
+/// ignores keyboard stutter (see [`collapse_repeated_chars`]). Lengths are counted in
+/// characters rather than bytes, so Cyrillic or CJK names are scored like Latin ones.
+/// Strings longer than three characters are compared by their
+/// [`damerau_levenshtein_distance`] relative to the longer one.
 #[must_use]
 pub fn calculate_similarity(a: &str, b: &str) -> f64 {
-    // Handle case insensitivity by converting to lowercase
-    let a_lower = a.to_lowercase();
-    let b_lower = b.to_lowercase();
+    edit_similarity(a, b, damerau_levenshtein_distance, true)
+}
+
+/// Like [`calculate_similarity`], comparing strings by their plain Levenshtein distance,
+/// where a transposition is two edits
+///
+/// Short strings are scored by their distance too: the lenient scoring of
+/// [`calculate_similarity`] forgives any reordering, which is what this algorithm doesn't.
+#[must_use]
+pub fn levenshtein_similarity(a: &str, b: &str) -> f64 {
+    edit_similarity(a, b, levenshtein_distance, false)
+}
+
+/// Score two strings by the edit `distance` relative to the longer one, after
+/// normalizing them and, if `lenient_short_strings` is set, with special handling for
+/// strings of up to three characters
+fn edit_similarity(
+    a: &str,
+    b: &str,
+    distance: fn(&str, &str) -> usize,
+    lenient_short_strings: bool,
+) -> f64 {
+    // Handle case insensitivity and stutter by normalizing both strings
+    let a_lower = normalize_for_similarity(a);
+    let b_lower = normalize_for_similarity(b);
 
     // Use the lowercase strings for comparison
     let a = a_lower.as_str();
     let b = b_lower.as_str();
 
+    let a_len = a.chars().count();
+    let b_len = b.chars().count();
+
     // Handle special cases for very short strings
-    if a.len() <= 3 && b.len() <= 3 {
+    if lenient_short_strings && a_len <= 3 && b_len <= 3 {
         // For very short strings, exact match is best
         if a == b {
             return 1.0;
@@ -130,7 +275,7 @@ pub fn calculate_similarity(a: &str, b: &str) -> f64 {
         }
 
         // Calculate similarity based on matches and length
-        let total = a.len().max(b.len());
+        let total = a_len.max(b_len);
         if total > 0 {
             // Use u32 as an intermediate type to avoid precision loss
             let matches_f64 = f64::from(u32::try_from(matches).unwrap_or(u32::MAX));
@@ -139,12 +284,90 @@ pub fn calculate_similarity(a: &str, b: &str) -> f64 {
         } else {
             0.0
         }
+    } else if a_len.max(b_len) == 0 {
+        1.0
     } else {
-        // For longer strings, use normalized Levenshtein distance
-        normalized_levenshtein(a, b)
+        // For longer strings, use the edit distance relative to the longer string
+        let distance = f64::from(u32::try_from(distance(a, b)).unwrap_or(u32::MAX));
+        let longer = f64::from(u32::try_from(a_len.max(b_len)).unwrap_or(u32::MAX));
+        1.0 - distance / longer
+    }
+}
+
+/// Ranking penalty for a candidate whose length differs from the query
+///
+/// Returns the square root of the shorter-to-longer length ratio (in characters),
+/// so equal lengths are not penalized and large mismatches are down-weighted.
+#[must_use]
+pub fn length_penalty(a: &str, b: &str) -> f64 {
+    let a_len = a.chars().count();
+    let b_len = b.chars().count();
+    let longer = a_len.max(b_len);
+    if longer == 0 {
+        return 1.0;
+    }
+
+    let shorter_f64 = f64::from(u32::try_from(a_len.min(b_len)).unwrap_or(u32::MAX));
+    let longer_f64 = f64::from(u32::try_from(longer).unwrap_or(u32::MAX));
+    (shorter_f64 / longer_f64).sqrt()
+}
+
+/// Default weight given to acceptance frequency when ranking correction candidates
+pub const FREQUENCY_WEIGHT: f64 = 0.2;
+
+/// Blend a similarity score with how often a correction has been accepted
+///
+/// The score is `(1 - weight) * similarity + weight * frequency / (frequency + 1)`.
+/// The frequency term saturates towards 1, so with a small `weight` frequency only
+/// reorders candidates whose similarities are already close to each other.
+///
+/// # Arguments
+///
+/// * `similarity` - Similarity between the query and the candidate (0.0 to 1.0)
+/// * `frequency` - Number of times the candidate has been accepted as a correction
+/// * `weight` - How much the frequency term contributes (0.0 to 1.0)
+#[must_use]
+pub fn combined_score(similarity: f64, frequency: usize, weight: f64) -> f64 {
+    let weight = weight.clamp(0.0, 1.0);
+    let frequency = f64::from(u32::try_from(frequency).unwrap_or(u32::MAX));
+    let frequency_term = frequency / (frequency + 1.0);
+
+    (1.0 - weight) * similarity + weight * frequency_term
+}
+
+/// Calculate similarity between two strings using a specific algorithm
+#[must_use]
+pub fn calculate_similarity_with(a: &str, b: &str, algorithm: SimilarityAlgorithm) -> f64 {
+    match algorithm {
+        SimilarityAlgorithm::Levenshtein => levenshtein_similarity(a, b),
+        SimilarityAlgorithm::DamerauLevenshtein => calculate_similarity(a, b),
+        SimilarityAlgorithm::JaroWinkler => {
+            jaro_winkler(&normalize_for_similarity(a), &normalize_for_similarity(b))
+        }
+        SimilarityAlgorithm::Trigram => trigram_similarity(a, b),
     }
 }
 
+/// Calculate the Jaccard similarity of two strings' character trigrams
+///
+/// Each string is normalized like [`calculate_similarity`] and padded with spaces, so
+/// short strings and word boundaries still yield trigrams. Cost grows linearly with
+/// length, unlike edit distances, and reordered words keep most of their trigrams.
+#[must_use]
+pub fn trigram_similarity(a: &str, b: &str) -> f64 {
+    let trigrams = |s: &str| -> HashSet<[char; 3]> {
+        let padded: Vec<char> = format!("  {} ", normalize_for_similarity(s)).chars().collect();
+        padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+    };
+    let (a, b) = (trigrams(a), trigrams(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    let shared = u32::try_from(a.intersection(&b).count()).unwrap_or(u32::MAX);
+    f64::from(shared) / f64::from(u32::try_from(union).unwrap_or(u32::MAX))
+}
+
 /// Checks if a file is executable on the current platform
 ///
 /// # Arguments
@@ -191,72 +414,89 @@ pub fn is_executable(path: &Path) -> bool {
     }
 }
 
+/// Check whether a command is runnable from PATH without loading the command cache
+///
+/// This is a lightweight alternative to `CommandCache::command_exists` for quick
+/// yes/no checks, e.g. before executing a corrected command.
+#[must_use]
+pub fn path_has_command(name: &str) -> bool {
+    which::which(name).is_ok()
+}
+
 /// Get all commands from the PATH environment variable
+///
+/// The names symlinks point to are included too; see [`get_path_commands_with`].
+#[must_use]
 pub fn get_path_commands() -> HashSet<String> {
-    let mut commands = HashSet::new();
+    get_path_commands_with(true)
+}
 
-    // Get all directories in PATH
-    if let Some(path) = env::var_os("PATH") {
-        for dir in env::split_paths(&path) {
-            if dir.exists() {
-                for entry in WalkDir::new(dir)
-                    .max_depth(1)
-                    .into_iter()
-                    .filter_map(Result::ok)
+/// Add the executables in `dirs` to `commands`, optionally with the names their symlinks
+/// resolve to, see [`get_path_commands_with`]
+pub(crate) fn add_dir_commands(
+    dirs: impl IntoIterator<Item = std::path::PathBuf>,
+    include_symlink_targets: bool,
+    commands: &mut HashSet<String>,
+) {
+    for dir in dirs {
+        if dir.exists() {
+            for entry in WalkDir::new(dir)
+                .max_depth(1)
+                .into_iter()
+                .filter_map(Result::ok)
+            {
+                if (entry.file_type().is_file() || entry.file_type().is_symlink())
+                    && is_executable(entry.path())
                 {
-                    if (entry.file_type().is_file() || entry.file_type().is_symlink())
-                        && is_executable(entry.path())
-                    {
-                        if let Some(name) = entry.file_name().to_str() {
-                            commands.insert(name.to_string());
-
-                            // If this is a symlink, follow it and add target name
-                            #[cfg(unix)]
-                            if entry.file_type().is_symlink() {
-                                let mut current_path = entry.path().to_path_buf();
-                                let mut seen_paths = HashSet::new();
-
-                                // Follow symlink chain to handle multiple levels
-                                while current_path.is_symlink() {
-                                    // Add the current path to our seen paths set to detect cycles
-                                    if !seen_paths.insert(current_path.clone()) {
-                                        // Circular symlink detected, stop here
-                                        debug!("Circular symlink detected: {:?}", current_path);
-                                        break;
-                                    }
+                    if let Some(name) = entry.file_name().to_str() {
+                        commands.insert(name.to_string());
+
+                        // If this is a symlink, follow it and add target name
+                        #[cfg(unix)]
+                        if include_symlink_targets && entry.file_type().is_symlink() {
+                            let mut current_path = entry.path().to_path_buf();
+                            let mut seen_paths = HashSet::new();
+
+                            // Follow symlink chain to handle multiple levels
+                            while current_path.is_symlink() {
+                                // Add the current path to our seen paths set to detect cycles
+                                if !seen_paths.insert(current_path.clone()) {
+                                    // Circular symlink detected, stop here
+                                    debug!("Circular symlink detected: {:?}", current_path);
+                                    break;
+                                }
 
-                                    match fs::read_link(&current_path) {
-                                        Ok(target) => {
-                                            // Resolve the target path, making it absolute if needed
-                                            current_path = if target.is_absolute() {
-                                                target
+                                match fs::read_link(&current_path) {
+                                    Ok(target) => {
+                                        // Resolve the target path, making it absolute if needed
+                                        current_path = if target.is_absolute() {
+                                            target
+                                        } else {
+                                            // Relative paths are relative to the directory containing the symlink
+                                            if let Some(parent) = current_path.parent() {
+                                                parent.join(&target)
                                             } else {
-                                                // Relative paths are relative to the directory containing the symlink
-                                                if let Some(parent) = current_path.parent() {
-                                                    parent.join(&target)
-                                                } else {
-                                                    target
-                                                }
-                                            };
-
-                                            // Extract the command name from the resolved path
-                                            if let Some(target_name) = current_path.file_name() {
-                                                if let Some(name) = target_name.to_str() {
-                                                    commands.insert(name.to_string());
-                                                    debug!("Added symlink target: {}", name);
-                                                }
+                                                target
+                                            }
+                                        };
+
+                                        // Extract the command name from the resolved path
+                                        if let Some(target_name) = current_path.file_name() {
+                                            if let Some(name) = target_name.to_str() {
+                                                commands.insert(name.to_string());
+                                                debug!("Added symlink target: {}", name);
                                             }
-                                        }
-                                        Err(e) => {
-                                            // Log errors but continue processing
-                                            debug!(
-                                                "Error following symlink {}: {}",
-                                                current_path.display(),
-                                                e
-                                            );
-                                            break;
                                         }
                                     }
+                                    Err(e) => {
+                                        // Log errors but continue processing
+                                        debug!(
+                                            "Error following symlink {}: {}",
+                                            current_path.display(),
+                                            e
+                                        );
+                                        break;
+                                    }
                                 }
                             }
                         }
@@ -265,6 +505,20 @@ pub fn get_path_commands() -> HashSet<String> {
             }
         }
     }
+}
+
+/// Get all commands from the PATH environment variable, optionally adding the names
+/// symlinks in it resolve to
+///
+/// e.g. with `include_symlink_targets`, busybox applets add `busybox` itself.
+#[must_use]
+pub fn get_path_commands_with(include_symlink_targets: bool) -> HashSet<String> {
+    let mut commands = HashSet::new();
+
+    // Get all directories in PATH
+    if let Some(path) = env::var_os("PATH") {
+        add_dir_commands(env::split_paths(&path), include_symlink_targets, &mut commands);
+    }
 
     // Add Python scripts from Python directories
     for python_cmd in ["python", "python3"] {
@@ -297,6 +551,56 @@ pub fn get_path_commands() -> HashSet<String> {
     commands
 }
 
+/// Man page sections that document commands: user commands and system administration
+pub const MAN_COMMAND_SECTIONS: &[&str] = &["1", "8"];
+
+/// Default man page directories, used when `MANPATH` is unset
+const DEFAULT_MAN_DIRS: &[&str] = &["/usr/share/man", "/usr/local/share/man"];
+
+/// Get the command names documented by installed man pages in sections 1 and 8
+///
+/// Scans `man1`/`man8` under each `MANPATH` directory (or the usual system ones), so
+/// commands that are installed but not in the current PATH are found too. File names
+/// like `git-commit.1.gz` give `git-commit`.
+#[must_use]
+pub fn get_man_page_commands() -> HashSet<String> {
+    let man_dirs: Vec<std::path::PathBuf> = env::var_os("MANPATH")
+        .filter(|manpath| !manpath.is_empty())
+        .map_or_else(
+            || DEFAULT_MAN_DIRS.iter().map(std::path::PathBuf::from).collect(),
+            |manpath| env::split_paths(&manpath).collect(),
+        );
+
+    let mut commands = HashSet::new();
+    for man_dir in &man_dirs {
+        for section in MAN_COMMAND_SECTIONS {
+            let Ok(entries) = fs::read_dir(man_dir.join(format!("man{section}"))) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                if let Some(name) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|file_name| man_page_command(file_name, section))
+                {
+                    commands.insert(name.to_string());
+                }
+            }
+        }
+    }
+    commands
+}
+
+/// Get the command a man page file documents, e.g. `ls.1.gz` -> `ls` in section `1`
+fn man_page_command<'a>(file_name: &'a str, section: &str) -> Option<&'a str> {
+    let uncompressed = [".gz", ".bz2", ".xz", ".zst"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .unwrap_or(file_name);
+    let (name, page_section) = uncompressed.rsplit_once('.')?;
+    (!name.is_empty() && page_section.starts_with(section)).then_some(name)
+}
+
 /// Remove trailing flags from an argument
 /// e.g. "file.txt:10" -> ("file.txt", ":10")
 #[must_use]
@@ -309,3 +613,101 @@ pub fn remove_trailing_flags(arg: &str) -> (&str, String) {
 
     (arg, String::new())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpositions_are_one_edit_by_default() {
+        assert_eq!(
+            SimilarityAlgorithm::default(),
+            SimilarityAlgorithm::DamerauLevenshtein
+        );
+        assert!((calculate_similarity("gti", "git") - 0.9).abs() < f64::EPSILON);
+        assert!((calculate_similarity("teh", "the") - 1.0).abs() < f64::EPSILON);
+        assert!((calculate_similarity("recieve", "receive") - 6.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn non_latin_names_are_scored_by_characters() {
+        let algorithms = [
+            SimilarityAlgorithm::Levenshtein,
+            SimilarityAlgorithm::DamerauLevenshtein,
+            SimilarityAlgorithm::JaroWinkler,
+            SimilarityAlgorithm::Trigram,
+        ];
+        for algorithm in algorithms {
+            for (a, b) in [("гит", "гти"), ("日本語", "日本"), ("e\u{301}cho", "écho"), ("", "ё")] {
+                let score = calculate_similarity_with(a, b, algorithm);
+                assert!((0.0..=1.0).contains(&score), "{algorithm}: {a} vs {b}");
+            }
+        }
+
+        assert!((calculate_similarity("сборка", "сброка") - 5.0 / 6.0).abs() < 1e-9);
+        let commands = ["сборка", "запуск", "日本語ツール", "ls"];
+        assert_eq!(find_closest_match("сброка", &commands, 0.6), Some(&"сборка"));
+        assert_eq!(find_closest_match("日本語ツル", &commands, 0.6), Some(&"日本語ツール"));
+        assert_eq!(find_closest_match("запуск", &commands, 0.6), Some(&"запуск"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_targets_are_added_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let (bin, libexec) = (dir.path().join("bin"), dir.path().join("libexec"));
+        fs::create_dir(&bin).unwrap();
+        fs::create_dir(&libexec).unwrap();
+        let busybox = libexec.join("busybox");
+        fs::write(&busybox, "").unwrap();
+        fs::set_permissions(&busybox, fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("../libexec/busybox", bin.join("ls")).unwrap();
+
+        let commands = |include_symlink_targets| {
+            let mut commands = HashSet::new();
+            add_dir_commands([bin.clone()], include_symlink_targets, &mut commands);
+            let mut commands: Vec<String> = commands.into_iter().collect();
+            commands.sort();
+            commands
+        };
+        assert_eq!(commands(true), ["busybox", "ls"]);
+        assert_eq!(commands(false), ["ls"]);
+    }
+
+    #[test]
+    fn levenshtein_counts_transpositions_as_two_edits() {
+        let score = |a, b| calculate_similarity_with(a, b, SimilarityAlgorithm::Levenshtein);
+        assert!((score("gti", "git") - 1.0 / 3.0).abs() < 1e-9);
+        assert!((score("teh", "the") - 1.0 / 3.0).abs() < 1e-9);
+        assert!((score("recieve", "receive") - 5.0 / 7.0).abs() < 1e-9);
+        assert!((score("git", "git") - 1.0).abs() < f64::EPSILON);
+        assert!((score("", "") - 1.0).abs() < f64::EPSILON);
+
+        for (typo, command) in [("gti", "git"), ("teh", "the"), ("recieve", "receive")] {
+            let damerau =
+                calculate_similarity_with(typo, command, SimilarityAlgorithm::DamerauLevenshtein);
+            assert!(damerau > score(typo, command), "{typo} -> {command}");
+        }
+    }
+
+    #[test]
+    fn length_penalty_favours_candidates_of_similar_length() {
+        assert!((length_penalty("git", "git") - 1.0).abs() < f64::EPSILON);
+        assert!(length_penalty("gi", "git") > length_penalty("gi", "gitweb"));
+        assert!((length_penalty("", "") - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn frequency_reorders_near_equal_candidates() {
+        assert!(combined_score(0.80, 5, 0.2) > combined_score(0.82, 0, 0.2));
+        assert!(combined_score(0.50, 100, 0.2) < combined_score(0.95, 0, 0.2));
+        assert!((combined_score(0.7, 9, 0.0) - 0.7).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn path_has_command_finds_runnable_commands_only() {
+        assert!(path_has_command("ls"));
+        assert!(!path_has_command("super-snoofer-no-such-command"));
+    }
+}